@@ -20,72 +20,27 @@ fn main() {
 
 	let mut ds = udf::Dataset::new();
 	ds.header.id = *b"OBJ\0";
-	for name in ["c", "gn", "g", "v", "vn", "vt", "fv", "fvt", "fvn"] {
-		ds.names.add(name, udf::hash(name));
-	}
 
-	ds.add_table(udf::TableRef {
-		key_name: udf::hash!("c"),
-		data: object.comment.as_data_ref(),
-		..Default::default()
-	});
+	ds.insert("c", object.comment.as_data_ref());
 	if object.g.len() > 0 {
-		ds.add_table(udf::TableRef {
-			key_name: udf::hash!("gn"),
-			data: udf::build_string_array_utf8(object.gn.iter().map(|s| s.as_str()), &mut Vec::new()),
-			..Default::default()
-		});
-		ds.add_table(udf::TableRef {
-			key_name: udf::hash!("g"),
-			data: object.g.as_data_ref(),
-			index_name: udf::hash!("v"),
-			related_name: udf::hash!("gn"),
-			..Default::default()
-		});
+		let mut storage = Vec::new();
+		ds.insert("gn", udf::build_string_array_utf8(object.gn.iter().map(|s| s.as_str()), &mut storage));
+		ds.insert("g", object.g.as_data_ref()).index("v").related("gn");
 	}
 
-	ds.add_table(udf::TableRef {
-		key_name: udf::hash!("v"),
-		data: object.v.as_data_ref(),
-		..Default::default()
-	});
+	ds.insert("v", object.v.as_data_ref());
 	if object.vt.len() > 0 {
-		ds.add_table(udf::TableRef {
-			key_name: udf::hash!("vt"),
-			data: object.vt.as_data_ref(),
-			..Default::default()
-		});
+		ds.insert("vt", object.vt.as_data_ref());
 	}
 	if object.vn.len() > 0 {
-		ds.add_table(udf::TableRef {
-			key_name: udf::hash!("vn"),
-			data: object.vn.as_data_ref(),
-			..Default::default()
-		});
+		ds.insert("vn", object.vn.as_data_ref());
 	}
-	ds.add_table(udf::TableRef {
-		key_name: udf::hash!("fv"),
-		data: object.fv.as_data_ref(),
-		index_name: udf::hash!("v"),
-		..Default::default()
-	});
+	ds.insert("fv", object.fv.as_data_ref()).index("v");
 	if object.fvt.len() > 0 {
-		ds.add_table(udf::TableRef {
-			key_name: udf::hash!("fvt"),
-			data: object.fvt.as_data_ref(),
-			index_name: udf::hash!("vt"),
-			related_name: udf::hash!("fv"),
-			..Default::default()
-		});
+		ds.insert("fvt", object.fvt.as_data_ref()).index("vt").related("fv");
 	}
 	if object.fvn.len() > 0 {
-		ds.add_table(udf::TableRef {
-			key_name: udf::hash!("fvn"),
-			data: object.fvn.as_data_ref(),
-			index_name: udf::hash!("vn"),
-			related_name: udf::hash!("fv"),
-			..Default::default()
-		});
+		ds.insert("fvn", object.fvn.as_data_ref()).index("vn").related("fv");
 	}
 	let fo = udf.add_dataset(&ds.finalize()).unwrap();
 
@@ -0,0 +1,46 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const AFTER_HELP: &str = "\
+	Only the datasets reachable from the root dataset are copied to the output file.\n\
+	The output file is replaced once the copy is complete, it may be the UDF file itself.\n\
+";
+
+pub struct Options<'a> {
+	pub file: &'a Path,
+	pub output: &'a Path,
+}
+
+pub fn run(opts: &Options) {
+	let mut file = expect!(
+		udf::FileIO::open(opts.file),
+		"Open UDF file='"{opts.file.display()}"'");
+
+	// Copy to a temporary file next to the output, the output may be the UDF file being compacted
+	let mut temp = opts.output.as_os_str().to_owned();
+	temp.push(".tmp");
+	let temp = PathBuf::from(temp);
+
+	let mut output = expect!(
+		udf::FileIO::create(&temp, file.id()),
+		"Create UDF file='"{temp.display()}"'");
+
+	let stats = match udf::compact(&mut file, &mut output) {
+		Ok(stats) => stats,
+		Err(err) => {
+			drop(output);
+			let _ = fs::remove_file(&temp);
+			crate::macros::exit_error(Some(&err), &fmtools::fmt!("Compact UDF file='"{opts.file.display()}"'"));
+		},
+	};
+
+	// Close both files before replacing the output
+	drop(output);
+	drop(file);
+	expect!(
+		fs::rename(&temp, opts.output),
+		"Replace UDF file='"{opts.output.display()}"'");
+
+	println!("Copied {} datasets", stats.datasets);
+	println!("Reclaimed {} ({} to {})", udf::FileSize(stats.reclaimed()), udf::FileSize(stats.old_size), udf::FileSize(stats.new_size));
+}
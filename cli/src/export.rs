@@ -1,5 +1,6 @@
 use std::{fs, str};
 use std::io::{self, Write};
+use std::ops;
 use std::path::Path;
 
 #[derive(Copy, Clone, Debug)]
@@ -10,6 +11,8 @@ pub enum Format {
 	Npy,
 	/// Print array format.
 	Print,
+	/// Comma separated values of the related tables side by side.
+	Csv,
 }
 impl Default for Format {
 	fn default() -> Self {
@@ -23,6 +26,7 @@ impl str::FromStr for Format {
 			"raw" => Ok(Format::Raw),
 			"npy" => Ok(Format::Npy),
 			"print" => Ok(Format::Print),
+			"csv" => Ok(Format::Csv),
 			_ => Err(udf::ParseError::InvalidFormat),
 		}
 	}
@@ -42,110 +46,101 @@ pub fn run(opts: &Options) {
 		udf::FileIO::open(opts.file),
 		"Open UDF file='"{opts.file.display()}"'");
 
-	let mut fo = opts.file_offset.unwrap_or_else(|| file.root());
-	let mut path = opts.path;
+	let fo = opts.file_offset.unwrap_or_else(|| file.root());
 
-	loop {
-		if opts.verbose {
-			println!("{}", fo);
-		}
-		let dataset = file.read_dataset(fo).unwrap();
-		let dataset = dataset.as_ref();
-		let names = dataset.names;
-
-		if path.is_empty() {
-			let output_dir = Path::new(opts.output);
-			expect!(std::fs::create_dir(output_dir),
-				"Create output at '"{output_dir.display()}"'");
-
-			let mut ini = String::new();
-			{
-				use std::fmt::Write;
-				let _ = write!(ini, "Id={}\n", udf::PrintId(dataset.header.id));
-				let _ = write!(ini, "Names={}\n", crate::Fmt(move |f| {
-					let mut comma = false;
-					for name in names.names().filter_map(|name| name) {
-						if comma {
-							f.write_str(",")?;
-						}
-						comma = true;
-						f.write_str(name)?;
-					}
-					Ok(())
-				}));
-			}
+	let matches = expect!(
+		file.resolve_all(fo, opts.path),
+		"Resolve path="{opts.path:?}"");
+	if matches.is_empty() {
+		error!("Nothing matches path="{opts.path:?});
+	}
 
-			for table in dataset.tables {
-				let key_name = udf::NameOrHash(names.lookup(table.key_name));
+	// Multiple matches are exported to a directory named after their paths
+	let output = Path::new(opts.output);
+	if matches.len() > 1 {
+		expect!(fs::create_dir(output),
+			"Create output at '"{output.display()}"'");
+	}
 
-				if let Err(err) = export_table(opts, &dataset, &names, table, Some(key_name), &mut ini) {
-					eprintln!("Error exporting {}: {}", key_name, err);
-					continue;
-				}
+	for resolved in &matches {
+		if opts.verbose {
+			for (fo, _) in resolved.datasets() {
+				println!("{}", fo);
 			}
+		}
+		if matches.len() > 1 {
+			export_resolved(opts, &output.join(resolved.path()), resolved);
+		}
+		else {
+			export_resolved(opts, output, resolved);
+		}
+	}
+}
 
-			// Write the Dataset.ini summary
-			expect!(fs::write(output_dir.join("Dataset.ini"), ini), "Error writing Dataset.ini");
+fn export_resolved(opts: &Options, output: &Path, resolved: &udf::Resolved) {
+	let dataset = resolved.dataset();
+	let names = dataset.names;
 
-			println!("Exported {:?} to {}", opts.path, Path::new(opts.output).display());
-			return;
-		}
+	if let Some(table) = resolved.table() {
+		let mut desc = String::new();
+		expect!(export_table(opts, output, &dataset, table, resolved.rows(), None, &mut desc), "Export "{resolved.path()});
+		print!("{}", desc);
+		return;
+	}
 
-		match udf::PathEl::parse(&mut path) {
-			Ok(udf::PathEl::Dir { name, index }) => {
-				let table = match names.find(name).and_then(|hash| dataset.find_table(hash)) {
-					Some(a) => a,
-					None => break eprintln!("Dataset does not have a table named {name:?}!"),
-				};
+	expect!(fs::create_dir(output),
+		"Create output at '"{output.display()}"'");
 
-				let data_ref = dataset.get_data_ref(table).unwrap();
-				if data_ref.type_info & (udf::format::TYPE_HINT_MASK | udf::format::TYPE_PRIM_MASK) == udf::format::TYPE_HINT_DATASET | udf::format::TYPE_PRIM_U64 {
-					let fos = data_ref.as_slice::<udf::format::FileOffset>().expect("dataset is malformed");
+	let mut ini = String::new();
+	{
+		use std::fmt::Write;
+		let _ = write!(ini, "Id={}\n", udf::PrintId(dataset.header.id));
+		let _ = write!(ini, "Names={}\n", crate::Fmt(move |f| {
+			let mut comma = false;
+			for name in names.names().filter_map(|name| name) {
+				if comma {
+					f.write_str(",")?;
+				}
+				comma = true;
+				f.write_str(name)?;
+			}
+			Ok(())
+		}));
+	}
 
-					fo = match fos.get(index as usize) {
-						Some(&fo) => fo,
-						None => break eprintln!(""),
-					};
+	for table in dataset.tables {
+		let key_name = udf::NameOrHash(names.lookup(table.key_name));
+
+		// Related tables are exported together with the first table of their chain
+		if let Format::Csv = opts.format {
+			if let Ok(related) = dataset.related_key(table.key_name) {
+				if related.tables().next().map(|first| first.key_name) != Some(table.key_name) {
 					continue;
 				}
-				else {
-					break eprintln!("The path does not refer to a dataset table!");
-				}
-				#[allow(unreachable_code)]
-				{ unreachable!() }
-			},
-			Ok(udf::PathEl::Name(name)) => {
-				if !path.is_empty() {
-					break eprintln!("The path is malformed");
-				}
+			}
+		}
 
-				let table = match names.find(name).and_then(|hash| dataset.find_table(hash)) {
-					Some(a) => a,
-					None => break eprintln!("Dataset does not have a table named {name:?}!"),
-				};
-
-				let mut desc = String::new();
-				export_table(opts, &dataset, &names, table, None, &mut desc).unwrap();
-				print!("{}", desc);
-				break;
-			},
-			Err(_err) => {
-				break eprintln!("The path is malformed");
-			},
+		if let Err(err) = export_table(opts, output, &dataset, table, None, Some(key_name), &mut ini) {
+			eprintln!("Error exporting {}: {}", key_name, err);
+			continue;
 		}
 	}
+
+	// Write the Dataset.ini summary
+	expect!(fs::write(output.join("Dataset.ini"), ini), "Error writing Dataset.ini");
+
+	println!("Exported {:?} to {}", resolved.path(), output.display());
 }
 
-fn export_table(opts: &Options, ds: &udf::DatasetRef<'_>, names: &udf::NamesRef<'_>, table: &udf::format::TableDesc, name: Option<udf::NameOrHash>, desc: &mut String) -> io::Result<()> {
-	let data = match ds.get_data_ref(table) {
-		Some(data) => data,
-		None => panic!("Unable to retrieve {:?}'s data", name),
-	};
+fn export_table(opts: &Options, output: &Path, dataset: &udf::DatasetRef<'_>, table: &udf::format::TableDesc, rows: Option<ops::Range<u32>>, name: Option<udf::NameOrHash>, desc: &mut String) -> io::Result<()> {
+	let names = &dataset.names;
 
-	// Decompress the data or fail
+	let Some(data) = dataset.get_data_ref(table) else {
+		return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "table data out of bounds"));
+	};
 	let mut storage = Vec::new();
 	let data = data.decompress(&mut storage);
-
+	// The data must be decompressed
 	if data.compress_info != udf::format::COMPRESS_NONE {
 		panic!("Decompression failed, cannot export compressed data");
 	}
@@ -156,32 +151,43 @@ fn export_table(opts: &Options, ds: &udf::DatasetRef<'_>, names: &udf::NamesRef<
 		Format::Raw => {
 			path = match name {
 				Some(name) => {
-					path_buf = Path::new(opts.output).join(name.to_string());
+					path_buf = output.join(name.to_string());
 					&path_buf
 				},
 				None => {
-					Path::new(opts.output)
+					output
 				},
 			};
 
+			let data = match rows {
+				Some(rows) => data.rows(rows).ok_or_else(rows_out_of_bounds)?,
+				None => data,
+			};
+
 			let mut fd = fs::File::create(path)?;
 			fd.write_all(data.bytes)?;
 		},
 		Format::Npy => {
 			// Figure out the descr and shape for the data array
-			let (descr, shape) = match data.type_info & udf::format::TYPE_PRIM_MASK {
-				udf::format::TYPE_PRIM_U8 => ("|u1", data.shape),
-				udf::format::TYPE_PRIM_I8 => ("|i1", data.shape),
-				udf::format::TYPE_PRIM_U16 => ("<u2", data.shape),
-				udf::format::TYPE_PRIM_I16 => ("<i2", data.shape),
-				udf::format::TYPE_PRIM_U32 => ("<u4", data.shape),
-				udf::format::TYPE_PRIM_I32 => ("<i4", data.shape),
-				udf::format::TYPE_PRIM_U64 => ("<u8", data.shape),
-				udf::format::TYPE_PRIM_I64 => ("<i8", data.shape),
-				udf::format::TYPE_PRIM_F32 => ("<f4", data.shape),
-				udf::format::TYPE_PRIM_F64 => ("<f8", data.shape),
+			let (descr, (shape, bytes)) = match data.type_info & udf::format::TYPE_PRIM_MASK {
+				udf::format::TYPE_PRIM_U8 => ("|u1", view_rows::<u8>(&data, rows)?),
+				udf::format::TYPE_PRIM_I8 => ("|i1", view_rows::<i8>(&data, rows)?),
+				udf::format::TYPE_PRIM_U16 => ("<u2", view_rows::<u16>(&data, rows)?),
+				udf::format::TYPE_PRIM_I16 => ("<i2", view_rows::<i16>(&data, rows)?),
+				udf::format::TYPE_PRIM_U32 => ("<u4", view_rows::<u32>(&data, rows)?),
+				udf::format::TYPE_PRIM_I32 => ("<i4", view_rows::<i32>(&data, rows)?),
+				udf::format::TYPE_PRIM_U64 => ("<u8", view_rows::<u64>(&data, rows)?),
+				udf::format::TYPE_PRIM_I64 => ("<i8", view_rows::<i64>(&data, rows)?),
+				udf::format::TYPE_PRIM_F32 => ("<f4", view_rows::<f32>(&data, rows)?),
+				udf::format::TYPE_PRIM_F64 => ("<f8", view_rows::<f64>(&data, rows)?),
 				// Fall back to dumping the array as bytes
-				_ => ("|u1", udf::Shape::D1(data.bytes.len() as u32)),
+				_ => {
+					let data = match rows {
+						Some(rows) => data.rows(rows).ok_or_else(rows_out_of_bounds)?,
+						None => data,
+					};
+					("|u1", (udf::Shape::D1(data.bytes.len() as u32), data.bytes))
+				},
 			};
 
 			// Formatter for numpy's shape
@@ -212,11 +218,11 @@ fn export_table(opts: &Options, ds: &udf::DatasetRef<'_>, names: &udf::NamesRef<
 			// Figure out the file name
 			path = match name {
 				Some(file_name) => {
-					path_buf = Path::new(opts.output).join(format!("{}.npy", file_name));
+					path_buf = output.join(format!("{}.npy", file_name));
 					&path_buf
 				},
 				None => {
-					Path::new(opts.output)
+					output
 				},
 			};
 
@@ -224,9 +230,33 @@ fn export_table(opts: &Options, ds: &udf::DatasetRef<'_>, names: &udf::NamesRef<
 			let mut fd = fs::File::create(path)?;
 			fd.write_all(&magic)?;
 			fd.write_all(header.as_bytes())?;
-			fd.write_all(data.bytes)?;
+			fd.write_all(bytes)?;
 		},
 		Format::Print => unimplemented!(),
+		Format::Csv => {
+			path = match name {
+				Some(file_name) => {
+					path_buf = output.join(format!("{}.csv", file_name));
+					&path_buf
+				},
+				None => {
+					output
+				},
+			};
+
+			let related = dataset.related_key(table.key_name)?;
+			// Only numeric data can be exported
+			if (0..related.tables().count()).any(|column| related.data(column).is_none_or(|data| data.print().is_err())) {
+				return Err(io::Error::new(io::ErrorKind::InvalidData, "cannot export non-numeric data as csv"));
+			}
+			let rows = rows.unwrap_or(0..related.len() as u32);
+			if rows.start > rows.end || rows.end as usize > related.len() {
+				return Err(rows_out_of_bounds());
+			}
+			let mut fd = io::BufWriter::new(fs::File::create(path)?);
+			write_csv(&mut fd, names, &related, rows)?;
+			fd.flush()?;
+		},
 	}
 
 	// Write descriptor
@@ -238,7 +268,7 @@ fn export_table(opts: &Options, ds: &udf::DatasetRef<'_>, names: &udf::NamesRef<
 			"CompressInfo="{table.compress_info}"\n"
 		}
 		"Shape="{udf::Shape::from_shape(table.type_info, table.data_shape)}"\n"
-		"Source=" match opts.format { Format::Raw => "raw", Format::Npy => "npy", Format::Print => "print" }"\n"
+		"Source=" match opts.format { Format::Raw => "raw", Format::Npy => "npy", Format::Print => "print", Format::Csv => "csv" }"\n"
 		"FilePath="{path.file_name().unwrap().to_string_lossy()}"\n"
 		if table.index_name != 0 {
 			"IndexName="{udf::NameOrHash(names.lookup(table.index_name))}"\n"
@@ -250,3 +280,83 @@ fn export_table(opts: &Options, ds: &udf::DatasetRef<'_>, names: &udf::NamesRef<
 
 	Ok(())
 }
+
+// Writes the rows of the related tables, tables with multiple elements per row span multiple columns
+fn write_csv(f: &mut dyn Write, names: &udf::NamesRef<'_>, related: &udf::Related<'_>, rows: ops::Range<u32>) -> io::Result<()> {
+	let row_len = related.len().max(1);
+	let widths: Vec<usize> = (0..related.tables().count()).map(|column| related.data(column).map_or(0, |data| data.len() / row_len)).collect();
+
+	let mut comma = false;
+	for (table, &width) in related.tables().zip(&widths) {
+		let name = udf::NameOrHash(names.lookup(table.key_name)).to_string();
+		for index in 0..width {
+			let field = if width == 1 { name.clone() } else { format!("{}[{}]", name, index) };
+			write!(f, "{}{}", if comma { "," } else { "" }, CsvField(&field))?;
+			comma = true;
+		}
+	}
+	writeln!(f)?;
+
+	for index in rows {
+		let mut comma = false;
+		for column in 0..widths.len() {
+			let Some(data) = related.data(column) else { continue };
+			match data.type_info & udf::format::TYPE_PRIM_MASK {
+				udf::format::TYPE_PRIM_U8 => write_fields(f, data.view::<u8>(), index as usize, &mut comma)?,
+				udf::format::TYPE_PRIM_I8 => write_fields(f, data.view::<i8>(), index as usize, &mut comma)?,
+				udf::format::TYPE_PRIM_U16 => write_fields(f, data.view::<u16>(), index as usize, &mut comma)?,
+				udf::format::TYPE_PRIM_I16 => write_fields(f, data.view::<i16>(), index as usize, &mut comma)?,
+				udf::format::TYPE_PRIM_U32 => write_fields(f, data.view::<u32>(), index as usize, &mut comma)?,
+				udf::format::TYPE_PRIM_I32 => write_fields(f, data.view::<i32>(), index as usize, &mut comma)?,
+				udf::format::TYPE_PRIM_U64 => write_fields(f, data.view::<u64>(), index as usize, &mut comma)?,
+				udf::format::TYPE_PRIM_I64 => write_fields(f, data.view::<i64>(), index as usize, &mut comma)?,
+				udf::format::TYPE_PRIM_F32 => write_fields(f, data.view::<f32>(), index as usize, &mut comma)?,
+				udf::format::TYPE_PRIM_F64 => write_fields(f, data.view::<f64>(), index as usize, &mut comma)?,
+				_ => return Err(io::Error::new(io::ErrorKind::InvalidData, "cannot export non-numeric data as csv")),
+			}
+		}
+		writeln!(f)?;
+	}
+	Ok(())
+}
+
+// Writes the elements of a row of the table as fields, scalar tables repeat their value on every row
+fn write_fields<T: std::fmt::Display>(f: &mut dyn Write, view: Option<udf::NdView<'_, T>>, index: usize, comma: &mut bool) -> io::Result<()> {
+	let view = view.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid table data"))?;
+	let row = if view.rank() == 0 { view } else { view.index_axis(0, index).ok_or_else(rows_out_of_bounds)? };
+	for value in row.iter() {
+		write!(f, "{}{}", if *comma { "," } else { "" }, CsvField(&value.to_string()))?;
+		*comma = true;
+	}
+	Ok(())
+}
+
+// Selects the rows of the table data along the first axis
+fn view_rows<'a, T: dataview::Pod>(data: &udf::DataRef<'a>, rows: Option<ops::Range<u32>>) -> io::Result<(udf::Shape, &'a [u8])> {
+	let view = data.view::<T>().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid table data"))?;
+	let view = match rows {
+		Some(rows) => view.slice_axis(0, rows.start as usize..rows.end as usize).ok_or_else(rows_out_of_bounds)?,
+		None => view,
+	};
+	// Slicing along the first axis keeps the elements contiguous
+	let values = view.as_slice().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid table data"))?;
+	Ok((view.shape(), dataview::bytes(values)))
+}
+
+fn rows_out_of_bounds() -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidInput, "rows out of bounds")
+}
+
+// Quotes fields containing separators
+struct CsvField<'a>(&'a str);
+
+impl std::fmt::Display for CsvField<'_> {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		if self.0.contains([',', '"', '\n']) {
+			write!(f, "\"{}\"", self.0.replace('"', "\"\""))
+		}
+		else {
+			f.write_str(self.0)
+		}
+	}
+}
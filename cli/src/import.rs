@@ -1,4 +1,3 @@
-use std::collections::HashSet;
 use std::{fs, str};
 use std::io::{self, Read, Seek};
 use std::path::Path;
@@ -19,6 +18,7 @@ pub struct Options<'a> {
 	pub import: &'a Path,
 	pub create_new: bool,
 	pub set_root: bool,
+	pub checksums: bool,
 	pub verbose: bool,
 }
 
@@ -30,6 +30,7 @@ pub fn run(opts: &Options) {
 
 	// Parse and process the import file
 	let mut ds = parse(opts, &import);
+	ds.checksums = opts.checksums;
 
 	// Create or open the UDF file for editing
 	let mut file = if opts.create_new {
@@ -51,8 +52,7 @@ pub fn run(opts: &Options) {
 
 	// Optionally set the newly added dataset as the root
 	if opts.set_root {
-		file.set_root(fo);
-		file.write_header().unwrap();
+		expect!(file.commit(fo), "Commit root="{fo});
 	}
 }
 
@@ -118,7 +118,6 @@ fn parse(opts: &Options, s: &str) -> udf::Dataset {
 	}
 
 	let mut ds = udf::Dataset::new();
-	let mut names = HashSet::new();
 
 	// Parse the dataset identifier
 	if let Some(id) = id {
@@ -136,7 +135,7 @@ fn parse(opts: &Options, s: &str) -> udf::Dataset {
 				desc.key_name = sect;
 			},
 			Item::SectionEnd => {
-				load_table(opts, &mut names, &mut ds, &desc);
+				load_table(opts, &mut ds, &desc);
 			},
 			Item::Property(key, Some(value)) => {
 				match key {
@@ -156,16 +155,11 @@ fn parse(opts: &Options, s: &str) -> udf::Dataset {
 		}
 	}
 
-	// Add all the relevant names to the names table
-	for name in &names {
-		ds.names.add(name, udf::hash(name));
-	}
-
 	return ds;
 }
 
 
-fn load_table(opts: &Options, names: &mut HashSet<String>, ds: &mut udf::Dataset, desc: &IniTableDesc) {
+fn load_table(opts: &Options, ds: &mut udf::Dataset, desc: &IniTableDesc) {
 	if opts.verbose {
 		eprintln!("Loading Datatable {}...", desc.key_name);
 	}
@@ -182,18 +176,6 @@ fn load_table(opts: &Options, names: &mut HashSet<String>, ds: &mut udf::Dataset
 	let shape = expect!(shape.parse::<udf::Shape>(),
 		"Datatable "{desc.key_name}": Invalid Shape");
 
-	let key_name = udf::hash(desc.key_name);
-	let index_name = desc.index_name.map(|index| udf::hash(index)).unwrap_or(0);
-	let related_name = desc.related_name.map(|related| udf::hash(related)).unwrap_or(0);
-
-	names.insert(desc.key_name.to_string());
-	if let Some(index_name) = desc.index_name {
-		names.insert(index_name.to_string());
-	}
-	if let Some(related_name) = desc.related_name {
-		names.insert(related_name.to_string());
-	}
-
 	let mut su8: Vec<u8>;
 	let si8: Vec<i8>;
 	let su16: Vec<u16>;
@@ -303,7 +285,13 @@ fn load_table(opts: &Options, names: &mut HashSet<String>, ds: &mut udf::Dataset
 	let compress_info = udf::format::COMPRESS_NONE;
 	let data = udf::DataRef { bytes, type_info, compress_info, shape };
 
-	ds.add_table(udf::TableRef { key_name, data, index_name, related_name });
+	let mut table = ds.insert(desc.key_name, data);
+	if let Some(index_name) = desc.index_name {
+		table = table.index(index_name);
+	}
+	if let Some(related_name) = desc.related_name {
+		table.related(related_name);
+	}
 
 	if opts.verbose {
 		eprintln!("done");
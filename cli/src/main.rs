@@ -23,7 +23,7 @@ fn main() {
 				.arg(clap::arg!(<file> "The UDF file").allow_invalid_utf8(true))
 				.arg(clap::arg!([path] "Path to the dataset"))
 				.arg(clap::arg!(-p --"print-array" "Print the array contents"))
-				.arg(clap::arg!(-f --format [format] "Format option: one of hex, flat, array (default array)"))
+				.arg(clap::arg!(-f --format [format] "Format option: one of hex, flat, array, records (default array)"))
 				.arg(clap::arg!(--"line-width" [line_width] "Sets the line width for the purpose of inserting line breaks (default 75)"))
 				.arg(clap::arg!(--"file-offset" [file_offset] "File offset to the root dataset"))
 				.arg(clap::arg!(--verbose "Verbose output"))
@@ -33,7 +33,7 @@ fn main() {
 				.arg(clap::arg!(<file> "The UDF file").allow_invalid_utf8(true))
 				.arg(clap::arg!(<path> "Path to the dataset"))
 				.arg(clap::arg!(<output> "Output path").allow_invalid_utf8(true))
-				.arg(clap::arg!(-f --format [format] "Format option: one of raw, npy (default raw)"))
+				.arg(clap::arg!(-f --format [format] "Format option: one of raw, npy, csv (default raw)"))
 				.arg(clap::arg!(--"file-offset" [file_offset] "File offset to the root dataset"))
 				.arg(clap::arg!(--verbose "Verbose output"))
 		).subcommand(
@@ -44,6 +44,7 @@ fn main() {
 				.arg(clap::arg!(<import> "Path to import file describing the dataset").allow_invalid_utf8(true))
 				.arg(clap::arg!(--"create-new" "Create a new UDF file instead of updating an existing UDF file"))
 				.arg(clap::arg!(--"set-root" "Set the imported dataset as the root dataset"))
+				.arg(clap::arg!(--checksums "Compute checksums for the imported dataset"))
 				.arg(clap::arg!(--verbose "Verbose output"))
 		).subcommand(
 			clap::Command::new("set-root")
@@ -51,6 +52,12 @@ fn main() {
 				.after_help(set_root::AFTER_HELP)
 				.arg(clap::arg!(<file> "The UDF file").allow_invalid_utf8(true))
 				.arg(clap::arg!(<"file-offset"> "The file offset to assign"))
+		).subcommand(
+			clap::Command::new("compact")
+				.about("Copy the reachable datasets to a new UDF file")
+				.after_help(compact::AFTER_HELP)
+				.arg(clap::arg!(<file> "The UDF file").allow_invalid_utf8(true))
+				.arg(clap::arg!(<output> "The compacted UDF file").allow_invalid_utf8(true))
 		).arg_required_else_help(true);
 
 	let matches = app.get_matches();
@@ -97,6 +104,7 @@ fn main() {
 			// Select a default format based on extension of the output
 			None => match Path::new(output).extension() {
 				Some(s) if s == "npy" => export::Format::Npy,
+				Some(s) if s == "csv" => export::Format::Csv,
 				_ => export::Format::Raw,
 			},
 		};
@@ -110,9 +118,10 @@ fn main() {
 		let import = matches.value_of_os("import").unwrap().as_ref();
 		let create_new = matches.is_present("create-new");
 		let set_root = matches.is_present("set-root");
+		let checksums = matches.is_present("checksums");
 		let verbose = matches.is_present("verbose");
 
-		let ref opts = import::Options { file, import, create_new, set_root, verbose };
+		let ref opts = import::Options { file, import, create_new, set_root, checksums, verbose };
 		import::run(opts);
 	}
 	else if let Some(matches) = matches.subcommand_matches("set-root") {
@@ -122,6 +131,13 @@ fn main() {
 		let ref opts = set_root::Options { file, file_offset };
 		set_root::run(opts);
 	}
+	else if let Some(matches) = matches.subcommand_matches("compact") {
+		let file = matches.value_of_os("file").unwrap().as_ref();
+		let output = matches.value_of_os("output").unwrap().as_ref();
+
+		let ref opts = compact::Options { file, output };
+		compact::run(opts);
+	}
 	else {
 		unreachable!()
 	}
@@ -140,6 +156,7 @@ mod print;
 mod export;
 mod import;
 mod set_root;
+mod compact;
 
 /*
 Ideas:
@@ -180,6 +197,17 @@ fn hex_dump(f: &mut dyn io::Write, bytes: &[u8]) -> io::Result<()> {
 	Ok(())
 }
 
+// Formats a row of table data, multiple elements are formatted as a flat array
+fn fmt_row(data: &udf::DataRef) -> String {
+	let mut ndprint = match data.print() {
+		Ok(ndprint) => ndprint,
+		Err(_) => return String::from("?"),
+	};
+	ndprint.set_line_width(0);
+	ndprint.set_shape(if data.len() == 1 { udf::Shape::Scalar } else { data.shape.flatten() });
+	ndprint.to_string()
+}
+
 #[repr(transparent)]
 pub struct Fmt<F: Fn(&mut fmt::Formatter) -> fmt::Result>(pub F);
 impl<F: Fn(&mut fmt::Formatter) -> fmt::Result> fmt::Display for Fmt<F> {
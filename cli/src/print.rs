@@ -1,4 +1,4 @@
-use std::{io, str};
+use std::{io, ops, str};
 use std::ffi::OsStr;
 
 pub enum Format {
@@ -8,6 +8,8 @@ pub enum Format {
 	FlatArray,
 	/// Print as a proper multidimensional array.
 	NdArray,
+	/// Print the rows of related tables side by side.
+	Records,
 }
 impl Default for Format {
 	fn default() -> Self {
@@ -21,6 +23,7 @@ impl str::FromStr for Format {
 			"hex" => Ok(Format::HexDump),
 			"flat" => Ok(Format::FlatArray),
 			"array" => Ok(Format::NdArray),
+			"records" => Ok(Format::Records),
 			_ => Err(udf::ParseError::InvalidFormat),
 		}
 	}
@@ -39,123 +42,115 @@ pub struct Options<'a> {
 pub fn run(opts: &Options) {
 	let mut file = udf::FileIO::open(opts.file).expect("open error");
 
-	let ref fo = opts.file_offset.unwrap_or_else(|| file.root());
+	let fo = opts.file_offset.unwrap_or_else(|| file.root());
 
-	walk(&mut file, opts, fo, opts.path, None);
-}
+	let matches = match file.resolve_all(fo, opts.path) {
+		Ok(matches) => matches,
+		Err(err) => return eprintln!("Error resolving {:?}: {}", opts.path, err),
+	};
+	if matches.is_empty() {
+		return eprintln!("Nothing matches {:?}!", opts.path);
+	}
 
-fn walk(file: &mut udf::FileIO, opts: &Options, fo: &udf::format::FileOffset, mut path: &str, parent: Option<&udf::WalkRef<udf::DatasetRef>>) {
-	if opts.verbose {
-		eprint!("reading dataset {}... ", fo);
+	for resolved in &matches {
+		if matches.len() > 1 {
+			println!("# {}\n", resolved.path());
+		}
+		print_resolved(opts, resolved);
 	}
-	let dataset = file.read_dataset(*fo).unwrap();
+}
+
+fn print_resolved(opts: &Options, resolved: &udf::Resolved) {
 	if opts.verbose {
-		eprintln!("ok");
+		for (fo, _) in resolved.datasets() {
+			eprintln!("read dataset {}", fo);
+		}
+		eprintln!();
 	}
-	let dataset = dataset.as_ref();
+
+	let dataset = resolved.dataset();
 	let names = &dataset.names;
 
-	if path.is_empty() {
-		if opts.verbose {
-			eprintln!();
-		}
-		print_dataset(fo, names, &dataset);
+	let Some(table) = resolved.table() else {
+		print_dataset(&resolved.file_offset(), names, &dataset);
 		return;
+	};
+
+	print_table_header(names, table);
+	if let Some(rows) = resolved.rows() {
+		println!("Rows: {}..{}\n", rows.start, rows.end);
 	}
 
-	if opts.verbose {
-		eprintln!("path={path:?}");
-	}
-
-	match udf::PathEl::parse(&mut path) {
-		Ok(udf::PathEl::Dir { name, index }) => {
-			let table = match names.find(name).and_then(|hash| dataset.find_table(hash)) {
-				Some(a) => a,
-				None => return eprintln!("Dataset does not have a table named {name:?}!"),
-			};
-
-			let data_ref = dataset.get_data_ref(table).unwrap();
-			if data_ref.type_info & (udf::format::TYPE_HINT_MASK | udf::format::TYPE_PRIM_MASK) == udf::format::TYPE_HINT_DATASET | udf::format::TYPE_PRIM_U64 {
-				let fos = data_ref.as_slice::<udf::format::FileOffset>().expect("dataset is malformed");
-
-				let fo = match fos.get(index as usize) {
-					Some(fo) => fo,
-					None => return eprintln!(""),
-				};
-
-				let chain = udf::WalkRef {
-					parent,
-					instance: &dataset,
-				};
-				walk(file, opts, fo, path, Some(&chain));
-			}
-			else {
-				return eprintln!("The path does not refer to a dataset table!");
-			}
-		},
-		Ok(udf::PathEl::Name(name)) => {
-			if opts.verbose {
-				eprintln!();
-			}
-
-			if !path.is_empty() {
-				return eprintln!("The path is malformed");
-			}
-
-			let table = match names.find(name).and_then(|hash| dataset.find_table(hash)) {
-				Some(a) => a,
-				None => return eprintln!("Dataset does not have a table named {name:?}!"),
-			};
-
-			print_table_header(names, table);
-
-			if opts.print_array {
-				let data_ref = match dataset.get_data_ref(table) {
-					Some(data_ref) => data_ref,
-					None => return eprintln!("Error reading table data!"),
-				};
-				let mut storage = Vec::new();
-				let data_ref = data_ref.decompress(&mut storage);
-
-				let f = io::stdout();
-				let mut f = f.lock();
-				let f: &mut dyn io::Write = &mut f;
-
-				match opts.format {
-					Format::HexDump => {
-						let _ = write!(f, "```\n");
-						let _ = crate::hex_dump(f, data_ref.as_slice::<u8>().unwrap());
-						let _ = writeln!(f, "\n```");
-					},
-					Format::FlatArray | Format::NdArray => {
-
-						let type_hint = data_ref.type_info & udf::format::TYPE_HINT_MASK;
-						if type_hint == udf::format::TYPE_HINT_TEXT || type_hint == udf::format::TYPE_HINT_JSON {
-							let text = match str::from_utf8(data_ref.bytes) {
-								Ok(text) => text,
-								Err(err) => return eprintln!("Error reading table data: {}!", err),
-							};
-
-							println!("{}", text);
-						}
-						else {
-							let mut ndprint = match data_ref.print() {
-								Ok(ndprint) => ndprint,
-								Err(_) => return eprintln!("Error printing table data!"),
-							};
-							ndprint.set_line_width(opts.line_width);
-							if matches!(opts.format, Format::FlatArray) {
-								ndprint.set_shape(data_ref.shape.flatten());
-							}
-							let _ = write!(f, "```\n{}\n```", ndprint);
-						}
-					},
-				};
-			}
-		},
-		Err(_err) => {
-			return eprintln!("The path is malformed");
-		},
+	if opts.print_array && matches!(opts.format, Format::Records) {
+		let related = match dataset.related_key(table.key_name) {
+			Ok(related) => related,
+			Err(err) => return eprintln!("Error reading related tables: {}!", err),
+		};
+		print_records(names, &related, resolved.rows());
+	}
+	else if opts.print_array {
+		let mut storage = Vec::new();
+		let data_ref = match resolved.data(&mut storage) {
+			Ok(data_ref) => data_ref,
+			Err(err) => return eprintln!("Error reading table data: {}!", err),
+		};
+
+		let f = io::stdout();
+		let mut f = f.lock();
+		let f: &mut dyn io::Write = &mut f;
+
+		match opts.format {
+			Format::HexDump => {
+				let _ = write!(f, "```\n");
+				let _ = crate::hex_dump(f, data_ref.as_slice::<u8>().unwrap());
+				let _ = writeln!(f, "\n```");
+			},
+			Format::FlatArray | Format::NdArray | Format::Records => {
+
+				let type_hint = data_ref.type_info & udf::format::TYPE_HINT_MASK;
+				if type_hint == udf::format::TYPE_HINT_TEXT || type_hint == udf::format::TYPE_HINT_JSON {
+					let text = match str::from_utf8(data_ref.bytes) {
+						Ok(text) => text,
+						Err(err) => return eprintln!("Error reading table data: {}!", err),
+					};
+
+					println!("{}", text);
+				}
+				else {
+					let mut ndprint = match data_ref.print() {
+						Ok(ndprint) => ndprint,
+						Err(_) => return eprintln!("Error printing table data!"),
+					};
+					ndprint.set_line_width(opts.line_width);
+					if matches!(opts.format, Format::FlatArray) {
+						ndprint.set_shape(data_ref.shape.flatten());
+					}
+					let _ = write!(f, "```\n{}\n```", ndprint);
+				}
+			},
+		};
+	}
+}
+
+fn print_records(names: &udf::NamesRef, related: &udf::Related, rows: Option<ops::Range<u32>>) {
+	let rows = rows.unwrap_or(0..related.len() as u32);
+
+	print!("|");
+	for table in related.tables() {
+		print!(" {} |", udf::NameOrHash(names.lookup(table.key_name)));
+	}
+	print!("\n|");
+	for _ in related.tables() {
+		print!(" --- |");
+	}
+	println!();
+
+	for row in rows.filter_map(|index| related.row(index as usize)) {
+		print!("|");
+		for (_, data) in row.iter() {
+			print!(" {} |", crate::fmt_row(&data));
+		}
+		println!();
 	}
 }
 
@@ -215,5 +210,8 @@ pub fn print_table_header(names: &udf::NamesRef, table: &udf::format::TableDesc)
 	if table.related_name != 0 {
 		println!("Related name: {}  ", udf::NameOrHash(names.lookup(table.related_name)));
 	}
+	if table.checksum != 0 {
+		println!("Checksum: {:#x}  ", table.checksum);
+	}
 	println!();
 }
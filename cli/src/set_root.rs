@@ -18,6 +18,5 @@ pub fn run(opts: &Options) {
 
 	let old_root = file.root();
 	println!("{}", old_root);
-	file.set_root(opts.file_offset);
-	file.write_header().unwrap();
+	expect!(file.commit(opts.file_offset), "Commit root="{opts.file_offset});
 }
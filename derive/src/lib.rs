@@ -0,0 +1,222 @@
+/*!
+Derive macro for UDF dataset schemas.
+
+```
+use udf::UdfDataset;
+use udf_derive::UdfDataset;
+
+#[derive(UdfDataset)]
+struct Slice {
+    #[udf(name = "Height")]
+    height: f32,
+    #[udf(name = "Points", hint = "coord")]
+    points: Vec<[f32; 2]>,
+    #[udf(name = "Contours", index = "Points", hint = "range")]
+    contours: Vec<[u32; 2]>,
+}
+
+let slice = Slice { height: 0.5, points: vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]], contours: vec![[0, 3]] };
+let mut ds = slice.to_dataset();
+ds.finalize();
+
+let slice = Slice::from_dataset_ref(&ds.as_ref()).unwrap();
+assert_eq!(slice.points.len(), 3);
+```
+*/
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::ext::IdentExt;
+
+/// Derives `udf::UdfDataset` for structs with named fields.
+///
+/// Every field is mapped to a table, its type must implement `udf::AsDataRef` and `udf::FromDataRef`.
+/// Fields of type `Option<T>` are left out when `None` and read as `None` when the table is missing.
+///
+/// The key names are hashed at compile time and the names are added to the names lookup of the dataset.
+///
+/// Field attributes:
+///
+/// * `#[udf(name = "Points")]`: The name of the table, defaults to the name of the field.
+/// * `#[udf(index = "Points")]`: The index name of the table.
+/// * `#[udf(related = "Contours")]`: The related name of the table.
+/// * `#[udf(hint = "coord")]`: The type hint of the table, one of `none`, `text`, `json`, `dataset`, `index`, `range`, `coord`, `hatch`, `transform` or `rgb`.
+///   The trailing axes of the data become the ghost dimensions required by the type hint and the table must have the type hint when read.
+#[proc_macro_derive(UdfDataset, attributes(udf))]
+pub fn derive_udf_dataset(input: TokenStream) -> TokenStream {
+	let input = syn::parse_macro_input!(input as syn::DeriveInput);
+	match expand(&input) {
+		Ok(tokens) => tokens.into(),
+		Err(err) => err.to_compile_error().into(),
+	}
+}
+
+struct Field<'a> {
+	ident: &'a syn::Ident,
+	name: String,
+	index: Option<String>,
+	related: Option<String>,
+	hint: Option<syn::Ident>,
+	optional: bool,
+}
+
+fn parse_field(field: &syn::Field) -> syn::Result<Field<'_>> {
+	let ident = field.ident.as_ref().unwrap();
+	let mut result = Field {
+		ident,
+		name: ident.unraw().to_string(),
+		index: None,
+		related: None,
+		hint: None,
+		optional: is_option(&field.ty),
+	};
+	for attr in &field.attrs {
+		if !attr.path().is_ident("udf") {
+			continue;
+		}
+		attr.parse_nested_meta(|meta| {
+			let value: syn::LitStr = meta.value()?.parse()?;
+			if meta.path.is_ident("name") {
+				result.name = value.value();
+			}
+			else if meta.path.is_ident("index") {
+				result.index = Some(value.value());
+			}
+			else if meta.path.is_ident("related") {
+				result.related = Some(value.value());
+			}
+			else if meta.path.is_ident("hint") {
+				result.hint = Some(parse_hint(&value)?);
+			}
+			else {
+				return Err(meta.error("unknown udf attribute, expected one of name, index, related or hint"));
+			}
+			Ok(())
+		})?;
+	}
+	Ok(result)
+}
+
+fn parse_hint(value: &syn::LitStr) -> syn::Result<syn::Ident> {
+	let hint = match &*value.value() {
+		"none" => "TYPE_HINT_NONE",
+		"text" => "TYPE_HINT_TEXT",
+		"json" => "TYPE_HINT_JSON",
+		"dataset" => "TYPE_HINT_DATASET",
+		"index" => "TYPE_HINT_INDEX",
+		"range" => "TYPE_HINT_RANGE",
+		"coord" => "TYPE_HINT_COORD",
+		"hatch" => "TYPE_HINT_HATCH",
+		"transform" => "TYPE_HINT_TRANSFORM",
+		"rgb" => "TYPE_HINT_RGB",
+		_ => return Err(syn::Error::new(value.span(), "unknown type hint")),
+	};
+	Ok(syn::Ident::new(hint, value.span()))
+}
+
+// Syntactically checks for `Option<T>`
+fn is_option(ty: &syn::Type) -> bool {
+	let syn::Type::Path(path) = ty else { return false };
+	path.qself.is_none() && path.path.segments.last().is_some_and(|segment| {
+		segment.ident == "Option" && matches!(segment.arguments, syn::PathArguments::AngleBracketed(_))
+	})
+}
+
+fn expand(input: &syn::DeriveInput) -> syn::Result<TokenStream2> {
+	let syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(fields), .. }) = &input.data else {
+		return Err(syn::Error::new_spanned(&input.ident, "UdfDataset can only be derived for structs with named fields"));
+	};
+	let fields = fields.named.iter().map(parse_field).collect::<syn::Result<Vec<_>>>()?;
+
+	// Register every name once
+	let mut names = Vec::new();
+	for (i, field) in fields.iter().enumerate() {
+		if fields[..i].iter().any(|other| other.name == field.name) {
+			return Err(syn::Error::new_spanned(field.ident, format!("duplicate table name {:?}", field.name)));
+		}
+		for name in [Some(&field.name), field.index.as_ref(), field.related.as_ref()].into_iter().flatten() {
+			if !names.contains(name) {
+				names.push(name.clone());
+			}
+		}
+	}
+
+	let hash = |name: Option<&String>| match name {
+		Some(name) => quote!(::udf::hash!(#name)),
+		None => quote!(0),
+	};
+
+	let writes = fields.iter().map(|field| {
+		let ident = field.ident;
+		let key_name = hash(Some(&field.name));
+		let index_name = hash(field.index.as_ref());
+		let related_name = hash(field.related.as_ref());
+		let data = match &field.hint {
+			Some(hint) => quote!(value.as_data_ref().with_type_hint(::udf::format::#hint)),
+			None => quote!(value.as_data_ref()),
+		};
+		let write = quote! {
+			dataset.add_table(::udf::TableRef { key_name: #key_name, data: #data, index_name: #index_name, related_name: #related_name });
+		};
+		if field.optional {
+			quote!(if let ::core::option::Option::Some(value) = &self.#ident { #write })
+		}
+		else {
+			quote!({ let value = &self.#ident; #write })
+		}
+	});
+
+	let reads = fields.iter().map(|field| {
+		let ident = field.ident;
+		let key_name = hash(Some(&field.name));
+		let read = match &field.hint {
+			Some(hint) => quote!(dataset.read_hint_key(#key_name, ::udf::format::#hint)?),
+			None => quote!(dataset.read_key(#key_name)?),
+		};
+		if field.optional {
+			quote! {
+				#ident: match dataset.find_table(#key_name) {
+					::core::option::Option::Some(_) => ::core::option::Option::Some(#read),
+					::core::option::Option::None => ::core::option::Option::None,
+				}
+			}
+		}
+		else {
+			quote!(#ident: #read)
+		}
+	});
+
+	// Borrow the data for the lifetime of the struct, if any
+	let mut generics = input.generics.clone();
+	let lifetime = match input.generics.lifetimes().next() {
+		Some(param) => param.lifetime.clone(),
+		None => {
+			let lifetime = syn::Lifetime::new("'udf", Span::call_site());
+			generics.params.insert(0, syn::GenericParam::Lifetime(syn::LifetimeParam::new(lifetime.clone())));
+			lifetime
+		},
+	};
+	let (impl_generics, _, _) = generics.split_for_impl();
+	let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+	let ident = &input.ident;
+
+	Ok(quote! {
+		impl #impl_generics ::udf::UdfDataset<#lifetime> for #ident #ty_generics #where_clause {
+			fn to_dataset(&self) -> ::udf::Dataset {
+				#[allow(unused_imports)]
+				use ::udf::AsDataRef as _;
+				let mut dataset = ::udf::Dataset::new();
+				#(dataset.names.add(#names, ::udf::hash!(#names));)*
+				#(#writes)*
+				dataset
+			}
+
+			fn from_dataset_ref(dataset: &::udf::DatasetRef<#lifetime>) -> ::core::result::Result<Self, ::udf::Error> {
+				::core::result::Result::Ok(#ident {
+					#(#reads,)*
+				})
+			}
+		}
+	})
+}
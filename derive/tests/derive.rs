@@ -0,0 +1,87 @@
+use udf::format;
+use udf::UdfDataset;
+use udf_derive::UdfDataset;
+
+#[derive(UdfDataset, Debug, PartialEq)]
+struct Slice {
+	#[udf(name = "Height")]
+	height: f32,
+	#[udf(name = "Points", hint = "coord")]
+	points: Vec<[f32; 2]>,
+	#[udf(name = "Contours", index = "Points", hint = "range")]
+	contours: Vec<[u32; 2]>,
+	#[udf(name = "Attributes", related = "Contours")]
+	attributes: Vec<u8>,
+	#[udf(name = "Label")]
+	label: String,
+	#[udf(name = "Comment")]
+	comment: Option<String>,
+	r#type: u32,
+}
+
+#[derive(UdfDataset)]
+struct SliceRef<'a> {
+	#[udf(name = "Points", hint = "coord")]
+	points: &'a [[f32; 2]],
+	#[udf(name = "Label")]
+	label: &'a str,
+}
+
+fn slice() -> Slice {
+	Slice {
+		height: 1.5,
+		points: vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 0.0]],
+		contours: vec![[0, 4]],
+		attributes: vec![7],
+		label: String::from("Outer"),
+		comment: None,
+		r#type: 2,
+	}
+}
+
+#[test]
+fn roundtrip() {
+	let slice = slice();
+	let mut ds = slice.to_dataset();
+	ds.finalize();
+	let ds_ref = ds.as_ref();
+
+	// The tables use the compile time hashes and type hints
+	let points = ds_ref.find_table(udf::hash!("Points")).unwrap();
+	assert_eq!(points.type_info, format::TYPE_HINT_COORD | format::TYPE_DIM_1D | format::TYPE_PRIM_F32);
+	let contours = ds_ref.find_table(udf::hash!("Contours")).unwrap();
+	assert_eq!(contours.type_info & format::TYPE_HINT_MASK, format::TYPE_HINT_RANGE);
+	assert_eq!(contours.index_name, udf::hash!("Points"));
+	assert_eq!(ds_ref.find_table(udf::hash!("Attributes")).unwrap().related_name, udf::hash!("Contours"));
+	assert!(ds_ref.find_table(udf::hash!("Comment")).is_none());
+	assert!(ds_ref.find_table(udf::hash!("type")).is_some());
+	assert_eq!(ds.names.len(), 7);
+
+	assert_eq!(Slice::from_dataset_ref(&ds_ref).unwrap(), slice);
+
+	let view = SliceRef::from_dataset_ref(&ds_ref).unwrap();
+	assert_eq!(view.points, &slice.points[..]);
+	assert_eq!(view.label, "Outer");
+
+	// The dataset is valid
+	let mut file = udf::FileIO::create_with(std::io::Cursor::new(Vec::new()), *b"TEST").unwrap();
+	let fo = file.add_dataset(&ds.finalize()).unwrap();
+	file.set_root(fo);
+	file.write_header().unwrap();
+	assert!(udf::validate(&mut file).is_ok());
+}
+
+#[test]
+fn optional_fields() {
+	let mut slice = slice();
+	slice.comment = Some(String::from("Hello"));
+	let mut ds = slice.to_dataset();
+	ds.finalize();
+	assert_eq!(Slice::from_dataset_ref(&ds.as_ref()).unwrap(), slice);
+
+	// Required tables are reported by key name
+	ds.remove_table(udf::hash!("Label"));
+	ds.finalize();
+	let err = Slice::from_dataset_ref(&ds.as_ref()).err().unwrap();
+	assert!(matches!(err, udf::Error::TableNotFound { key_name, .. } if key_name == udf::hash!("Label")));
+}
@@ -5,21 +5,10 @@ fn main() {
 	let mut writer = udf::FileIO::create("sample.udf", [0; 4]).unwrap();
 
 	let mut ds = udf::Dataset::new();
-	for &name in &["Floats", "Texts"] {
-		ds.names.add(name, udf::hash(name));
-	}
-	ds.add_table(udf::TableRef {
-		key_name: udf::hash("Floats"),
-		data: FLOATS[..].as_data_ref(),
-		..Default::default()
-	});
+	ds.insert("Floats", FLOATS[..].as_data_ref());
 
 	let text_fo = writer.add_dataset(&create_texts().finalize()).unwrap();
-	ds.add_table(udf::TableRef {
-		key_name: udf::hash("Texts"),
-		data: text_fo.as_data_ref(),
-		..Default::default()
-	});
+	ds.insert("Texts", text_fo.as_data_ref());
 
 	let fo = writer.add_dataset(&ds.finalize()).unwrap();
 	writer.set_root(fo);
@@ -50,79 +39,52 @@ fn create_texts() -> udf::Dataset {
 	];
 
 	let mut ds = udf::Dataset::new();
-	for &name in &["Text UTF-8", "Text UTF-16", "Text UTF-32", "List UTF-8", "List UTF-16", "List UTF-32"] {
-		ds.names.add(name, udf::hash(name));
-	}
 
-	ds.add_table(udf::TableRef {
-		key_name: udf::hash!("Text UTF-8"),
-		data: udf::DataRef {
-			bytes: s.as_bytes(),
-			compress_info: 0,
-			shape: udf::Shape::D1(s.len() as u32),
-			type_info: udf::format::TYPE_PRIM_U8 | udf::format::TYPE_DIM_SCALAR | udf::format::TYPE_HINT_TEXT,
-		},
-		..Default::default()
+	ds.insert("Text UTF-8", udf::DataRef {
+		bytes: s.as_bytes(),
+		compress_info: 0,
+		shape: udf::Shape::D1(s.len() as u32),
+		type_info: udf::format::TYPE_PRIM_U8 | udf::format::TYPE_DIM_SCALAR | udf::format::TYPE_HINT_TEXT,
 	});
 
 	let utf16 = s.encode_utf16().collect::<Vec<u16>>();
-	ds.add_table(udf::TableRef {
-		key_name: udf::hash!("Text UTF-16"),
-		data: udf::DataRef {
-			bytes: utf16.as_bytes(),
-			compress_info: 0,
-			shape: udf::Shape::D1(utf16.len() as u32),
-			type_info: udf::format::TYPE_PRIM_U16 | udf::format::TYPE_DIM_SCALAR | udf::format::TYPE_HINT_TEXT,
-		},
-		..Default::default()
+	ds.insert("Text UTF-16", udf::DataRef {
+		bytes: utf16.as_bytes(),
+		compress_info: 0,
+		shape: udf::Shape::D1(utf16.len() as u32),
+		type_info: udf::format::TYPE_PRIM_U16 | udf::format::TYPE_DIM_SCALAR | udf::format::TYPE_HINT_TEXT,
 	});
 
 	let utf32 = s.chars().map(|c| c as u32).collect::<Vec<u32>>();
-	ds.add_table(udf::TableRef {
-		key_name: udf::hash!("Text UTF-32"),
-		data: udf::DataRef {
-			bytes: utf32.as_bytes(),
-			compress_info: 0,
-			shape: udf::Shape::D1(utf32.len() as u32),
-			type_info: udf::format::TYPE_PRIM_U32 | udf::format::TYPE_DIM_SCALAR | udf::format::TYPE_HINT_TEXT,
-		},
-		..Default::default()
+	ds.insert("Text UTF-32", udf::DataRef {
+		bytes: utf32.as_bytes(),
+		compress_info: 0,
+		shape: udf::Shape::D1(utf32.len() as u32),
+		type_info: udf::format::TYPE_PRIM_U32 | udf::format::TYPE_DIM_SCALAR | udf::format::TYPE_HINT_TEXT,
 	});
 
 	let slist8 = encode_text_list(&TEXT_LIST);
-	ds.add_table(udf::TableRef {
-		key_name: udf::hash!("List UTF-8"),
-		data: udf::DataRef {
-			bytes: slist8.0.as_bytes(),
-			compress_info: 0,
-			shape: udf::Shape::D2(TEXT_LIST.len() as u32, slist8.1 as u32),
-			type_info: udf::format::TYPE_PRIM_U8 | udf::format::TYPE_DIM_1D | udf::format::TYPE_HINT_TEXT,
-		},
-		..Default::default()
+	ds.insert("List UTF-8", udf::DataRef {
+		bytes: slist8.0.as_bytes(),
+		compress_info: 0,
+		shape: udf::Shape::D2(TEXT_LIST.len() as u32, slist8.1 as u32),
+		type_info: udf::format::TYPE_PRIM_U8 | udf::format::TYPE_DIM_1D | udf::format::TYPE_HINT_TEXT,
 	});
 
 	let slist16 = slist8.0.encode_utf16().collect::<Vec<u16>>();
-	ds.add_table(udf::TableRef {
-		key_name: udf::hash!("List UTF-16"),
-		data: udf::DataRef {
-			bytes: slist16.as_bytes(),
-			compress_info: 0,
-			shape: udf::Shape::D2(TEXT_LIST.len() as u32, slist8.1 as u32),
-			type_info: udf::format::TYPE_PRIM_U16 | udf::format::TYPE_DIM_1D | udf::format::TYPE_HINT_TEXT,
-		},
-		..Default::default()
+	ds.insert("List UTF-16", udf::DataRef {
+		bytes: slist16.as_bytes(),
+		compress_info: 0,
+		shape: udf::Shape::D2(TEXT_LIST.len() as u32, slist8.1 as u32),
+		type_info: udf::format::TYPE_PRIM_U16 | udf::format::TYPE_DIM_1D | udf::format::TYPE_HINT_TEXT,
 	});
 
 	let slist32 = slist8.0.chars().map(|c| c as u32).collect::<Vec<u32>>();
-	ds.add_table(udf::TableRef {
-		key_name: udf::hash!("List UTF-32"),
-		data: udf::DataRef {
-			bytes: slist32.as_bytes(),
-			compress_info: 0,
-			shape: udf::Shape::D2(TEXT_LIST.len() as u32, slist8.1 as u32),
-			type_info: udf::format::TYPE_PRIM_U32 | udf::format::TYPE_DIM_1D | udf::format::TYPE_HINT_TEXT,
-		},
-		..Default::default()
+	ds.insert("List UTF-32", udf::DataRef {
+		bytes: slist32.as_bytes(),
+		compress_info: 0,
+		shape: udf::Shape::D2(TEXT_LIST.len() as u32, slist8.1 as u32),
+		type_info: udf::format::TYPE_PRIM_U32 | udf::format::TYPE_DIM_1D | udf::format::TYPE_HINT_TEXT,
 	});
 
 	return ds;
@@ -15,9 +15,6 @@ fn main() {
 
 	for slice_index in 0..n_slices {
 		let mut ds = udf::Dataset::new();
-		for &name in &["Points", "Contours", "Attributes", "Metadata", "PartIndex", "Height", "PartsInfo"] {
-			ds.names.add(name, udf::hash(name));
-		}
 
 		let height = slice_index as f32 * slice_thickness + base_height;
 		heights.push(height);
@@ -32,47 +29,18 @@ fn main() {
 			generate_contour(&mut rng, &mut points, &mut contours, &mut attributes, &mut part_indices);
 		}
 
-		ds.add_table(udf::TableRef {
-			key_name: udf::hash!("Height"),
-			data: height.as_data_ref(),
-			..Default::default()
-		});
-		ds.add_table(udf::TableRef {
-			key_name: udf::hash!("Points"),
-			data: points.as_data_ref(),
-			..Default::default()
-		});
-		ds.add_table(udf::TableRef {
-			key_name: udf::hash!("Contours"),
-			data: contours.as_data_ref(),
-			index_name: udf::hash!("Points"),
-			..Default::default()
-		});
-		ds.add_table(udf::TableRef {
-			key_name: udf::hash!("Attributes"),
-			data: attributes.as_data_ref(),
-			related_name: udf::hash!("Contours"),
-			index_name: udf::hash!("Metadata"),
-			..Default::default()
-		});
+		ds.insert("Height", height.as_data_ref());
+		ds.insert("Points", points.as_data_ref());
+		ds.insert("Contours", contours.as_data_ref()).index("Points");
+		ds.insert("Attributes", attributes.as_data_ref()).related("Contours").index("Metadata");
 		let metadata = "[{}, {}]";
-		ds.add_table(udf::TableRef {
-			key_name: udf::hash!("Metadata"),
-			data: udf::DataRef {
-				bytes: metadata.as_bytes(),
-				type_info: udf::format::TYPE_HINT_JSON | udf::format::TYPE_DIM_1D | udf::format::TYPE_PRIM_CUSTOM,
-				compress_info: udf::format::COMPRESS_NONE,
-				shape: udf::Shape::D1(2),
-			},
-			..Default::default()
-		});
-		ds.add_table(udf::TableRef {
-			key_name: udf::hash!("PartIndex"),
-			data: part_indices.as_data_ref(),
-			related_name: udf::hash!("Contours"),
-			index_name: udf::hash!("PartsInfo"),
-			..Default::default()
+		ds.insert("Metadata", udf::DataRef {
+			bytes: metadata.as_bytes(),
+			type_info: udf::format::TYPE_HINT_JSON | udf::format::TYPE_DIM_1D | udf::format::TYPE_PRIM_CUSTOM,
+			compress_info: udf::format::COMPRESS_NONE,
+			shape: udf::Shape::D1(2),
 		});
+		ds.insert("PartIndex", part_indices.as_data_ref()).related("Contours").index("PartsInfo");
 		ds.header.id = *b"VL2\0";
 
 		let fo = udf.add_dataset(&ds.finalize()).unwrap();
@@ -81,32 +49,16 @@ fn main() {
 
 	{
 		let mut ds = udf::Dataset::new();
-		for &name in &["PartsInfo", "Slices", "Heights"] {
-			ds.names.add(name, udf::hash(name));
-		}
 
 		let parts_info = "[]";
-		ds.add_table(udf::TableRef {
-			key_name: udf::hash!("PartsInfo"),
-			data: udf::DataRef {
-				bytes: parts_info.as_bytes(),
-				type_info: udf::format::TYPE_HINT_JSON | udf::format::TYPE_DIM_1D | udf::format::TYPE_PRIM_CUSTOM,
-				compress_info: udf::format::COMPRESS_NONE,
-				shape: udf::Shape::D1(0),
-			},
-			..Default::default()
-		});
-		ds.add_table(udf::TableRef {
-			key_name: udf::hash!("Heights"),
-			data: heights.as_data_ref(),
-			related_name: udf::hash!("Slices"),
-			..Default::default()
-		});
-		ds.add_table(udf::TableRef {
-			key_name: udf::hash!("Slices"),
-			data: slices_fo.as_data_ref(),
-			..Default::default()
+		ds.insert("PartsInfo", udf::DataRef {
+			bytes: parts_info.as_bytes(),
+			type_info: udf::format::TYPE_HINT_JSON | udf::format::TYPE_DIM_1D | udf::format::TYPE_PRIM_CUSTOM,
+			compress_info: udf::format::COMPRESS_NONE,
+			shape: udf::Shape::D1(0),
 		});
+		ds.insert("Heights", heights.as_data_ref()).related("Slices");
+		ds.insert("Slices", slices_fo.as_data_ref());
 		ds.header.id = *b"STCK";
 
 		let root_fo = udf.add_dataset(&ds.finalize()).unwrap();
@@ -15,7 +15,7 @@ macro_rules! impl_as_data_ref_prim {
 					bytes: dataview::bytes(self),
 					type_info: format::TYPE_HINT_NONE | format::TYPE_DIM_SCALAR | format::$prim_type,
 					compress_info: format::COMPRESS_NONE,
-					shape: Shape::D1(1),
+					shape: Shape::Scalar,
 				}
 			}
 		}
@@ -35,7 +35,7 @@ macro_rules! impl_as_data_ref_prim {
 			fn as_data_ref(&self) -> DataRef<'_> {
 				DataRef {
 					bytes: dataview::bytes(self),
-					type_info: format::TYPE_HINT_NONE | format::TYPE_DIM_SCALAR | format::$prim_type,
+					type_info: format::TYPE_HINT_NONE | format::TYPE_DIM_1D | format::$prim_type,
 					compress_info: format::COMPRESS_NONE,
 					shape: Shape::D1(self.len() as u32),
 				}
@@ -47,7 +47,7 @@ macro_rules! impl_as_data_ref_prim {
 				assert!(LEN < 0x1000000);
 				DataRef {
 					bytes: dataview::bytes(self),
-					type_info: format::TYPE_HINT_NONE | format::TYPE_DIM_1D | format::$prim_type,
+					type_info: format::TYPE_HINT_NONE | format::TYPE_DIM_2D | format::$prim_type,
 					compress_info: format::COMPRESS_NONE,
 					shape: Shape::D2(self.len() as u32, LEN as u32),
 				}
@@ -59,7 +59,7 @@ macro_rules! impl_as_data_ref_prim {
 				assert!(M < 0x1000000);
 				DataRef {
 					bytes: dataview::bytes(self),
-					type_info: format::TYPE_HINT_NONE | format::TYPE_DIM_SCALAR | format::$prim_type,
+					type_info: format::TYPE_HINT_NONE | format::TYPE_DIM_2D | format::$prim_type,
 					compress_info: format::COMPRESS_NONE,
 					shape: Shape::D2(N as u32, M as u32),
 				}
@@ -72,7 +72,7 @@ macro_rules! impl_as_data_ref_prim {
 				assert!(M < 0x100);
 				DataRef {
 					bytes: dataview::bytes(self),
-					type_info: format::TYPE_HINT_NONE | format::TYPE_DIM_1D | format::$prim_type,
+					type_info: format::TYPE_HINT_NONE | format::TYPE_DIM_3D | format::$prim_type,
 					compress_info: format::COMPRESS_NONE,
 					shape: Shape::D3(self.len() as u32, N as u32, M as u8),
 				}
@@ -95,6 +95,41 @@ impl_as_data_ref_prim!(f64, TYPE_PRIM_F64);
 
 
 macro_rules! impl_as_data_ref_typed {
+	($ty:ty, [$rows:literal, $cols:literal], $hint:ident, $prim:ident) => {
+		impl AsDataRef for $ty {
+			#[inline]
+			fn as_data_ref(&self) -> DataRef<'_> {
+				DataRef {
+					bytes: dataview::bytes(self),
+					type_info: format::$hint | format::TYPE_DIM_SCALAR | format::$prim,
+					compress_info: format::COMPRESS_NONE,
+					shape: Shape::D2($rows, $cols),
+				}
+			}
+		}
+		impl AsDataRef for [$ty] {
+			#[inline]
+			fn as_data_ref(&self) -> DataRef<'_> {
+				DataRef {
+					bytes: dataview::bytes(self),
+					type_info: format::$hint | format::TYPE_DIM_1D | format::$prim,
+					compress_info: format::COMPRESS_NONE,
+					shape: Shape::D3(self.len() as u32, $rows, $cols),
+				}
+			}
+		}
+		impl<const LEN: usize> AsDataRef for [$ty; LEN] {
+			#[inline]
+			fn as_data_ref(&self) -> DataRef<'_> {
+				DataRef {
+					bytes: dataview::bytes(self),
+					type_info: format::$hint | format::TYPE_DIM_1D | format::$prim,
+					compress_info: format::COMPRESS_NONE,
+					shape: Shape::D3(self.len() as u32, $rows, $cols),
+				}
+			}
+		}
+	};
 	($ty:ty, $elts:literal, $hint:ident, $prim:ident) => {
 		impl AsDataRef for $ty {
 			#[inline]
@@ -140,8 +175,8 @@ impl_as_data_ref_typed!(format::RangeU32, 2, TYPE_HINT_RANGE, TYPE_PRIM_U32);
 impl_as_data_ref_typed!(format::Coord2F32, 2, TYPE_HINT_COORD, TYPE_PRIM_F32);
 impl_as_data_ref_typed!(format::Coord3F32, 3, TYPE_HINT_COORD, TYPE_PRIM_F32);
 impl_as_data_ref_typed!(format::HatchF32, 4, TYPE_HINT_HATCH, TYPE_PRIM_F32);
-impl_as_data_ref_typed!(format::Transform2F32, 6, TYPE_HINT_TRANSFORM, TYPE_PRIM_F32);
-impl_as_data_ref_typed!(format::Transform3F32, 12, TYPE_HINT_TRANSFORM, TYPE_PRIM_F32);
+impl_as_data_ref_typed!(format::Transform2F32, [2, 3], TYPE_HINT_TRANSFORM, TYPE_PRIM_F32);
+impl_as_data_ref_typed!(format::Transform3F32, [3, 4], TYPE_HINT_TRANSFORM, TYPE_PRIM_F32);
 
 impl AsDataRef for str {
 	#[inline]
@@ -0,0 +1,70 @@
+/// CRC-32C (Castagnoli) checksum.
+///
+/// Used for the dataset header and datatable checksums.
+///
+/// ```
+/// let mut crc = udf::Crc32c::new();
+/// crc.update(b"1234");
+/// crc.update(b"56789");
+/// assert_eq!(crc.finish(), 0xE3069283);
+/// assert_eq!(udf::crc32c(b"123456789"), 0xE3069283);
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Crc32c(u32);
+
+impl Crc32c {
+	#[inline]
+	pub const fn new() -> Crc32c {
+		Crc32c(!0)
+	}
+
+	/// Feeds more bytes into the checksum.
+	#[inline]
+	pub fn update(&mut self, bytes: &[u8]) {
+		let mut crc = self.0;
+		for &byte in bytes {
+			crc = TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+		}
+		self.0 = crc;
+	}
+
+	/// Returns the checksum of all the bytes fed so far.
+	#[inline]
+	pub const fn finish(&self) -> u32 {
+		!self.0
+	}
+}
+
+impl Default for Crc32c {
+	#[inline]
+	fn default() -> Crc32c {
+		Crc32c::new()
+	}
+}
+
+/// Returns the CRC-32C checksum of the bytes.
+#[inline]
+pub fn crc32c(bytes: &[u8]) -> u32 {
+	let mut crc = Crc32c::new();
+	crc.update(bytes);
+	crc.finish()
+}
+
+// Reversed Castagnoli polynomial
+const POLY: u32 = 0x82f63b78;
+
+static TABLE: [u32; 256] = {
+	let mut table = [0u32; 256];
+	let mut i = 0;
+	while i < 256 {
+		let mut crc = i as u32;
+		let mut j = 0;
+		while j < 8 {
+			crc = if crc & 1 != 0 { (crc >> 1) ^ POLY } else { crc >> 1 };
+			j += 1;
+		}
+		table[i] = crc;
+		i += 1;
+	}
+	table
+};
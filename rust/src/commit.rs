@@ -0,0 +1,111 @@
+/*!
+Crash-safe commits of the root dataset.
+
+Every commit appends a [`CommitRecord`](format::CommitRecord) to the end of the file before the file header is updated to point to it.
+When the file is opened the root is recovered from the last valid commit record.
+*/
+
+#[cfg(feature = "std")]
+use core::mem;
+use crate::*;
+
+impl format::CommitRecord {
+	/// Creates a new commit record and computes its checksum.
+	pub fn new(seq: u64, root: format::FileOffset, prev: u64) -> format::CommitRecord {
+		let mut record = format::CommitRecord {
+			magic: format::CommitRecord::MAGIC,
+			checksum: 0,
+			seq,
+			root,
+			prev,
+			reserved: 0,
+		};
+		record.checksum = record.compute_checksum();
+		record
+	}
+
+	/// Computes the checksum of the commit record.
+	///
+	/// The checksum is computed over the little-endian representation.
+	#[inline]
+	pub fn compute_checksum(&self) -> u32 {
+		crc32c(&dataview::bytes(&endian::to_le(*self))[8..])
+	}
+}
+
+#[cfg(feature = "std")]
+pub(crate) const COMMIT_SIZE: u64 = mem::size_of::<format::CommitRecord>() as u64;
+
+#[cfg(feature = "std")]
+#[inline]
+pub(crate) fn commit_fo(offset: u64) -> format::FileOffset {
+	format::FileOffset { offset, size: COMMIT_SIZE }
+}
+
+#[cfg(feature = "std")]
+// Commit records are 16-byte aligned, follow the file header and are contained in the file
+pub(crate) fn check_commit_bounds(offset: u64, file_size: u64) -> Result<(), Error> {
+	let fo = commit_fo(offset);
+	if offset & 0xf != 0 {
+		return Err(Error::InvalidField { field: Field::Commit, file_offset: fo, expected: Expected::MultipleOf(16), actual: offset });
+	}
+	let header_size = mem::size_of::<format::UdfHeader>() as u64;
+	if offset < header_size {
+		return Err(Error::OutOfBounds { field: Field::Commit, file_offset: fo, end: offset, size: header_size });
+	}
+	fileio::check_file_bounds(fo, file_size).map_err(|_| Error::OutOfBounds {
+		field: Field::Commit,
+		file_offset: fo,
+		end: offset.saturating_add(COMMIT_SIZE),
+		size: file_size,
+	})
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn check_commit(record: &format::CommitRecord, offset: u64) -> Result<(), Error> {
+	let fo = commit_fo(offset);
+	if record.magic != format::CommitRecord::MAGIC {
+		return Err(Error::InvalidField {
+			field: Field::Commit,
+			file_offset: fo,
+			expected: Expected::Equal(u32::from_le_bytes(format::CommitRecord::MAGIC) as u64),
+			actual: u32::from_le_bytes(record.magic) as u64,
+		});
+	}
+	let actual = record.compute_checksum();
+	if actual != record.checksum {
+		return Err(Error::ChecksumMismatch { field: Field::Commit, file_offset: fo, key_name: 0, expected: record.checksum, actual });
+	}
+	if record.reserved != 0 {
+		return Err(Error::InvalidField { field: Field::Reserved, file_offset: fo, expected: Expected::Equal(0), actual: record.reserved });
+	}
+	Ok(())
+}
+
+#[cfg(feature = "std")]
+/// Recovers the root from the last valid commit record.
+///
+/// A commit interrupted after its record was written but before the file header was updated leaves its record at the end of the file.
+/// This record is only accepted if it directly follows the commit record in the file header.
+///
+/// If the commit record in the file header is not valid the file header is left as is.
+pub(crate) fn recover(header: &mut format::UdfHeader, file_size: u64, mut read: impl FnMut(u64) -> Result<format::CommitRecord, Error>) {
+	if header.next == 0 {
+		return;
+	}
+	let Ok(mut last) = read(header.next) else {
+		return;
+	};
+
+	let tail = file_size.wrapping_sub(COMMIT_SIZE);
+	if file_size & 0xf == 0 && tail != header.next {
+		if let Ok(record) = read(tail) {
+			if record.prev == header.next && record.seq == last.seq.wrapping_add(1) {
+				header.next = tail;
+				last = record;
+			}
+		}
+	}
+
+	header.root = last.root;
+}
@@ -0,0 +1,86 @@
+/*!
+Compaction of UDF files.
+*/
+
+use std::collections::HashMap;
+use std::io;
+use crate::*;
+
+/// Compaction statistics.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Compaction {
+	/// Number of reachable datasets copied.
+	pub datasets: usize,
+	/// Size of the source file in bytes.
+	pub old_size: u64,
+	/// Size of the compacted file in bytes.
+	pub new_size: u64,
+}
+
+impl Compaction {
+	/// Returns the number of bytes reclaimed.
+	#[inline]
+	pub fn reclaimed(&self) -> u64 {
+		self.old_size.saturating_sub(self.new_size)
+	}
+}
+
+/// Compacts the UDF file by copying only the reachable datasets.
+///
+/// Datasets are reachable from the root through `TYPE_HINT_DATASET` tables.
+/// They are written to `dest` after their children and the file offsets they reference are remapped.
+/// Datasets shared between multiple parents are copied once.
+/// Checksums are recomputed for datasets which had them.
+///
+/// The `dest` file should be newly created, its root is updated and the header written.
+pub fn compact<B: Backend, W: Backend + io::Write>(file: &mut FileIO<B>, dest: &mut FileIO<W>) -> Result<Compaction, Error> {
+	let mut compactor = Compactor { dest, map: HashMap::new() };
+
+	let root = file.root();
+	walk::walk(file, root, u32::MAX, &mut compactor)?;
+	let new_root = compactor.map.get(&root).copied().unwrap_or(format::FileOffset::NULL);
+	compactor.dest.set_root(new_root);
+	compactor.dest.write_header()?;
+
+	let datasets = compactor.map.len();
+	let old_size = file.get_mut().end()?;
+	let new_size = compactor.dest.get_mut().end()?;
+	Ok(Compaction { datasets, old_size, new_size })
+}
+
+struct Compactor<'a, W> {
+	dest: &'a mut FileIO<W>,
+	// Maps the old file offsets to the new file offsets
+	map: HashMap<format::FileOffset, format::FileOffset>,
+}
+
+// Datasets are copied after their children to remap their file offsets
+impl<W: Backend + io::Write> walk::Visitor for Compactor<'_, W> {
+	fn enter(&mut self, fo: format::FileOffset) -> bool {
+		!fo.is_null() && !self.map.contains_key(&fo)
+	}
+
+	fn visit(&mut self, _walk: &Walk<'_>) -> WalkControl {
+		WalkControl::Continue
+	}
+
+	fn leave(&mut self, walk: &Walk<'_>) -> Result<(), Error> {
+		let mut dataset = walk.dataset.to_owned();
+		for table in walk.dataset.tables {
+			if !walk::is_dataset_table(table) {
+				continue;
+			}
+			let Some(children) = walk.dataset.get_data_ref(table).and_then(|data| data.as_slice::<format::FileOffset>()) else {
+				return Err(Error::InvalidTableData { file_offset: walk.file_offset, key_name: table.key_name });
+			};
+			let children: Vec<format::FileOffset> = children.iter().map(|child| self.map.get(child).copied().unwrap_or(*child)).collect();
+			let start = table.mem_start as usize * 8;
+			let bytes = &mut dataview::bytes_mut(dataset.storage.as_mut_slice())[start..start + table.data_size as usize];
+			bytes.copy_from_slice(dataview::bytes(children.as_slice()));
+		}
+
+		let new_fo = self.dest.add_dataset(&dataset.finalize())?;
+		self.map.insert(walk.file_offset, new_fo);
+		Ok(())
+	}
+}
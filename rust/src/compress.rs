@@ -12,6 +12,7 @@ mod simplu32;
 mod simplu16;
 mod simpf32;
 
+use alloc::vec::Vec;
 use dataview::Pod;
 
 pub use self::simplu32::SimpleU32;
@@ -71,10 +72,10 @@ fn hash64(mut z: u64) -> u64 {
 	return z ^ (z >> 31);
 }
 fn castu16(slice: &[i16]) -> &[u16] {
-	unsafe { std::mem::transmute(slice) }
+	unsafe { core::mem::transmute(slice) }
 }
 fn castu32(slice: &[i32]) -> &[u32] {
-	unsafe { std::mem::transmute(slice) }
+	unsafe { core::mem::transmute(slice) }
 }
 
 const fn sign_extend32(int: u32, bits: usize) -> i32 {
@@ -35,6 +35,16 @@ impl SimpleF32 {
 	}
 }
 
+// Rounds half away from zero like `f32::round` which is not available without std
+#[inline]
+fn round(v: f32) -> i32 {
+	let t = v as i32;
+	let frac = v - t as f32;
+	if frac >= 0.5 { t.saturating_add(1) }
+	else if frac <= -0.5 { t.saturating_sub(1) }
+	else { t }
+}
+
 fn compress(buf: &mut Vec<u8>, data: &[f32], unit: f32) {
 	let inv_unit = 1.0 / unit;
 	let ule = unit.to_le_bytes();
@@ -50,7 +60,7 @@ fn compress(buf: &mut Vec<u8>, data: &[f32], unit: f32) {
 	let mut lookup = [0u32; 64]; // lookup table for index
 
 	for i in 0..data.len() {
-		let v = (data[i] * inv_unit).round() as i32 as u32;
+		let v = round(data[i] * inv_unit) as u32;
 
 		// Check for repeated value
 		if v == lastv {
@@ -1,4 +1,4 @@
-use std::{fmt, mem, slice};
+use core::{fmt, mem, ops, slice};
 use crate::*;
 
 /// Data reference.
@@ -27,6 +27,40 @@ impl<'a> DataRef<'a> {
 		self.compress_info != format::COMPRESS_NONE
 	}
 
+	/// Returns the data with the type hint.
+	///
+	/// The trailing axes of the shape become the ghost dimensions required by the type hint,
+	/// eg. `[[f32; 2]]` with [`TYPE_HINT_COORD`](format::TYPE_HINT_COORD) becomes 1D coordinates with a ghost dimension of 2.
+	///
+	/// ```
+	/// use udf::AsDataRef;
+	///
+	/// let data = [[1.0f32, 2.0], [3.0, 4.0]].as_data_ref().with_type_hint(udf::format::TYPE_HINT_COORD);
+	/// assert_eq!(data.type_info, udf::format::TYPE_HINT_COORD | udf::format::TYPE_DIM_1D | udf::format::TYPE_PRIM_F32);
+	/// assert_eq!(data.shape, udf::Shape::D2(2, 2));
+	/// ```
+	pub fn with_type_hint(self, type_hint: u16) -> DataRef<'a> {
+		let rank = self.shape.dim() >> 4;
+		let ghost = match type_hint {
+			format::TYPE_HINT_NONE | format::TYPE_HINT_JSON => 0,
+			format::TYPE_HINT_INDEX => if rank >= 2 { 1 } else { 0 },
+			format::TYPE_HINT_TRANSFORM => 2,
+			_ => 1,
+		};
+		let dims = rank.saturating_sub(ghost) << 4;
+		let type_info = type_hint & format::TYPE_HINT_MASK | dims | self.type_info & format::TYPE_PRIM_MASK;
+		DataRef { type_info, ..self }
+	}
+
+	/// Returns the data without its type hint, the inverse of [`with_type_hint`](Self::with_type_hint).
+	///
+	/// The ghost dimensions become the trailing axes of the shape, eg. 1D coordinates with a ghost dimension of 2 become `[[f32; 2]]`.
+	#[inline]
+	pub fn without_type_hint(self) -> DataRef<'a> {
+		let type_info = self.shape.dim() | self.type_info & format::TYPE_PRIM_MASK;
+		DataRef { type_info, ..self }
+	}
+
 	/// Reinterpret the data.
 	///
 	/// The data bytes must have the correct alignment and size or this cast fails.
@@ -48,9 +82,45 @@ impl<'a> DataRef<'a> {
 		unsafe { Some(slice::from_raw_parts(data, len)) }
 	}
 
+	/// Returns an n-dimensional view of the data.
+	///
+	/// The size of `T` must match the primitive type and the data must not be compressed or the view fails, see [`NdView`].
+	#[inline]
+	pub fn view<T: dataview::Pod>(&self) -> Option<NdView<'a, T>> {
+		NdView::new(self.as_slice::<T>()?, self.shape, self.type_info)
+	}
+
+	/// Returns a range of rows along the first axis.
+	///
+	/// Returns `None` if the data is compressed, a scalar or the range is out of bounds.
+	pub fn rows(&self, range: ops::Range<u32>) -> Option<DataRef<'a>> {
+		if self.is_compressed() || range.start > range.end {
+			return None;
+		}
+		let n = range.end - range.start;
+		let (len, shape) = match self.shape {
+			Shape::Scalar => return None,
+			Shape::D1(x) => (x, Shape::D1(n)),
+			Shape::D2(x, y) => (x, Shape::D2(n, y)),
+			Shape::D3(x, y, z) => (x, Shape::D3(n, y, z)),
+		};
+		if range.end > len {
+			return None;
+		}
+		if len == 0 {
+			return Some(*self);
+		}
+		if !self.bytes.len().is_multiple_of(len as usize) {
+			return None;
+		}
+		let row_size = self.bytes.len() / len as usize;
+		let bytes = &self.bytes[range.start as usize * row_size..range.end as usize * row_size];
+		Some(DataRef { bytes, shape, ..*self })
+	}
+
 	/// Returns the data as a printable array.
 	///
-	/// This operation fails if the data is invalid (as_slice fails).
+	/// This operation fails if the data is invalid (view fails).
 	#[inline(never)]
 	pub fn print(&self) -> Result<PrintArray, fmt::Error> {
 		// Cannot print compressed data
@@ -78,61 +148,61 @@ impl<'a> DataRef<'a> {
 		items = match self.type_info & format::TYPE_PRIM_MASK {
 			format::TYPE_PRIM_U8 => {
 				avg_size = 1;
-				let values = self.as_slice::<u8>().ok_or(fmt::Error)?;
+				let values = self.view::<u8>().ok_or(fmt::Error)?;
 				iter_u8 = values.iter().map(|v| v as _);
 				&mut iter_u8
 			},
 			format::TYPE_PRIM_I8 => {
 				avg_size = 1;
-				let values = self.as_slice::<i8>().ok_or(fmt::Error)?;
+				let values = self.view::<i8>().ok_or(fmt::Error)?;
 				iter_i8 = values.iter().map(|v| v as _);
 				&mut iter_i8
 			},
 			format::TYPE_PRIM_U16 => {
 				avg_size = 1;
-				let values = self.as_slice::<u16>().ok_or(fmt::Error)?;
+				let values = self.view::<u16>().ok_or(fmt::Error)?;
 				iter_u16 = values.iter().map(|v| v as _);
 				&mut iter_u16
 			},
 			format::TYPE_PRIM_I16 => {
 				avg_size = 1;
-				let values = self.as_slice::<i16>().ok_or(fmt::Error)?;
+				let values = self.view::<i16>().ok_or(fmt::Error)?;
 				iter_i16 = values.iter().map(|v| v as _);
 				&mut iter_i16
 			},
 			format::TYPE_PRIM_U32 => {
 				avg_size = 2;
-				let values = self.as_slice::<u32>().ok_or(fmt::Error)?;
+				let values = self.view::<u32>().ok_or(fmt::Error)?;
 				iter_u32 = values.iter().map(|v| v as _);
 				&mut iter_u32
 			},
 			format::TYPE_PRIM_I32 => {
 				avg_size = 2;
-				let values = self.as_slice::<i32>().ok_or(fmt::Error)?;
+				let values = self.view::<i32>().ok_or(fmt::Error)?;
 				iter_i32 = values.iter().map(|v| v as _);
 				&mut iter_i32
 			},
 			format::TYPE_PRIM_U64 => {
 				avg_size = 2;
-				let values = self.as_slice::<u64>().ok_or(fmt::Error)?;
+				let values = self.view::<u64>().ok_or(fmt::Error)?;
 				iter_u64 = values.iter().map(|v| v as _);
 				&mut iter_u64
 			},
 			format::TYPE_PRIM_I64 => {
 				avg_size = 2;
-				let values = self.as_slice::<i64>().ok_or(fmt::Error)?;
+				let values = self.view::<i64>().ok_or(fmt::Error)?;
 				iter_i64 = values.iter().map(|v| v as _);
 				&mut iter_i64
 			},
 			format::TYPE_PRIM_F32 => {
 				avg_size = 5;
-				let values = self.as_slice::<f32>().ok_or(fmt::Error)?;
+				let values = self.view::<f32>().ok_or(fmt::Error)?;
 				iter_f32 = values.iter().map(|v| PrintF32::wrap(v) as _);
 				&mut iter_f32
 			},
 			format::TYPE_PRIM_F64 => {
 				avg_size = 8;
-				let values = self.as_slice::<f64>().ok_or(fmt::Error)?;
+				let values = self.view::<f64>().ok_or(fmt::Error)?;
 				iter_f64 = values.iter().map(|v| PrintF64::wrap(v) as _);
 				&mut iter_f64
 			},
@@ -185,4 +255,19 @@ impl<'a> DataRef<'a> {
 		// Not compressed or there was an error decompressing
 		return *self;
 	}
+
+	/// Swaps the byte order of the data into the storage.
+	///
+	/// Compressed data is a byte stream without byte order and is returned as is.
+	pub fn swap_bytes(&self, storage: &'a mut Vec<u64>) -> DataRef<'a> {
+		if self.is_compressed() {
+			return *self;
+		}
+		storage.clear();
+		storage.resize_with(self.bytes.len().div_ceil(8), Default::default);
+		let bytes = &mut dataview::bytes_mut(storage.as_mut_slice())[..self.bytes.len()];
+		bytes.copy_from_slice(self.bytes);
+		endian::swap_data(self.type_info, bytes);
+		DataRef { bytes, ..*self }
+	}
 }
@@ -1,4 +1,4 @@
-use std::mem;
+use core::mem;
 use crate::*;
 
 /// In-memory Dataset.
@@ -8,6 +8,10 @@ pub struct Dataset {
 	pub descs: Vec<format::TableDesc>,
 	pub names: Names,
 	pub storage: Vec<u64>,
+	/// Computes the header and datatable checksums when finalizing.
+	///
+	/// If `false` the checksums are cleared instead.
+	pub checksums: bool,
 }
 
 impl Dataset {
@@ -18,6 +22,7 @@ impl Dataset {
 			descs: Vec::new(),
 			names: Names::default(),
 			storage: Vec::new(),
+			checksums: false,
 		}
 	}
 
@@ -27,6 +32,7 @@ impl Dataset {
 			header: &self.header,
 			storage: &self.storage,
 			names: self.names.as_ref(),
+			padding: &[],
 			tables: &self.descs,
 		}
 	}
@@ -43,6 +49,11 @@ impl Dataset {
 	///
 	/// If another table already exists with the key name returns `false` and does not insert the table.
 	pub fn add_table(&mut self, table_ref: TableRef) -> bool {
+		if self.find_index(table_ref.key_name).is_some() {
+			return false;
+		}
+		self.header.flags &= !format::DatasetHeader::FLAG_SORTED;
+
 		let storage = table_ref.data.bytes;
 		let (mem_start, mem_end) = self.write_data(storage);
 
@@ -66,7 +77,128 @@ impl Dataset {
 			checksum: 0,
 			reserved: [0; 1],
 		});
-		return true;
+		true
+	}
+
+	/// Inserts a new table by name.
+	///
+	/// The name is hashed and registered in the names lookup, panics if a different name with the same hash is registered.
+	/// If another table already exists with the name it is replaced.
+	/// Use the returned builder to set the optional index, related and type names.
+	///
+	/// ```
+	/// use udf::AsDataRef;
+	///
+	/// let mut ds = udf::Dataset::new();
+	/// ds.insert("Points", [1.0f32, 2.0, 3.0].as_data_ref());
+	/// ds.insert("Contours", [0u32, 2].as_data_ref()).index("Points");
+	/// ds.insert("Attributes", [1u32, 0].as_data_ref()).related("Contours").type_name("Attr");
+	///
+	/// // Names are registered once
+	/// assert_eq!(ds.names.len(), 4);
+	/// assert_eq!(ds.descs[1].index_name, udf::hash!("Points"));
+	/// assert_eq!(ds.descs[2].related_name, udf::hash!("Contours"));
+	/// ```
+	pub fn insert(&mut self, name: &str, data: DataRef) -> TableBuilder<'_> {
+		let key_name = self.names.insert(name);
+		let table_ref = TableRef { key_name, data, ..Default::default() };
+		if !self.replace_table(table_ref) {
+			self.add_table(table_ref);
+		}
+		let index = self.find_index(key_name).unwrap();
+		TableBuilder { dataset: self, index }
+	}
+
+	/// Removes a table.
+	///
+	/// The storage of its data is reclaimed.
+	///
+	/// Returns `false` if no table exists with the key name.
+	/// Names are not removed as they may still be referenced by other tables.
+	pub fn remove_table(&mut self, key_name: u32) -> bool {
+		let Some(index) = self.find_index(key_name) else {
+			return false;
+		};
+		let desc = self.descs.remove(index);
+		self.remove_data(desc.mem_start, desc.mem_end);
+		true
+	}
+
+	/// Replaces the data and names of a table.
+	///
+	/// The storage of the old data is reclaimed, the table keeps its position and type name.
+	///
+	/// Returns `false` if no table exists with the key name and does not insert the table.
+	pub fn replace_table(&mut self, table_ref: TableRef) -> bool {
+		let Some(index) = self.find_index(table_ref.key_name) else {
+			return false;
+		};
+		let desc = self.descs[index];
+		self.descs[index].mem_start = 0;
+		self.descs[index].mem_end = 0;
+		self.remove_data(desc.mem_start, desc.mem_end);
+		let (mem_start, mem_end) = self.write_data(table_ref.data.bytes);
+
+		let desc = &mut self.descs[index];
+		desc.type_info = table_ref.data.type_info;
+		desc.compress_info = table_ref.data.compress_info;
+		desc.mem_start = mem_start;
+		desc.mem_end = mem_end;
+		desc.data_size = table_ref.data.bytes.len() as u32;
+		desc.data_shape = table_ref.data.shape.encode();
+		desc.index_name = table_ref.index_name;
+		desc.related_name = table_ref.related_name;
+		true
+	}
+
+	/// Renames a table.
+	///
+	/// Index and related names of other tables referring to the table are renamed as well.
+	/// The new name should be added to the names lookup.
+	///
+	/// Returns `false` if no table exists with the old key name or a table already exists with the new key name.
+	pub fn rename_table(&mut self, old_key_name: u32, new_key_name: u32) -> bool {
+		if self.find_index(new_key_name).is_some() {
+			return false;
+		}
+		let Some(index) = self.find_index(old_key_name) else {
+			return false;
+		};
+		self.descs[index].key_name = new_key_name;
+		self.header.flags &= !format::DatasetHeader::FLAG_SORTED;
+		for desc in &mut self.descs {
+			if desc.index_name == old_key_name {
+				desc.index_name = new_key_name;
+			}
+			if desc.related_name == old_key_name {
+				desc.related_name = new_key_name;
+			}
+		}
+		true
+	}
+
+	fn find_index(&self, key_name: u32) -> Option<usize> {
+		self.descs.iter().position(|desc| desc.key_name == key_name)
+	}
+
+	// Removes the storage blocks and moves the data of the following tables down
+	fn remove_data(&mut self, mem_start: u32, mem_end: u32) {
+		if mem_start >= mem_end || mem_end as usize > self.storage.len() {
+			return;
+		}
+		// Storage shared with other tables is not reclaimed
+		if self.descs.iter().any(|desc| desc.mem_start < mem_end && mem_start < desc.mem_end) {
+			return;
+		}
+
+		self.storage.drain(mem_start as usize..mem_end as usize);
+		let len = mem_end - mem_start;
+		for desc in &mut self.descs {
+			if desc.mem_start >= mem_end {
+				desc.mem_start -= len;
+				desc.mem_end -= len;
+			}
+		}
 	}
 
 	fn write_data(&mut self, storage: &[u8]) -> (u32, u32) {
@@ -88,10 +220,23 @@ impl Dataset {
 		return (old_len as u32, new_len as u32);
 	}
 
+	/// Finalizes the dataset for writing.
+	///
+	/// Sorts the table descriptors by key name and marks the dataset as sorted unless key names are duplicated.
+	/// Computes the header fields and the checksums if enabled.
 	pub fn finalize(&mut self) -> Final<DatasetRef> {
 		self.header.check = format::DatasetHeader::CHECK;
 		self.names.finalize();
 
+		// Sort the table descriptors to enable binary search
+		self.descs.sort_by_key(|desc| desc.key_name);
+		if dataset_ref::is_sorted(&self.descs) {
+			self.header.flags |= format::DatasetHeader::FLAG_SORTED;
+		}
+		else {
+			self.header.flags &= !format::DatasetHeader::FLAG_SORTED;
+		}
+
 		let mut size = 0;
 		size += mem::size_of_val(&self.header);
 		size += mem::size_of_val(&self.descs[..]);
@@ -103,6 +248,19 @@ impl Dataset {
 		self.header.lookup_len = self.names.entries.len() as u16;
 		self.header.string_len = self.names.strings.len() as u16;
 
+		// The header checksum includes the datatable checksums
+		for desc in &mut self.descs {
+			desc.checksum = 0;
+		}
+		self.header.checksum = 0;
+		if self.checksums {
+			for i in 0..self.descs.len() {
+				let checksum = self.as_ref().table_checksum(&self.descs[i]).unwrap_or(0);
+				self.descs[i].checksum = checksum;
+			}
+			self.header.checksum = self.as_ref().header_checksum();
+		}
+
 		Final { inner: self.as_ref() }
 	}
 }
@@ -1,56 +1,96 @@
-use std::{io, mem};
+use alloc::borrow::Cow;
+use core::{any, mem};
+#[cfg(feature = "std")]
+use std::io;
 use crate::*;
 
+/// Dataset parsing mode.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum ParseMode {
+	/// Enforces every requirement of the dataset header.
+	#[default]
+	Strict,
+	/// Only checks what is required to access the dataset safely.
+	///
+	/// Intended for tools salvaging data from damaged files.
+	Lenient,
+}
+
 /// Dataset by reference.
 #[derive(Copy, Clone, Debug)]
 pub struct DatasetRef<'a> {
 	pub header: &'a format::DatasetHeader,
 	pub tables: &'a [format::TableDesc],
 	pub names: NamesRef<'a>,
+	/// Bytes between the strings and the header size, only present in leniently parsed datasets.
+	pub padding: &'a [u8],
 	pub storage: &'a [u64],
 }
 
 impl<'a> DatasetRef<'a> {
 	/// Parses the dataset from the storage itself.
-	pub fn parse(storage: &'a [u64]) -> Result<DatasetRef<'a>, ParseError> {
+	///
+	/// Errors are not associated with a file offset, see [`Error::at`].
+	#[inline]
+	pub fn parse(storage: &'a [u64]) -> Result<DatasetRef<'a>, Error> {
+		Self::parse_with(storage, ParseMode::Strict)
+	}
+
+	/// Parses the dataset from the storage with the given parse mode.
+	pub fn parse_with(storage: &'a [u64], mode: ParseMode) -> Result<DatasetRef<'a>, Error> {
 		let view = dataview::DataView::from(storage);
 		let Some(header) = view.try_get::<format::DatasetHeader>(0) else {
-			return Err(ParseError::OutOfBounds);
+			return Err(out_of_bounds(Field::Header, mem::size_of::<format::DatasetHeader>(), view.len()));
 		};
 
 		if header.size % 8 != 0 {
-			return Err(ParseError::InvalidFormat);
+			return Err(Error::InvalidField {
+				field: Field::HeaderSize,
+				file_offset: format::FileOffset::NULL,
+				expected: Expected::MultipleOf(8),
+				actual: header.size as u64,
+			});
+		}
+
+		if mode == ParseMode::Strict {
+			check_header(header)?;
 		}
 
 		let Some(head) = view.try_slice::<u8>(0, header.size as usize) else {
-			return Err(ParseError::OutOfBounds);
+			return Err(out_of_bounds(Field::HeaderSize, header.size as usize, view.len()));
 		};
 
 		let view = dataview::DataView::from(head);
 
 		let mut offset = mem::size_of_val(header);
+		let descs_size = header.descs_len as usize * mem::size_of::<format::TableDesc>();
 		let Some(descs) = view.try_slice::<format::TableDesc>(offset, header.descs_len as usize) else {
-			return Err(ParseError::OutOfBounds);
+			return Err(out_of_bounds(Field::Descriptors, offset + descs_size, head.len()));
 		};
 
+		if mode == ParseMode::Strict {
+			check_sorted(header, descs)?;
+		}
+
 		offset += mem::size_of_val(descs);
+		let entries_size = header.lookup_len as usize * mem::size_of::<format::LookupEntry>();
 		let Some(entries) = view.try_slice::<format::LookupEntry>(offset, header.lookup_len as usize) else {
-			return Err(ParseError::OutOfBounds);
+			return Err(out_of_bounds(Field::LookupEntries, offset + entries_size, head.len()));
 		};
 
 		offset += mem::size_of_val(entries);
-		let Some(strings) = view.try_slice::<u8>(offset, header.string_len as usize) else {
-			return Err(ParseError::OutOfBounds);
-		};
+		if view.try_slice::<u8>(offset, header.string_len as usize).is_none() {
+			return Err(out_of_bounds(Field::Strings, offset + header.string_len as usize, head.len()));
+		}
+		let strings = &head[offset..offset + header.string_len as usize];
+		let padding = &head[offset + header.string_len as usize..];
 
 		let names = NamesRef { entries, strings };
 
-		let storage = match storage.get(header.size as usize / 8..) {
-			Some(storage) => storage,
-			None => return Err(ParseError::OutOfBounds),
-		};
+		// Header size is a multiple of 8 and fits in the storage
+		let storage = &storage[header.size as usize / 8..];
 
-		return Ok(DatasetRef { header, tables: descs, names, storage });
+		return Ok(DatasetRef { header, tables: descs, names, padding, storage });
 	}
 
 	pub fn to_owned(&self) -> Dataset {
@@ -59,6 +99,7 @@ impl<'a> DatasetRef<'a> {
 			descs: self.tables.to_owned(),
 			names: self.names.to_owned(),
 			storage: self.storage.to_owned(),
+			checksums: self.header.checksum != 0,
 		}
 	}
 
@@ -68,14 +109,26 @@ impl<'a> DatasetRef<'a> {
 		self.tables.len()
 	}
 
+	/// Returns `true` if the table descriptors are sorted by key name.
+	#[inline]
+	pub fn is_sorted(&self) -> bool {
+		self.header.flags & format::DatasetHeader::FLAG_SORTED != 0
+	}
+
 	/// Finds a table descriptor by its key name.
+	///
+	/// Uses binary search if the table descriptors are sorted, otherwise falls back to a linear scan.
 	#[inline]
-	pub fn find_table(&self, key_name: u32) -> Option<&format::TableDesc> {
-		self.tables.iter().find(move |&table| table.key_name == key_name)
-		// match self.tables.binary_search_by_key(&key_name, |table| table.key_name) {
-		// 	Ok(index) => Some(&self.tables[index]),
-		// 	Err(_) => None,
-		// }
+	pub fn find_table(&self, key_name: u32) -> Option<&'a format::TableDesc> {
+		if self.is_sorted() {
+			match self.tables.binary_search_by_key(&key_name, |table| table.key_name) {
+				Ok(index) => Some(&self.tables[index]),
+				Err(_) => None,
+			}
+		}
+		else {
+			self.tables.iter().find(move |&table| table.key_name == key_name)
+		}
 	}
 
 	#[inline]
@@ -88,17 +141,317 @@ impl<'a> DatasetRef<'a> {
 		Some(DataRef { bytes, type_info, compress_info, shape })
 	}
 
+	/// Returns the data of a table as a slice of elements.
+	///
+	/// The primitive type, type hint and ghost dimensions of the table must match the element type, see [`Element`].
+	/// Primitive types can be read from any table with that primitive type, the data is flattened.
+	///
+	/// Compressed data is decompressed into an owned buffer.
+	///
+	/// ```
+	/// use udf::{format, AsDataRef};
+	///
+	/// let points = [format::Coord2F32 { x: 1.0, y: 2.0 }, format::Coord2F32 { x: 3.0, y: 4.0 }];
+	/// let mut ds = udf::Dataset::new();
+	/// ds.insert("Points", points.as_data_ref());
+	///
+	/// let ds = ds.as_ref();
+	/// assert_eq!(ds.get::<format::Coord2F32>("Points").unwrap()[1].y, 4.0);
+	/// assert_eq!(&*ds.get::<f32>("Points").unwrap(), &[1.0, 2.0, 3.0, 4.0]);
+	/// assert!(ds.get::<format::RangeU32>("Points").is_err());
+	/// ```
+	pub fn get<T: Element>(&self, name: &str) -> Result<Cow<'a, [T]>, Error> {
+		let (key_name, data) = self.find_data(name)?;
+		element::read(key_name, data)
+	}
+
+	/// Returns the data of a scalar table.
+	///
+	/// See [`get`](Self::get) for the requirements of the element type.
+	pub fn get_scalar<T: Element>(&self, name: &str) -> Result<T, Error> {
+		let (key_name, data) = self.find_data(name)?;
+		if data.type_info & format::TYPE_DIM_MASK != format::TYPE_DIM_SCALAR {
+			return Err(Error::TypeMismatch { file_offset: format::FileOffset::NULL, key_name, type_info: data.type_info, shape: data.shape, expected: any::type_name::<T>() });
+		}
+		let values = element::read::<T>(key_name, data)?;
+		match values.first() {
+			Some(&value) if values.len() == 1 => Ok(value),
+			_ => Err(Error::InvalidTableData { file_offset: format::FileOffset::NULL, key_name }),
+		}
+	}
+
+	/// Returns the text of a scalar text table.
+	///
+	/// UTF-8 text has the `u8` or `i8` primitive type, UTF-16 and UTF-32 text is decoded into an owned string.
+	pub fn get_text(&self, name: &str) -> Result<Cow<'a, str>, Error> {
+		let (key_name, data) = self.find_data(name)?;
+		element::read_text(key_name, data)
+	}
+
+	/// Dereferences an index or range table against its target table in this dataset.
+	///
+	/// See [`Indexed`], use [`Walk::get_indexed`] or [`Resolved::get_indexed`] to find the target table in parent datasets.
+	pub fn get_indexed<T: Element>(&self, name: &str) -> Result<Indexed<'a, T>, Error> {
+		Indexed::new([*self], name)
+	}
+
+	/// Returns the chain of tables related to the named table.
+	///
+	/// See [`Related`], the related tables must have the same shape.
+	pub fn related(&self, name: &str) -> Result<Related<'a>, Error> {
+		self.related_key(self.names.key_name(name))
+	}
+
+	/// Returns the chain of tables related to the table by its key name.
+	#[inline]
+	pub fn related_key(&self, key_name: u32) -> Result<Related<'a>, Error> {
+		related::related(self, key_name)
+	}
+
+	/// Returns the data of a table converted to `T`.
+	///
+	/// See [`FromDataRef`] for the supported types.
+	///
+	/// ```
+	/// use udf::AsDataRef;
+	///
+	/// let mut ds = udf::Dataset::new();
+	/// ds.insert("Grid", [[1.0f32, 2.0], [3.0, 4.0]].as_data_ref());
+	/// ds.insert("Name", "Hello".as_data_ref());
+	/// ds.finalize();
+	///
+	/// let ds = ds.as_ref();
+	/// assert_eq!(ds.read::<Vec<[f32; 2]>>("Grid").unwrap(), [[1.0, 2.0], [3.0, 4.0]]);
+	/// assert_eq!(ds.read::<&str>("Name").unwrap(), "Hello");
+	/// assert!(ds.read::<Vec<[f32; 3]>>("Grid").is_err());
+	/// ```
+	pub fn read<T: FromDataRef<'a>>(&self, name: &str) -> Result<T, Error> {
+		self.read_key(self.names.key_name(name))
+	}
+
+	/// Returns the data of a table converted to `T` by its key name.
+	///
+	/// See [`read`](Self::read), the key name can be computed at compile time with [`hash!`].
+	pub fn read_key<T: FromDataRef<'a>>(&self, key_name: u32) -> Result<T, Error> {
+		let data = self.find_data_key(key_name)?;
+		T::from_data_ref(&data).map_err(|err| err.with_key_name(key_name))
+	}
+
+	/// Returns the data of a table with the type hint converted to `T`.
+	///
+	/// The ghost dimensions of the type hint become the trailing axes of the shape, see [`DataRef::without_type_hint`].
+	///
+	/// ```
+	/// use udf::{format, AsDataRef};
+	///
+	/// let mut ds = udf::Dataset::new();
+	/// ds.insert("Points", [[1.0f32, 2.0], [3.0, 4.0]].as_data_ref().with_type_hint(format::TYPE_HINT_COORD));
+	/// ds.finalize();
+	///
+	/// let ds = ds.as_ref();
+	/// assert_eq!(ds.read_hint::<Vec<[f32; 2]>>("Points", format::TYPE_HINT_COORD).unwrap(), [[1.0, 2.0], [3.0, 4.0]]);
+	/// assert!(ds.read::<Vec<[f32; 2]>>("Points").is_err());
+	/// ```
+	pub fn read_hint<T: FromDataRef<'a>>(&self, name: &str, type_hint: u16) -> Result<T, Error> {
+		self.read_hint_key(self.names.key_name(name), type_hint)
+	}
+
+	/// Returns the data of a table with the type hint converted to `T` by its key name.
+	///
+	/// Returns [`Error::TypeMismatch`] if the table does not have the type hint.
+	pub fn read_hint_key<T: FromDataRef<'a>>(&self, key_name: u32, type_hint: u16) -> Result<T, Error> {
+		let data = self.find_data_key(key_name)?;
+		if data.type_info & format::TYPE_HINT_MASK != type_hint & format::TYPE_HINT_MASK {
+			return Err(element::mismatch(key_name, &data, any::type_name::<T>()));
+		}
+		T::from_data_ref(&data.without_type_hint()).map_err(|err| err.with_key_name(key_name))
+	}
+
+	fn find_data(&self, name: &str) -> Result<(u32, DataRef<'a>), Error> {
+		let key_name = self.names.key_name(name);
+		let data = self.find_data_key(key_name)?;
+		Ok((key_name, data))
+	}
+
+	fn find_data_key(&self, key_name: u32) -> Result<DataRef<'a>, Error> {
+		let Some(table) = self.find_table(key_name) else {
+			return Err(Error::TableNotFound { file_offset: format::FileOffset::NULL, key_name });
+		};
+		let Some(data) = self.get_data_ref(table) else {
+			let end = table.mem_end as usize * 8;
+			return Err(out_of_bounds(Field::TableData, end, mem::size_of_val(self.storage)));
+		};
+		Ok(data)
+	}
+
+	/// Verifies the data against the table checksum and returns the data.
+	///
+	/// Tables without checksum are not verified.
+	pub fn get_data_ref_verified(&self, table: &format::TableDesc) -> Result<DataRef<'a>, Error> {
+		let Some(data) = self.get_data_ref(table) else {
+			let end = table.mem_end as usize * 8;
+			return Err(out_of_bounds(Field::TableData, end, mem::size_of_val(self.storage)));
+		};
+		if table.checksum != 0 {
+			let actual = self.table_checksum(table).unwrap_or(0);
+			if actual != table.checksum {
+				return Err(Error::ChecksumMismatch {
+					field: Field::TableData,
+					file_offset: format::FileOffset::NULL,
+					key_name: table.key_name,
+					expected: table.checksum,
+					actual,
+				});
+			}
+		}
+		Ok(data)
+	}
+
+	/// Computes the checksum of the header.
+	///
+	/// The checksum covers the header bytes from the `id` field up to the header size:
+	/// the header after the checksum field, the table descriptors, the names and their nul padding.
+	/// It is computed over the little-endian representation.
+	pub fn header_checksum(&self) -> u32 {
+		let mut crc = Crc32c::new();
+		crc.update(&dataview::bytes(&endian::to_le(*self.header))[8..]);
+		if endian::NATIVE {
+			crc.update(dataview::bytes(self.tables));
+			crc.update(dataview::bytes(self.names.entries));
+		}
+		else {
+			for table in self.tables {
+				crc.update(dataview::bytes(&endian::to_le(*table)));
+			}
+			for entry in self.names.entries {
+				crc.update(dataview::bytes(&endian::to_le(*entry)));
+			}
+		}
+		crc.update(self.names.strings);
+		crc.update(self.padding);
+		crc.finish()
+	}
+
+	/// Computes the checksum of the memory block of a table.
+	///
+	/// It is computed over the little-endian representation.
+	/// Returns `None` if the memory block is out of bounds.
+	pub fn table_checksum(&self, table: &format::TableDesc) -> Option<u32> {
+		let storage = self.storage.get(table.mem_start as usize..table.mem_end as usize)?;
+		if endian::NATIVE || table.compress_info != format::COMPRESS_NONE {
+			return Some(crc32c(dataview::bytes(storage)));
+		}
+		let mut block = storage.to_vec();
+		let bytes = dataview::bytes_mut(block.as_mut_slice());
+		let len = usize::min(table.data_size as usize, bytes.len());
+		endian::swap_data(table.type_info, &mut bytes[..len]);
+		Some(crc32c(bytes))
+	}
+
+	/// Verifies the header checksum.
+	///
+	/// Succeeds if the header has no checksum.
+	pub fn verify_checksum(&self) -> Result<(), Error> {
+		if self.header.checksum != 0 {
+			let actual = self.header_checksum();
+			if actual != self.header.checksum {
+				return Err(Error::ChecksumMismatch {
+					field: Field::Checksum,
+					file_offset: format::FileOffset::NULL,
+					key_name: 0,
+					expected: self.header.checksum,
+					actual,
+				});
+			}
+		}
+		Ok(())
+	}
+
+	/// Verifies the header and all the table checksums.
+	pub fn verify_checksums(&self) -> Result<(), Error> {
+		self.verify_checksum()?;
+		for table in self.tables {
+			self.get_data_ref_verified(table)?;
+		}
+		Ok(())
+	}
+
 	/// Returns the file size in bytes that this dataset requires.
 	#[inline]
 	pub fn file_size(&self) -> usize {
-		mem::size_of_val(self.header) + mem::size_of_val(self.tables) + self.names.file_size() + mem::size_of_val(self.storage)
+		mem::size_of_val(self.header) + mem::size_of_val(self.tables) + self.names.file_size() + self.padding.len() + mem::size_of_val(self.storage)
 	}
 
+	#[cfg(feature = "std")]
 	pub fn write(&self, w: &mut dyn io::Write) -> io::Result<()> {
+		if !endian::NATIVE {
+			// Convert a copy of the dataset to little-endian
+			let mut bytes = Vec::new();
+			self.write_native(&mut bytes)?;
+			let mut storage = vec![0u64; bytes.len() / 8];
+			dataview::bytes_mut(storage.as_mut_slice()).copy_from_slice(&bytes);
+			endian::dataset_to_le(&mut storage)?;
+			return w.write_all(dataview::bytes(storage.as_slice()));
+		}
+		self.write_native(w)
+	}
+
+	#[cfg(feature = "std")]
+	fn write_native(&self, w: &mut dyn io::Write) -> io::Result<()> {
 		w.write_all(dataview::bytes(self.header))?;
 		w.write_all(dataview::bytes(&self.tables[..]))?;
 		self.names.write(w)?;
+		w.write_all(self.padding)?;
 		w.write_all(dataview::bytes(&self.storage[..]))?;
 		Ok(())
 	}
 }
+
+#[inline]
+fn out_of_bounds(field: Field, end: usize, size: usize) -> Error {
+	Error::OutOfBounds { field, file_offset: format::FileOffset::NULL, end: end as u64, size: size as u64 }
+}
+
+fn invalid_field(field: Field, expected: Expected, actual: u64) -> Error {
+	Error::InvalidField { field, file_offset: format::FileOffset::NULL, expected, actual }
+}
+
+// Checks every requirement of the dataset header
+fn check_header(header: &format::DatasetHeader) -> Result<(), Error> {
+	if header.check != format::DatasetHeader::CHECK {
+		return Err(invalid_field(Field::Check, Expected::Equal(format::DatasetHeader::CHECK as u64), header.check as u64));
+	}
+	if !utils::is_printable_id(&header.id) {
+		return Err(invalid_field(Field::Id, Expected::Printable, u32::from_le_bytes(header.id) as u64));
+	}
+	if !header.string_len.is_multiple_of(8) {
+		return Err(invalid_field(Field::StringLen, Expected::MultipleOf(8), header.string_len as u64));
+	}
+	let size = mem::size_of::<format::DatasetHeader>()
+		+ header.descs_len as usize * mem::size_of::<format::TableDesc>()
+		+ header.lookup_len as usize * mem::size_of::<format::LookupEntry>()
+		+ header.string_len as usize;
+	if header.size as usize != size {
+		return Err(invalid_field(Field::HeaderSize, Expected::Equal(size as u64), header.size as u64));
+	}
+	if header.flags & !format::DatasetHeader::FLAGS_MASK != 0 {
+		let expected = header.flags & format::DatasetHeader::FLAGS_MASK;
+		return Err(invalid_field(Field::Flags, Expected::Equal(expected as u64), header.flags as u64));
+	}
+	if header.reserved != [0; 1] {
+		return Err(invalid_field(Field::Reserved, Expected::Equal(0), header.reserved[0] as u64));
+	}
+	Ok(())
+}
+
+// Sorted tables must have strictly ascending key names for binary search
+fn check_sorted(header: &format::DatasetHeader, tables: &[format::TableDesc]) -> Result<(), Error> {
+	if header.flags & format::DatasetHeader::FLAG_SORTED != 0 && !is_sorted(tables) {
+		return Err(invalid_field(Field::Flags, Expected::Sorted, header.flags as u64));
+	}
+	Ok(())
+}
+
+pub(crate) fn is_sorted(tables: &[format::TableDesc]) -> bool {
+	tables.windows(2).all(|pair| pair[0].key_name < pair[1].key_name)
+}
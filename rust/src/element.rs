@@ -0,0 +1,209 @@
+/*!
+Typed datatable elements.
+*/
+
+use alloc::borrow::Cow;
+use core::{any, mem, str};
+use crate::*;
+
+/// Types which can be read from datatables.
+///
+/// The inverse of [`AsDataRef`], see [`DatasetRef::get`].
+pub trait Element: dataview::Pod + Copy {
+	/// Primitive type of the element.
+	const PRIM: u16;
+	/// Type hint of the element.
+	///
+	/// Primitive types use [`TYPE_HINT_NONE`](format::TYPE_HINT_NONE) and can be read from datatables with any type hint.
+	const HINT: u16;
+	/// Lengths of the ghost dimensions of a single element.
+	const GHOST: &'static [u32];
+}
+
+macro_rules! impl_element {
+	($ty:ty, $hint:ident, $prim:ident, [$($ghost:literal),*]) => {
+		impl Element for $ty {
+			const PRIM: u16 = format::$prim;
+			const HINT: u16 = format::$hint;
+			const GHOST: &'static [u32] = &[$($ghost),*];
+		}
+	};
+}
+
+impl_element!(u8, TYPE_HINT_NONE, TYPE_PRIM_U8, []);
+impl_element!(i8, TYPE_HINT_NONE, TYPE_PRIM_I8, []);
+impl_element!(u16, TYPE_HINT_NONE, TYPE_PRIM_U16, []);
+impl_element!(i16, TYPE_HINT_NONE, TYPE_PRIM_I16, []);
+impl_element!(u32, TYPE_HINT_NONE, TYPE_PRIM_U32, []);
+impl_element!(i32, TYPE_HINT_NONE, TYPE_PRIM_I32, []);
+impl_element!(u64, TYPE_HINT_NONE, TYPE_PRIM_U64, []);
+impl_element!(i64, TYPE_HINT_NONE, TYPE_PRIM_I64, []);
+impl_element!(f32, TYPE_HINT_NONE, TYPE_PRIM_F32, []);
+impl_element!(f64, TYPE_HINT_NONE, TYPE_PRIM_F64, []);
+
+impl_element!(format::FileOffset, TYPE_HINT_DATASET, TYPE_PRIM_U64, [2]);
+impl_element!(format::IndexU32, TYPE_HINT_INDEX, TYPE_PRIM_U32, []);
+impl_element!(format::Index2U32, TYPE_HINT_INDEX, TYPE_PRIM_U32, [2]);
+impl_element!(format::Index3U32, TYPE_HINT_INDEX, TYPE_PRIM_U32, [3]);
+impl_element!(format::RangeU32, TYPE_HINT_RANGE, TYPE_PRIM_U32, [2]);
+impl_element!(format::Coord2F32, TYPE_HINT_COORD, TYPE_PRIM_F32, [2]);
+impl_element!(format::Coord3F32, TYPE_HINT_COORD, TYPE_PRIM_F32, [3]);
+impl_element!(format::HatchF32, TYPE_HINT_HATCH, TYPE_PRIM_F32, [4]);
+impl_element!(format::Transform2F32, TYPE_HINT_TRANSFORM, TYPE_PRIM_F32, [2, 3]);
+impl_element!(format::Transform3F32, TYPE_HINT_TRANSFORM, TYPE_PRIM_F32, [3, 4]);
+
+// Checks the primitive type, type hint and ghost dimensions against the element type
+fn matches<T: Element>(type_info: u16, shape: [u32; 2]) -> bool {
+	if type_info & format::TYPE_PRIM_MASK != T::PRIM {
+		return false;
+	}
+	// Primitives can be read from any table
+	if T::HINT == format::TYPE_HINT_NONE {
+		return true;
+	}
+	if type_info & format::TYPE_HINT_MASK != T::HINT {
+		return false;
+	}
+	// Typed elements are read from scalar and 1D tables followed by their ghost dimensions
+	let (dims, axes) = Shape::decode_axes(type_info, shape);
+	if dims > 1 {
+		return false;
+	}
+	let ghost = &axes[dims..];
+	ghost.iter().enumerate().all(|(index, &len)| len == T::GHOST.get(index).copied().unwrap_or(0))
+}
+
+/// Reads the data of a table as elements.
+pub(crate) fn read<'a, T: Element>(key_name: u32, data: DataRef<'a>) -> Result<Cow<'a, [T]>, Error> {
+	if !matches::<T>(data.type_info, data.shape.encode()) {
+		return Err(mismatch(key_name, &data, any::type_name::<T>()));
+	}
+	// The number of primitives per element
+	let ghost_len = T::GHOST.iter().product::<u32>() as usize;
+	cast(key_name, data, data.shape.len() / ghost_len)
+}
+
+/// Reads the data of a table as arrays of primitives.
+///
+/// The table has the dimensions of the array with the given inner axes.
+pub(crate) fn read_array<'a, T: Element, A: dataview::Pod + Copy>(key_name: u32, data: DataRef<'a>, inner: &[u32]) -> Result<Cow<'a, [A]>, Error> {
+	let type_info = data.type_info;
+	let dims = ((type_info & format::TYPE_DIM_MASK) >> 4) as usize;
+	let hint = type_info & format::TYPE_HINT_MASK;
+	let axes = data.shape.encode();
+	let axes = [axes[0], axes[1] & 0xffffff, axes[1] >> 24];
+	let matches = type_info & format::TYPE_PRIM_MASK == T::PRIM
+		&& (T::HINT == format::TYPE_HINT_NONE || hint == T::HINT)
+		&& T::GHOST.is_empty()
+		&& dims == inner.len() + 1
+		&& axes[1..dims] == *inner
+		&& axes[dims..].iter().all(|&len| len == 0);
+	if !matches {
+		return Err(mismatch(key_name, &data, any::type_name::<A>()));
+	}
+	cast(key_name, data, axes[0] as usize)
+}
+
+// Reinterprets the data as elements, decompressing into an owned buffer when needed
+fn cast<'a, A: dataview::Pod + Copy>(key_name: u32, data: DataRef<'a>, len: usize) -> Result<Cow<'a, [A]>, Error> {
+	let invalid = || Error::InvalidTableData { file_offset: format::FileOffset::NULL, key_name };
+
+	if data.is_compressed() {
+		let mut storage = Vec::new();
+		let decompressed = data.decompress(&mut storage);
+		if decompressed.is_compressed() {
+			return Err(invalid());
+		}
+		let values = decompressed.as_slice::<A>().and_then(|values| values.get(..len)).ok_or_else(invalid)?;
+		return Ok(Cow::Owned(values.to_vec()));
+	}
+
+	match data.as_slice::<A>() {
+		Some(values) if values.len() == len && len * mem::size_of::<A>() == data.bytes.len() => Ok(Cow::Borrowed(values)),
+		_ => Err(invalid()),
+	}
+}
+
+/// Reads the text of a table.
+pub(crate) fn read_text<'a>(key_name: u32, data: DataRef<'a>) -> Result<Cow<'a, str>, Error> {
+	let type_info = data.type_info;
+	if type_info & (format::TYPE_HINT_MASK | format::TYPE_DIM_MASK) != format::TYPE_HINT_TEXT | format::TYPE_DIM_SCALAR || data.is_compressed() {
+		return Err(mismatch(key_name, &data, "str"));
+	}
+	let invalid = || Error::InvalidTableData { file_offset: format::FileOffset::NULL, key_name };
+	match type_info & format::TYPE_PRIM_MASK {
+		format::TYPE_PRIM_I8 | format::TYPE_PRIM_U8 => {
+			let text = str::from_utf8(data.bytes).map_err(|_| invalid())?;
+			Ok(Cow::Borrowed(text))
+		},
+		format::TYPE_PRIM_U16 => {
+			let units = data.as_slice::<u16>().ok_or_else(invalid)?;
+			let text = String::from_utf16(units).map_err(|_| invalid())?;
+			Ok(Cow::Owned(text))
+		},
+		format::TYPE_PRIM_U32 => {
+			let chars = data.as_slice::<u32>().ok_or_else(invalid)?;
+			let text = chars.iter().map(|&chr| char::from_u32(chr)).collect::<Option<String>>().ok_or_else(invalid)?;
+			Ok(Cow::Owned(text))
+		},
+		_ => Err(mismatch(key_name, &data, "str")),
+	}
+}
+
+/// Reads the rows of a text table.
+///
+/// Every row is padded with nul characters to the length of the longest row.
+pub(crate) fn read_text_list(key_name: u32, data: DataRef<'_>) -> Result<Vec<String>, Error> {
+	let type_info = data.type_info;
+	if type_info & (format::TYPE_HINT_MASK | format::TYPE_DIM_MASK) != format::TYPE_HINT_TEXT | format::TYPE_DIM_1D || data.is_compressed() {
+		return Err(mismatch(key_name, &data, "Vec<String>"));
+	}
+	let (rows, width) = match data.shape {
+		Shape::D1(rows) => (rows as usize, 0),
+		Shape::D2(rows, width) => (rows as usize, width as usize),
+		_ => return Err(mismatch(key_name, &data, "Vec<String>")),
+	};
+	let invalid = || Error::InvalidTableData { file_offset: format::FileOffset::NULL, key_name };
+	let mut strings = Vec::with_capacity(rows);
+	match type_info & format::TYPE_PRIM_MASK {
+		format::TYPE_PRIM_I8 | format::TYPE_PRIM_U8 => {
+			let units = data.bytes.get(..rows * width).ok_or_else(invalid)?;
+			for row in units.chunks(width.max(1)).take(rows) {
+				let text = str::from_utf8(trim_nul(row)).map_err(|_| invalid())?;
+				strings.push(String::from(text));
+			}
+		},
+		format::TYPE_PRIM_U16 => {
+			let units = data.as_slice::<u16>().and_then(|units| units.get(..rows * width)).ok_or_else(invalid)?;
+			for row in units.chunks(width.max(1)).take(rows) {
+				strings.push(String::from_utf16(trim_nul(row)).map_err(|_| invalid())?);
+			}
+		},
+		format::TYPE_PRIM_U32 => {
+			let units = data.as_slice::<u32>().and_then(|units| units.get(..rows * width)).ok_or_else(invalid)?;
+			for row in units.chunks(width.max(1)).take(rows) {
+				let text = trim_nul(row).iter().map(|&chr| char::from_u32(chr)).collect::<Option<String>>().ok_or_else(invalid)?;
+				strings.push(text);
+			}
+		},
+		_ => return Err(mismatch(key_name, &data, "Vec<String>")),
+	}
+	// Rows of empty strings
+	strings.resize(rows, String::new());
+	Ok(strings)
+}
+
+fn trim_nul<T: Copy + Default + PartialEq>(row: &[T]) -> &[T] {
+	let len = row.iter().rposition(|&unit| unit != T::default()).map_or(0, |index| index + 1);
+	&row[..len]
+}
+
+pub(crate) fn mismatch(key_name: u32, data: &DataRef, expected: &'static str) -> Error {
+	Error::TypeMismatch {
+		file_offset: format::FileOffset::NULL,
+		key_name,
+		type_info: data.type_info,
+		shape: data.shape,
+		expected,
+	}
+}
@@ -0,0 +1,207 @@
+/*!
+Byte order conversion.
+
+The UDF file format is little-endian.
+On big-endian hosts datasets are converted to the host byte order when read and back to little-endian when written.
+Datasets in memory are always in the host byte order.
+*/
+
+use core::mem;
+use crate::*;
+
+/// Whether the host byte order is the little-endian byte order of the file format.
+pub const NATIVE: bool = cfg!(target_endian = "little");
+
+/// Disk format structs with byte order conversion.
+pub trait SwapBytes: Copy {
+	/// Swaps the byte order of every field.
+	fn swap_bytes(&mut self);
+}
+
+impl SwapBytes for format::UdfHeader {
+	fn swap_bytes(&mut self) {
+		self.next = self.next.swap_bytes();
+		self.root.swap_bytes();
+		for reserved in &mut self.reserved {
+			*reserved = reserved.swap_bytes();
+		}
+	}
+}
+
+impl SwapBytes for format::CommitRecord {
+	fn swap_bytes(&mut self) {
+		self.checksum = self.checksum.swap_bytes();
+		self.seq = self.seq.swap_bytes();
+		self.root.swap_bytes();
+		self.prev = self.prev.swap_bytes();
+		self.reserved = self.reserved.swap_bytes();
+	}
+}
+
+impl SwapBytes for format::FileOffset {
+	fn swap_bytes(&mut self) {
+		self.offset = self.offset.swap_bytes();
+		self.size = self.size.swap_bytes();
+	}
+}
+
+impl SwapBytes for format::DatasetHeader {
+	fn swap_bytes(&mut self) {
+		self.check = self.check.swap_bytes();
+		self.checksum = self.checksum.swap_bytes();
+		self.size = self.size.swap_bytes();
+		self.descs_len = self.descs_len.swap_bytes();
+		self.lookup_len = self.lookup_len.swap_bytes();
+		self.string_len = self.string_len.swap_bytes();
+		self.flags = self.flags.swap_bytes();
+		self.reserved[0] = self.reserved[0].swap_bytes();
+	}
+}
+
+impl SwapBytes for format::TableDesc {
+	fn swap_bytes(&mut self) {
+		self.key_name = self.key_name.swap_bytes();
+		self.type_info = self.type_info.swap_bytes();
+		self.compress_info = self.compress_info.swap_bytes();
+		self.mem_start = self.mem_start.swap_bytes();
+		self.mem_end = self.mem_end.swap_bytes();
+		self.data_size = self.data_size.swap_bytes();
+		self.data_shape[0] = self.data_shape[0].swap_bytes();
+		self.data_shape[1] = self.data_shape[1].swap_bytes();
+		self.index_name = self.index_name.swap_bytes();
+		self.related_name = self.related_name.swap_bytes();
+		self.type_name = self.type_name.swap_bytes();
+		self.checksum = self.checksum.swap_bytes();
+		self.reserved[0] = self.reserved[0].swap_bytes();
+	}
+}
+
+impl SwapBytes for format::LookupEntry {
+	fn swap_bytes(&mut self) {
+		self.hash = self.hash.swap_bytes();
+		self.offset = self.offset.swap_bytes();
+		self.len = self.len.swap_bytes();
+	}
+}
+
+/// Converts the value from the file byte order to the host byte order.
+#[inline]
+pub fn from_le<T: SwapBytes>(mut value: T) -> T {
+	if !NATIVE {
+		value.swap_bytes();
+	}
+	value
+}
+
+/// Converts the value from the host byte order to the file byte order.
+#[inline]
+pub fn to_le<T: SwapBytes>(value: T) -> T {
+	from_le(value)
+}
+
+/// Swaps the byte order of the primitives in the data bytes.
+///
+/// Custom primitives are bytes and left as is.
+pub fn swap_data(type_info: u16, bytes: &mut [u8]) {
+	let size = format::type_prim_align(type_info);
+	if size > 1 {
+		for prim in bytes.chunks_exact_mut(size) {
+			prim.reverse();
+		}
+	}
+}
+
+/// Swaps the byte order of a dataset in place.
+///
+/// The byte order of the dataset is detected from its check value, it is converted in either direction.
+/// The strings are left as is, uncompressed table data is swapped per primitive and compressed data is left as is.
+///
+/// Returns an error if the check value is invalid or the dataset is out of bounds, the dataset is not modified.
+pub fn swap_dataset(storage: &mut [u64]) -> Result<(), Error> {
+	let check = dataview::DataView::from(&*storage).try_read::<format::DatasetHeader>(0).map(|header| header.check);
+	match check {
+		Some(format::DatasetHeader::CHECK) => swap_dataset_(storage, false),
+		Some(check) if check.swap_bytes() == format::DatasetHeader::CHECK => swap_dataset_(storage, true),
+		_ => Err(Error::InvalidField {
+			field: Field::Check,
+			file_offset: format::FileOffset::NULL,
+			expected: Expected::Equal(format::DatasetHeader::CHECK as u64),
+			actual: check.unwrap_or(0) as u64,
+		}),
+	}
+}
+
+// Converts the dataset read from the file to the host byte order
+#[cfg(feature = "std")]
+#[inline]
+pub(crate) fn dataset_from_le(storage: &mut [u64]) -> Result<(), Error> {
+	if !NATIVE {
+		swap_dataset_(storage, true)?;
+	}
+	Ok(())
+}
+
+// Converts the dataset in the host byte order to the file byte order
+#[cfg(feature = "std")]
+#[inline]
+pub(crate) fn dataset_to_le(storage: &mut [u64]) -> Result<(), Error> {
+	if !NATIVE {
+		swap_dataset_(storage, false)?;
+	}
+	Ok(())
+}
+
+// The fields are read before swapping unless the dataset is in the swapped byte order
+fn swap_dataset_(storage: &mut [u64], swapped: bool) -> Result<(), Error> {
+	let out_of_bounds = |field, end: usize, size: usize| Error::OutOfBounds {
+		field,
+		file_offset: format::FileOffset::NULL,
+		end: end as u64,
+		size: size as u64,
+	};
+	let read = |mut value: format::TableDesc| { if swapped { value.swap_bytes() } value };
+
+	let view = dataview::DataView::from_mut(storage);
+	let Some(mut header) = view.try_read::<format::DatasetHeader>(0) else {
+		return Err(out_of_bounds(Field::Header, mem::size_of::<format::DatasetHeader>(), view.len()));
+	};
+	if swapped {
+		header.swap_bytes();
+	}
+
+	// Check the bounds before modifying the dataset
+	let header_size = header.size as usize;
+	let descs_offset = mem::size_of::<format::DatasetHeader>();
+	let entries_offset = descs_offset + header.descs_len as usize * mem::size_of::<format::TableDesc>();
+	let entries_end = entries_offset + header.lookup_len as usize * mem::size_of::<format::LookupEntry>();
+	if !header_size.is_multiple_of(8) || header_size > view.len() {
+		return Err(out_of_bounds(Field::HeaderSize, header_size, view.len()));
+	}
+	if entries_end > header_size {
+		return Err(out_of_bounds(Field::Descriptors, entries_end, header_size));
+	}
+	let data_len = view.len() - header_size;
+	for i in 0..header.descs_len as usize {
+		let desc = read(view.read::<format::TableDesc>(descs_offset + i * mem::size_of::<format::TableDesc>()));
+		let end = desc.mem_start as usize * 8 + desc.data_size as usize;
+		if desc.mem_start > desc.mem_end || end > data_len {
+			return Err(out_of_bounds(Field::TableData, end, data_len));
+		}
+	}
+
+	// Swap the table data while the descriptors are readable
+	for i in 0..header.descs_len as usize {
+		let offset = descs_offset + i * mem::size_of::<format::TableDesc>();
+		let desc = read(view.read::<format::TableDesc>(offset));
+		if desc.compress_info == format::COMPRESS_NONE {
+			let start = header_size + desc.mem_start as usize * 8;
+			swap_data(desc.type_info, view.slice_mut::<u8>(start, desc.data_size as usize));
+		}
+		view.get_mut::<format::TableDesc>(offset).swap_bytes();
+	}
+	for i in 0..header.lookup_len as usize {
+		view.get_mut::<format::LookupEntry>(entries_offset + i * mem::size_of::<format::LookupEntry>()).swap_bytes();
+	}
+	view.get_mut::<format::DatasetHeader>(0).swap_bytes();
+	Ok(())
+}
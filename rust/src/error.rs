@@ -0,0 +1,324 @@
+/*!
+Errors reading and writing UDF files.
+*/
+
+use core::fmt;
+#[cfg(feature = "std")]
+use std::{error, io};
+use crate::*;
+
+/// Fields of the file format referred to by errors.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Field {
+	/// The file header magic.
+	Magic,
+	/// The file offset of a dataset.
+	FileOffset,
+	/// The file or dataset header.
+	Header,
+	/// The dataset header check value.
+	Check,
+	/// The dataset identifier.
+	Id,
+	/// The dataset header size.
+	HeaderSize,
+	/// The dataset header string length.
+	StringLen,
+	/// The dataset header flags.
+	Flags,
+	/// The dataset header reserved field.
+	Reserved,
+	/// The datatable descriptors.
+	Descriptors,
+	/// The string lookup entries.
+	LookupEntries,
+	/// The strings following the lookup entries.
+	Strings,
+	/// The dataset header checksum.
+	Checksum,
+	/// The datatable data.
+	TableData,
+	/// A commit record.
+	Commit,
+}
+
+impl fmt::Display for Field {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let name = match self {
+			Field::Magic => "magic",
+			Field::FileOffset => "file offset",
+			Field::Header => "header",
+			Field::Check => "check",
+			Field::Id => "id",
+			Field::HeaderSize => "header_size",
+			Field::StringLen => "string_len",
+			Field::Flags => "flags",
+			Field::Reserved => "reserved",
+			Field::Descriptors => "descriptors",
+			Field::LookupEntries => "lookup entries",
+			Field::Strings => "strings",
+			Field::Checksum => "checksum",
+			Field::TableData => "table data",
+			Field::Commit => "commit record",
+		};
+		f.pad(name)
+	}
+}
+
+/// Expected value of a field.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Expected {
+	/// Must be equal to the value.
+	Equal(u64),
+	/// Must be a multiple of the value.
+	MultipleOf(u64),
+	/// Must not be zero.
+	NonZero,
+	/// Must be printable ASCII padded with nul bytes.
+	Printable,
+	/// The datatable descriptors must be sorted by key name.
+	Sorted,
+}
+
+impl fmt::Display for Expected {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Expected::Equal(value) => write!(f, "{:#x}", value),
+			Expected::MultipleOf(value) => write!(f, "a multiple of {}", value),
+			Expected::NonZero => f.pad("non-zero"),
+			Expected::Printable => f.pad("printable ascii"),
+			Expected::Sorted => f.pad("descriptors sorted by key_name"),
+		}
+	}
+}
+
+/// Errors reading and writing UDF files.
+///
+/// The file offset is [`NULL`](format::FileOffset::NULL) when the error is not associated with a dataset in a file,
+/// eg. when parsing a dataset directly from memory.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+	/// The storage backend failed.
+	#[cfg(feature = "std")]
+	Io(io::Error),
+	/// A field does not have the expected value.
+	InvalidField {
+		field: Field,
+		file_offset: format::FileOffset,
+		expected: Expected,
+		actual: u64,
+	},
+	/// A field extends beyond the available bytes.
+	OutOfBounds {
+		field: Field,
+		file_offset: format::FileOffset,
+		/// Byte offset where the field ends.
+		end: u64,
+		/// Number of bytes available.
+		size: u64,
+	},
+	/// A checksum does not match.
+	ChecksumMismatch {
+		field: Field,
+		file_offset: format::FileOffset,
+		/// Key name of the datatable, zero for the dataset header and commit records.
+		key_name: u32,
+		expected: u32,
+		actual: u32,
+	},
+	/// A dataset references itself through its children.
+	CyclicDataset {
+		file_offset: format::FileOffset,
+	},
+	/// A datatable is not found.
+	TableNotFound {
+		file_offset: format::FileOffset,
+		key_name: u32,
+	},
+	/// A datatable cannot be read as the requested type.
+	TypeMismatch {
+		file_offset: format::FileOffset,
+		key_name: u32,
+		type_info: u16,
+		shape: Shape,
+		/// Name of the requested type.
+		expected: &'static str,
+	},
+	/// A datatable's data is not valid for its type.
+	///
+	/// Eg. text which is not valid unicode or data which fails to decompress.
+	InvalidTableData {
+		file_offset: format::FileOffset,
+		key_name: u32,
+	},
+	/// The operation is not supported on this host.
+	///
+	/// Eg. borrowing datasets from a memory mapped file on a big-endian host.
+	Unsupported {
+		file_offset: format::FileOffset,
+		/// Description of the operation.
+		message: &'static str,
+	},
+	/// A custom error message.
+	///
+	/// Eg. a value which cannot be serialized as a datatable.
+	Custom {
+		file_offset: format::FileOffset,
+		/// Key name of the datatable, zero if not associated with a datatable.
+		key_name: u32,
+		message: String,
+	},
+}
+
+impl Error {
+	/// Returns the field at fault.
+	#[inline]
+	pub fn field(&self) -> Option<Field> {
+		match self {
+			#[cfg(feature = "std")]
+			Error::Io(_) => None,
+			&Error::InvalidField { field, .. } => Some(field),
+			&Error::OutOfBounds { field, .. } => Some(field),
+			&Error::ChecksumMismatch { field, .. } => Some(field),
+			Error::CyclicDataset { .. } => Some(Field::FileOffset),
+			Error::TableNotFound { .. } => Some(Field::Descriptors),
+			Error::TypeMismatch { .. } => Some(Field::Descriptors),
+			Error::InvalidTableData { .. } => Some(Field::TableData),
+			Error::Unsupported { .. } => None,
+			Error::Custom { .. } => None,
+		}
+	}
+
+	/// Returns the file offset of the dataset at fault.
+	#[inline]
+	pub fn file_offset(&self) -> Option<format::FileOffset> {
+		match self {
+			#[cfg(feature = "std")]
+			Error::Io(_) => None,
+			&Error::InvalidField { file_offset, .. } => Some(file_offset),
+			&Error::OutOfBounds { file_offset, .. } => Some(file_offset),
+			&Error::ChecksumMismatch { file_offset, .. } => Some(file_offset),
+			&Error::CyclicDataset { file_offset } => Some(file_offset),
+			&Error::TableNotFound { file_offset, .. } => Some(file_offset),
+			&Error::TypeMismatch { file_offset, .. } => Some(file_offset),
+			&Error::InvalidTableData { file_offset, .. } => Some(file_offset),
+			&Error::Unsupported { file_offset, .. } => Some(file_offset),
+			&Error::Custom { file_offset, .. } => Some(file_offset),
+		}
+	}
+
+	/// Associates the error with the file offset of the dataset.
+	///
+	/// Has no effect if the error already has a file offset.
+	pub fn at(mut self, fo: format::FileOffset) -> Error {
+		match &mut self {
+			#[cfg(feature = "std")]
+			Error::Io(_) => (),
+			Error::InvalidField { file_offset, .. } |
+			Error::OutOfBounds { file_offset, .. } |
+			Error::ChecksumMismatch { file_offset, .. } |
+			Error::CyclicDataset { file_offset } |
+			Error::TableNotFound { file_offset, .. } |
+			Error::TypeMismatch { file_offset, .. } |
+			Error::InvalidTableData { file_offset, .. } |
+			Error::Unsupported { file_offset, .. } |
+			Error::Custom { file_offset, .. } => {
+				if file_offset.is_null() {
+					*file_offset = fo;
+				}
+			},
+		}
+		self
+	}
+
+	// Fills in the key name of errors raised by the table data
+	pub(crate) fn with_key_name(mut self, name: u32) -> Error {
+		match &mut self {
+			Error::TypeMismatch { key_name, .. } |
+			Error::InvalidTableData { key_name, .. } |
+			Error::Custom { key_name, .. } if *key_name == 0 => *key_name = name,
+			_ => (),
+		}
+		self
+	}
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for Error {
+	#[inline]
+	fn from(err: io::Error) -> Error {
+		Error::Io(err)
+	}
+}
+
+#[cfg(feature = "std")]
+impl From<Error> for io::Error {
+	fn from(err: Error) -> io::Error {
+		match err {
+			Error::Io(err) => err,
+			Error::OutOfBounds { .. } => io::Error::new(io::ErrorKind::UnexpectedEof, err),
+			Error::TableNotFound { .. } => io::Error::new(io::ErrorKind::NotFound, err),
+			Error::Unsupported { .. } => io::Error::new(io::ErrorKind::Unsupported, err),
+			_ => io::Error::new(io::ErrorKind::InvalidData, err),
+		}
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if let Some(file_offset) = self.file_offset() {
+			if !file_offset.is_null() {
+				write!(f, "{}: ", file_offset)?;
+			}
+		}
+		match self {
+			#[cfg(feature = "std")]
+			Error::Io(err) => err.fmt(f),
+			Error::InvalidField { field, expected, actual, .. } => {
+				write!(f, "invalid {}: expected {} but found {:#x}", field, expected, actual)
+			},
+			Error::OutOfBounds { field, end, size, .. } => {
+				write!(f, "{} out of bounds: ends at {:#x} but only {:#x} bytes available", field, end, size)
+			},
+			Error::ChecksumMismatch { field, key_name, expected, actual, .. } => {
+				write!(f, "{} checksum mismatch", field)?;
+				if *key_name != 0 {
+					write!(f, " (key_name={:#010x})", key_name)?;
+				}
+				write!(f, ": expected {:#010x} but found {:#010x}", expected, actual)
+			},
+			Error::CyclicDataset { .. } => f.pad("cyclic dataset reference"),
+			Error::TableNotFound { key_name, .. } => {
+				write!(f, "table {:#010x} not found", key_name)
+			},
+			Error::TypeMismatch { key_name, type_info, shape, expected, .. } => {
+				write!(f, "table {:#010x} of type {} with shape {} cannot be read as {}", key_name, PrintTypeInfo(*type_info), shape, expected)
+			},
+			Error::InvalidTableData { key_name, .. } => {
+				write!(f, "table {:#010x} has invalid data", key_name)
+			},
+			Error::Unsupported { message, .. } => {
+				write!(f, "{} is not supported on this host", message)
+			},
+			Error::Custom { key_name, message, .. } => {
+				if *key_name != 0 {
+					write!(f, "table {:#010x}: ", key_name)?;
+				}
+				f.write_str(message)
+			},
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl error::Error for Error {
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+		match self {
+			Error::Io(err) => Some(err),
+			_ => None,
+		}
+	}
+}
@@ -1,4 +1,4 @@
-use std::{error, fmt, num, str};
+use core::{fmt, num, str};
 use crate::*;
 
 /// Parse errors.
@@ -29,8 +29,9 @@ impl fmt::Display for ParseError {
 	}
 }
 
-impl error::Error for ParseError {
-	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
 		match self {
 			ParseError::ParseIntError(err) => Some(err),
 			_ => None,
@@ -4,13 +4,108 @@ Standard file writer.
 
 use std::{fs, mem};
 use std::path::Path;
-use std::io::{self, Read, Seek, Write};
+use std::io;
 use crate::*;
 
+/// Storage backend for [`FileIO`].
+///
+/// Reading requires [`io::Read`] and [`io::Seek`], writing additionally requires [`io::Write`].
+///
+/// Implemented for files, in-memory buffers and any stream wrapped in [`Stream`].
+pub trait Backend: io::Read + io::Seek {
+	/// Returns the offset of the end of the storage.
+	fn end(&mut self) -> io::Result<u64> {
+		self.seek(io::SeekFrom::End(0))
+	}
+
+	/// Ensures the written data has reached durable storage.
+	///
+	/// Used by [`FileIO::commit`] to order the writes, does nothing by default.
+	fn sync(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+impl Backend for fs::File {
+	#[inline]
+	fn end(&mut self) -> io::Result<u64> {
+		Ok(self.metadata()?.len())
+	}
+	#[inline]
+	fn sync(&mut self) -> io::Result<()> {
+		self.sync_data()
+	}
+}
+
+impl<T: AsRef<[u8]>> Backend for io::Cursor<T> {
+	#[inline]
+	fn end(&mut self) -> io::Result<u64> {
+		Ok(self.get_ref().as_ref().len() as u64)
+	}
+}
+
+impl<B: Backend + ?Sized> Backend for &mut B {
+	#[inline]
+	fn end(&mut self) -> io::Result<u64> {
+		(**self).end()
+	}
+	#[inline]
+	fn sync(&mut self) -> io::Result<()> {
+		(**self).sync()
+	}
+}
+
+impl<B: Backend + ?Sized> Backend for Box<B> {
+	#[inline]
+	fn end(&mut self) -> io::Result<u64> {
+		(**self).end()
+	}
+	#[inline]
+	fn sync(&mut self) -> io::Result<()> {
+		(**self).sync()
+	}
+}
+
+/// Backend adapter for arbitrary `Read + Seek` (and `Write`) streams.
+///
+/// The end of the storage is found by seeking to the end of the stream.
+#[derive(Debug)]
+#[repr(transparent)]
+pub struct Stream<T>(pub T);
+
+impl<T: io::Read> io::Read for Stream<T> {
+	#[inline]
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		self.0.read(buf)
+	}
+}
+impl<T: io::Seek> io::Seek for Stream<T> {
+	#[inline]
+	fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+		self.0.seek(pos)
+	}
+}
+impl<T: io::Write> io::Write for Stream<T> {
+	#[inline]
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.0.write(buf)
+	}
+	#[inline]
+	fn flush(&mut self) -> io::Result<()> {
+		self.0.flush()
+	}
+}
+impl<T: io::Read + io::Seek> Backend for Stream<T> {}
+
 /// Manipulate UDF files through File IO.
-pub struct FileIO {
-	file: fs::File,
+///
+/// The storage is a file by default, see [`Backend`] for other storage options.
+pub struct FileIO<B = fs::File> {
+	file: B,
 	header: format::UdfHeader,
+	mode: ParseMode,
+	// Released regions sorted by offset, adjacent regions are merged
+	free: Vec<format::FileOffset>,
 }
 
 impl FileIO {
@@ -18,54 +113,96 @@ impl FileIO {
 	///
 	/// Creates a new file if it does not exists, and will truncate if it does.
 	#[inline]
-	pub fn create(path: impl AsRef<Path>, id: [u8; 4]) -> io::Result<FileIO> {
+	pub fn create(path: impl AsRef<Path>, id: [u8; 4]) -> Result<FileIO, Error> {
 		Self::create_(path.as_ref(), id)
 	}
-	fn create_(path: &Path, id: [u8; 4]) -> io::Result<FileIO> {
-		let mut file = fs::File::create(path)?;
-		let header = format::UdfHeader {
-			magic: format::UdfHeader::MAGIC,
-			id,
-			next: 0,
-			root: format::FileOffset::default(),
-			reserved: [0; 4],
-		};
-		file.write_all(dataview::bytes(&header))?;
-		Ok(FileIO { file, header })
+	fn create_(path: &Path, id: [u8; 4]) -> Result<FileIO, Error> {
+		let file = fs::File::create(path)?;
+		FileIO::create_with(file, id)
 	}
 
 	/// Opens an UDF file in read-only mode.
 	///
 	/// Write methods will return an error.
 	#[inline]
-	pub fn open(path: impl AsRef<Path>) -> io::Result<FileIO> {
+	pub fn open(path: impl AsRef<Path>) -> Result<FileIO, Error> {
 		Self::open_(path.as_ref())
 	}
-	fn open_(path: &Path) -> io::Result<FileIO> {
-		let mut file = fs::File::open(path)?;
-		let mut header = format::UdfHeader::default();
-		file.read_exact(dataview::bytes_mut(&mut header))?;
-		if header.magic != format::UdfHeader::MAGIC {
-			return Err(io::Error::from(io::ErrorKind::InvalidData));
-		}
-		Ok(FileIO { file, header })
+	fn open_(path: &Path) -> Result<FileIO, Error> {
+		let file = fs::File::open(path)?;
+		FileIO::open_with(file)
 	}
 
 	/// Opens an UDF file for editing.
 	///
 	/// Creates a new file if it does not exists.
 	#[inline]
-	pub fn edit(path: impl AsRef<Path>) -> io::Result<FileIO> {
+	pub fn edit(path: impl AsRef<Path>) -> Result<FileIO, Error> {
 		Self::edit_(path.as_ref())
 	}
-	fn edit_(path: &Path) -> io::Result<FileIO> {
-		let mut file = fs::OpenOptions::new().read(true).write(true).open(path)?;
+	fn edit_(path: &Path) -> Result<FileIO, Error> {
+		let file = fs::OpenOptions::new().read(true).write(true).open(path)?;
+		FileIO::open_with(file)
+	}
+}
+
+impl<B: Backend> FileIO<B> {
+	/// Opens an UDF file from the given backend.
+	///
+	/// The header is read from the start of the backend.
+	/// The root is recovered from the last valid commit record, see [`commit`](Self::commit).
+	pub fn open_with(mut file: B) -> Result<FileIO<B>, Error> {
+		let file_size = file.end()?;
+		check_header_bounds(file_size)?;
 		let mut header = format::UdfHeader::default();
+		file.seek(io::SeekFrom::Start(0))?;
 		file.read_exact(dataview::bytes_mut(&mut header))?;
-		if header.magic != format::UdfHeader::MAGIC {
-			return Err(io::Error::from(io::ErrorKind::InvalidData));
-		}
-		Ok(FileIO { file, header })
+		check_magic(&header)?;
+		let mut header = endian::from_le(header);
+		let mut fileio = FileIO { file, header, mode: ParseMode::Strict, free: Vec::new() };
+		commit::recover(&mut header, file_size, |offset| fileio.read_commit(offset));
+		fileio.header = header;
+		Ok(fileio)
+	}
+
+	/// Returns a reference to the underlying backend.
+	#[inline]
+	pub fn get_ref(&self) -> &B {
+		&self.file
+	}
+
+	/// Returns a mutable reference to the underlying backend.
+	///
+	/// Needless to say it is trivial to corrupt the UDF file with this.
+	#[inline]
+	pub fn get_mut(&mut self) -> &mut B {
+		&mut self.file
+	}
+
+	/// Unwraps the underlying backend.
+	#[inline]
+	pub fn into_inner(self) -> B {
+		self.file
+	}
+
+	/// Returns the file header.
+	#[inline]
+	pub fn header(&self) -> &format::UdfHeader {
+		&self.header
+	}
+
+	/// Returns the parse mode used to read datasets.
+	#[inline]
+	pub fn parse_mode(&self) -> ParseMode {
+		self.mode
+	}
+
+	/// Sets the parse mode used to read datasets.
+	///
+	/// Datasets are parsed in strict mode by default.
+	#[inline]
+	pub fn set_parse_mode(&mut self, mode: ParseMode) {
+		self.mode = mode;
 	}
 
 	/// Returns the file id.
@@ -88,32 +225,185 @@ impl FileIO {
 	/// Sets the root dataset.
 	///
 	/// Invoke [`write_header`](Self::write_header) to persist the change.
+	/// This also unlinks the last commit record, prefer [`commit`](Self::commit) to persist the change crash-safely.
 	pub fn set_root(&mut self, root: format::FileOffset) {
 		self.header.root = root;
+		self.header.next = 0;
 	}
 
-	/// Writes the updated header to the file.
-	pub fn write_header(&mut self) -> io::Result<()> {
-		self.file.seek(io::SeekFrom::Start(0))?;
-		self.file.write_all(dataview::bytes(&self.header))?;
-		Ok(())
+	/// Reads and verifies a commit record.
+	///
+	/// The offset of the last commit record is stored in the file header.
+	pub fn read_commit(&mut self, offset: u64) -> Result<format::CommitRecord, Error> {
+		let file_size = self.file.end()?;
+		commit::check_commit_bounds(offset, file_size)?;
+		let mut record = format::CommitRecord::default();
+		self.file.seek(io::SeekFrom::Start(offset))?;
+		self.file.read_exact(dataview::bytes_mut(&mut record))?;
+		let record = endian::from_le(record);
+		commit::check_commit(&record, offset)?;
+		Ok(record)
 	}
 
 	/// Allocates a file offset.
+	///
+	/// Reuses the first released region large enough to hold the size, otherwise allocates at the end of the file.
+	/// The size is rounded up to a multiple of 16 bytes, zero sizes allocate 16 bytes.
 	pub fn allocate(&mut self, size: usize) -> format::FileOffset {
-		let offset = match self.file.metadata() {
-			Ok(md) => md.len(),
+		let size = align16(u64::max(size as u64, 1));
+
+		if let Some(index) = self.free.iter().position(|region| region.size >= size) {
+			let region = &mut self.free[index];
+			let fo = format::FileOffset { offset: region.offset, size };
+			region.offset += size;
+			region.size -= size;
+			if region.size == 0 {
+				self.free.remove(index);
+			}
+			return fo;
+		}
+
+		let mut offset = match self.file.end() {
+			Ok(offset) => align16(offset),
 			Err(_) => return Default::default(),
 		};
-		let offset = (offset.wrapping_sub(1) & !0xf).wrapping_add(0x10);
-		let size = (size.wrapping_sub(1) & !0xf).wrapping_add(0x10) as u64;
+		// Extend a released region at the end of the file
+		if let Some(last) = self.free.last() {
+			if last.offset + last.size == offset {
+				offset = last.offset;
+				self.free.pop();
+			}
+		}
 		format::FileOffset { offset, size }
 	}
 
+	/// Releases the region of a dataset for reuse by [`allocate`](Self::allocate).
+	///
+	/// The free list is kept in memory only, released regions are lost when the file is closed.
+	/// Use [`compact`] to reclaim unreachable datasets from a file.
+	///
+	/// Needless to say any references to the released dataset become invalid.
+	pub fn free(&mut self, fo: format::FileOffset) -> Result<(), Error> {
+		check_file_offset(fo)?;
+		// The file header is never released
+		let header_size = mem::size_of::<format::UdfHeader>() as u64;
+		if fo.offset < header_size {
+			return Err(Error::OutOfBounds { field: Field::FileOffset, file_offset: fo, end: fo.offset, size: header_size });
+		}
+		let file_size = self.file.end()?;
+		check_file_bounds(fo, file_size)?;
+
+		// Merge with overlapping and adjacent regions
+		let mut start = fo.offset;
+		let mut end = fo.offset + fo.size;
+		self.free.retain(|region| {
+			let region_end = region.offset + region.size;
+			if region.offset <= end && start <= region_end {
+				start = u64::min(start, region.offset);
+				end = u64::max(end, region_end);
+				return false;
+			}
+			true
+		});
+		let index = self.free.partition_point(|region| region.offset < start);
+		self.free.insert(index, format::FileOffset { offset: start, size: end - start });
+		Ok(())
+	}
+
+	/// Returns the released regions sorted by offset.
+	#[inline]
+	pub fn free_list(&self) -> &[format::FileOffset] {
+		&self.free
+	}
+
+	/// Reads a dataset from the UDF file.
+	///
+	/// This API lets you specify an arbitrary file offset.
+	/// It should only be used with file offsets retrieved from [`allocate`](Self::allocate).
+	/// However there's some safety checks against reading arbitrary data as datasets.
+	pub fn read_dataset(&mut self, fo: format::FileOffset) -> Result<Dataset, Error> {
+		check_file_offset(fo)?;
+
+		// The whole dataset must be contained in the file
+		let file_size = self.file.end()?;
+		check_file_bounds(fo, file_size)?;
+
+		// Copy the whole dataset in memory
+		let mut storage = vec![0u64; (fo.size / 8) as usize];
+		self.file.seek(io::SeekFrom::Start(fo.offset))?;
+		self.file.read_exact(dataview::bytes_mut(storage.as_mut_slice()))?;
+		endian::dataset_from_le(&mut storage).map_err(|err| err.at(fo))?;
+
+		let ds = DatasetRef::parse_with(&storage, self.mode).map_err(|err| err.at(fo))?;
+		Ok(ds.to_owned())
+	}
+
+	/// Resolves a path starting at the given dataset.
+	///
+	/// The path is a sequence of elements separated by `.`, see [`PathEl`].
+	/// Every element except the last must select from a dataset table, eg. `Slices[3].Points`.
+	/// The path refers to datasets if the last element selects from a dataset table, otherwise it refers to a table or its rows.
+	/// The empty path refers to the root dataset.
+	///
+	/// Returns an error unless the path matches exactly one dataset or table, see [`resolve_all`](Self::resolve_all).
+	pub fn resolve(&mut self, root: format::FileOffset, path: &str) -> Result<Resolved, Error> {
+		resolve::resolve(self, root, path)
+	}
+
+	/// Resolves a path matching any number of datasets and tables, eg. `Slices[*].Height`.
+	///
+	/// The matches are in the order of their indices.
+	/// Null datasets are skipped unless selected by a single index.
+	pub fn resolve_all(&mut self, root: format::FileOffset, path: &str) -> Result<Vec<Resolved>, Error> {
+		resolve::resolve_all(self, root, path)
+	}
+
+	/// Recursively walks the datasets starting at the given file offset.
+	///
+	/// The datasets are visited depth first in the order of their dataset tables, parents before their children.
+	/// Null file offsets are skipped and datasets shared by multiple parents are visited once for every parent.
+	///
+	/// Returns an error if a dataset fails to read or refers to one of its parents.
+	pub fn walk<F: FnMut(&Walk<'_>) -> WalkControl>(&mut self, root: format::FileOffset, mut f: F) -> Result<(), Error> {
+		walk::walk(self, root, u32::MAX, &mut f)
+	}
+
+	/// Recursively walks the datasets up to a maximum depth.
+	///
+	/// The root is at depth zero, see [`walk`](Self::walk).
+	pub fn walk_depth<F: FnMut(&Walk<'_>) -> WalkControl>(&mut self, root: format::FileOffset, max_depth: u32, mut f: F) -> Result<(), Error> {
+		walk::walk(self, root, max_depth, &mut f)
+	}
+}
+
+impl<B: Backend + io::Write> FileIO<B> {
+	/// Creates a new UDF file in the given backend.
+	///
+	/// The header is written at the start of the backend.
+	pub fn create_with(mut file: B, id: [u8; 4]) -> Result<FileIO<B>, Error> {
+		let header = format::UdfHeader {
+			magic: format::UdfHeader::MAGIC,
+			id,
+			next: 0,
+			root: format::FileOffset::default(),
+			reserved: [0; 4],
+		};
+		file.seek(io::SeekFrom::Start(0))?;
+		file.write_all(dataview::bytes(&endian::to_le(header)))?;
+		Ok(FileIO { file, header, mode: ParseMode::Strict, free: Vec::new() })
+	}
+
+	/// Writes the updated header to the file.
+	pub fn write_header(&mut self) -> Result<(), Error> {
+		self.file.seek(io::SeekFrom::Start(0))?;
+		self.file.write_all(dataview::bytes(&endian::to_le(self.header)))?;
+		Ok(())
+	}
+
 	/// Adds a dataset to the UDF file.
 	///
 	/// A new section is allocated for the dataset.
-	pub fn add_dataset(&mut self, ds: &Final<DatasetRef<'_>>) -> io::Result<format::FileOffset> {
+	pub fn add_dataset(&mut self, ds: &Final<DatasetRef<'_>>) -> Result<format::FileOffset, Error> {
 		let fo = self.allocate(ds.inner.file_size());
 		self.write_dataset(fo, ds)?;
 		Ok(fo)
@@ -124,18 +414,15 @@ impl FileIO {
 	/// This API lets you specify an arbitrary file offset.
 	/// Needless to say it is trivial to corrupt the UDF file with this.
 	/// Only use this with values retrieved from [`allocate`](Self::allocate).
-	pub fn write_dataset(&mut self, fo: format::FileOffset, ds: &Final<DatasetRef<'_>>) -> io::Result<()> {
+	pub fn write_dataset(&mut self, fo: format::FileOffset, ds: &Final<DatasetRef<'_>>) -> Result<(), Error> {
 		let ds = &ds.inner;
 
-		// File offsets must be 16-byte aligned
-		if fo.is_null() || !fo.is_aligned() {
-			return Err(io::Error::from(io::ErrorKind::InvalidInput));
-		}
+		check_file_offset(fo)?;
 
 		// Must have enough space to hold the dataset
 		let ds_file_size = ds.file_size() as u64;
 		if fo.size < ds_file_size {
-			return Err(io::Error::from(io::ErrorKind::InvalidInput));
+			return Err(Error::OutOfBounds { field: Field::FileOffset, file_offset: fo, end: ds_file_size, size: fo.size });
 		}
 
 		// Write the dataset to the file
@@ -152,35 +439,127 @@ impl FileIO {
 		Ok(())
 	}
 
-	/// Reads a dataset from the UDF file.
+	/// Replaces a dataset in the UDF file.
 	///
-	/// This API lets you specify an arbitrary file offset.
-	/// It should only be used with file offsets retrieved from [`allocate`](Self::allocate).
-	/// However there's some safety checks against reading arbitrary data as datasets.
-	pub fn read_dataset(&mut self, fo: format::FileOffset) -> io::Result<Dataset> {
-		// File offsets must be 16-byte aligned
-		if fo.is_null() || !fo.is_aligned() {
-			return Err(io::Error::from(io::ErrorKind::InvalidInput));
+	/// The dataset is written in place if it fits, the unused tail of the old region is released.
+	/// Otherwise the old region is released and a new region is allocated.
+	///
+	/// Returns the new file offset, references to the old dataset must be updated.
+	/// If the old dataset is the root dataset the root is updated, invoke [`write_header`](Self::write_header) to persist the change.
+	pub fn replace_dataset(&mut self, old: format::FileOffset, ds: &Final<DatasetRef<'_>>) -> Result<format::FileOffset, Error> {
+		check_file_offset(old)?;
+
+		let size = align16(ds.inner.file_size() as u64);
+		let fo = if size <= old.size {
+			let fo = format::FileOffset { offset: old.offset, size };
+			self.write_dataset(fo, ds)?;
+			if size < old.size {
+				self.free(format::FileOffset { offset: old.offset + size, size: old.size - size })?;
+			}
+			fo
 		}
+		else {
+			self.free(old)?;
+			self.add_dataset(ds)?
+		};
 
-		// Copy the whole dataset in memory
-		let mut storage = vec![0u64; (fo.size / 8) as usize];
-		self.file.seek(io::SeekFrom::Start(fo.offset))?;
-		self.file.read_exact(dataview::bytes_mut(storage.as_mut_slice()))?;
+		if self.header.root == old {
+			self.header.root = fo;
+		}
+		Ok(fo)
+	}
 
-		// println!("{:?}", storage);
+	/// Commits the root dataset.
+	///
+	/// After a crash the file is opened with either the previous root or the new root.
+	///
+	/// The writes are ordered and synced to durable storage:
+	///
+	/// 1. The datasets written so far are synced.
+	/// 2. A commit record is appended at the end of the file and synced.
+	/// 3. The file header is updated to point to the commit record and synced.
+	///
+	/// Datasets reachable from the previous root must not be overwritten before the commit,
+	/// eg. by [`replace_dataset`](Self::replace_dataset) or by reusing their regions after [`free`](Self::free).
+	pub fn commit(&mut self, root: format::FileOffset) -> Result<(), Error> {
+		self.file.flush()?;
+		self.file.sync()?;
 
-		let Ok(ds) = DatasetRef::parse(&storage) else {
-			return Err(io::Error::from(io::ErrorKind::InvalidData));
+		let prev = self.header.next;
+		let seq = match prev {
+			0 => 1,
+			_ => self.read_commit(prev)?.seq + 1,
 		};
+		let record = format::CommitRecord::new(seq, root, prev);
+		let offset = align16(self.file.end()?);
+		self.file.seek(io::SeekFrom::Start(offset))?;
+		self.file.write_all(dataview::bytes(&endian::to_le(record)))?;
+		self.file.flush()?;
+		self.file.sync()?;
 
-		Ok(ds.to_owned())
+		self.header.next = offset;
+		self.header.root = root;
+		self.write_header()?;
+		self.file.flush()?;
+		self.file.sync()?;
+		Ok(())
 	}
 
 	/// Flushes the underlying file object.
-	pub fn flush(&mut self) -> io::Result<()> {
-		self.file.flush()
+	pub fn flush(&mut self) -> Result<(), Error> {
+		self.file.flush()?;
+		Ok(())
+	}
+}
+
+// The file must be large enough for the file header
+pub(crate) fn check_header_bounds(file_size: u64) -> Result<(), Error> {
+	let header_size = mem::size_of::<format::UdfHeader>() as u64;
+	if file_size < header_size {
+		return Err(Error::OutOfBounds {
+			field: Field::Header,
+			file_offset: format::FileOffset { offset: 0, size: header_size },
+			end: header_size,
+			size: file_size,
+		});
+	}
+	Ok(())
+}
+
+pub(crate) fn check_magic(header: &format::UdfHeader) -> Result<(), Error> {
+	if header.magic != format::UdfHeader::MAGIC {
+		return Err(Error::InvalidField {
+			field: Field::Magic,
+			file_offset: format::FileOffset { offset: 0, size: mem::size_of::<format::UdfHeader>() as u64 },
+			expected: Expected::Equal(u32::from_le_bytes(format::UdfHeader::MAGIC) as u64),
+			actual: u32::from_le_bytes(header.magic) as u64,
+		});
+	}
+	Ok(())
+}
+
+// File offsets must be non-null and 16-byte aligned
+pub(crate) fn check_file_offset(fo: format::FileOffset) -> Result<(), Error> {
+	if fo.is_null() {
+		return Err(Error::InvalidField { field: Field::FileOffset, file_offset: fo, expected: Expected::NonZero, actual: 0 });
+	}
+	if !fo.is_aligned() {
+		let actual = if fo.offset & 0xf != 0 { fo.offset } else { fo.size };
+		return Err(Error::InvalidField { field: Field::FileOffset, file_offset: fo, expected: Expected::MultipleOf(16), actual });
 	}
+	Ok(())
+}
+
+pub(crate) fn check_file_bounds(fo: format::FileOffset, file_size: u64) -> Result<(), Error> {
+	match fo.offset.checked_add(fo.size) {
+		Some(end) if end <= file_size => Ok(()),
+		end => Err(Error::OutOfBounds { field: Field::FileOffset, file_offset: fo, end: end.unwrap_or(u64::MAX), size: file_size }),
+	}
+}
+
+#[inline]
+fn align16(value: u64) -> u64 {
+	(value.wrapping_sub(1) & !0xf).wrapping_add(0x10)
 }
 
 static ZEROS: [u8; 512] = [0; 512];
@@ -2,7 +2,7 @@
 Disk format structs.
 */
 
-use std::mem;
+use core::mem;
 
 /// The UDF file header.
 #[derive(Copy, Clone, Default, dataview::Pod)]
@@ -16,7 +16,9 @@ pub struct UdfHeader {
 	///
 	/// This field can be used to quickly identify if this UDF file is deliberately created for your application.
 	pub id: [u8; 4],
-	/// Reserved for future use.
+	/// Offset of the last commit record.
+	///
+	/// If zero, the file has no commit records and the root is read from this header.
 	pub next: u64,
 	/// The root dataset.
 	pub root: FileOffset,
@@ -30,6 +32,34 @@ impl UdfHeader {
 	pub const MAGIC: [u8; 4] = *b"UDF0";
 }
 
+/// Commit record of the root dataset.
+///
+/// Appended to the file by every commit, see [`FileIO::commit`](crate::FileIO::commit).
+#[derive(Copy, Clone, Debug, Default, dataview::Pod)]
+#[repr(C)]
+pub struct CommitRecord {
+	/// Magic commit record identifier.
+	///
+	/// Must be set to [`MAGIC`](Self::MAGIC).
+	pub magic: [u8; 4],
+	/// Checksum of the commit record (starting from the next field).
+	pub checksum: u32,
+	/// Sequence number, incremented by every commit.
+	pub seq: u64,
+	/// The committed root dataset.
+	pub root: FileOffset,
+	/// Offset of the previous commit record, zero for the first commit.
+	pub prev: u64,
+	/// Reserved for future use, must be zero.
+	pub reserved: u64,
+}
+
+const _: [(); 0x30] = [(); mem::size_of::<CommitRecord>()];
+
+impl CommitRecord {
+	pub const MAGIC: [u8; 4] = *b"UDFC";
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Hash, dataview::Pod)]
 #[repr(C)]
 pub struct FileOffset {
@@ -71,7 +101,7 @@ pub struct DatasetHeader {
 	pub id: [u8; 4],
 	/// Size of the dataset header in bytes (including tables and strings).
 	///
-	/// Must be 8-byte aligned.
+	/// Must be 8-byte aligned and equal to the size of its contents.
 	pub size: u16,
 	/// Number of datatable descriptors following the header.
 	pub descs_len: u16,
@@ -81,14 +111,23 @@ pub struct DatasetHeader {
 	///
 	/// Must be 8-byte aligned.
 	pub string_len: u16,
-	/// Reserved for future use.
-	pub reserved: [u16; 2],
+	/// Dataset flags, see [`FLAG_SORTED`](Self::FLAG_SORTED).
+	///
+	/// Undefined flags must be zero.
+	pub flags: u16,
+	/// Reserved for future use, must be zero.
+	pub reserved: [u16; 1],
 }
 
 const _: [(); 0x18] = [(); mem::size_of::<DatasetHeader>()];
 
 impl DatasetHeader {
 	pub const CHECK: u32 = 0x7fcea59b;
+
+	/// The datatable descriptors are sorted by strictly ascending key name.
+	pub const FLAG_SORTED: u16 = 0x1;
+	/// Mask of the defined flags.
+	pub const FLAGS_MASK: u16 = Self::FLAG_SORTED;
 }
 
 #[derive(Copy, Clone, Debug, Default, dataview::Pod)]
@@ -157,13 +196,15 @@ pub const TYPE_HINT_NONE: u16 = 0 << 8;
 
 /// Data is plain text.
 ///
-/// * Dimensions must be `TYPE_DIM_1D` with the shape.X equal to the number of (byte) chars.
-///   No ghost dimensions are allowed and must be zero.
+/// * Any dimensions are allowed.
+///   Must have a single ghost dimension equal to the number of chars of each string element.
 ///
 /// * Primitive can be `TYPE_PRIM_U8`, `TYPE_PRIM_I8` and must be valid UTF-8 encoded text.
 ///
 /// * Primitive can be `TYPE_PRIM_U16` and must be valid UTF-16 (little endian) encoded text.
 ///
+/// * Primitive can be `TYPE_PRIM_U32` and must be valid UTF-32 (little endian) encoded text.
+///
 /// Note that nul chars are allowed as per unicode standard.
 pub const TYPE_HINT_TEXT: u16 = 1 << 8;
 
@@ -196,10 +237,9 @@ pub const TYPE_HINT_DATASET: u16 = 3 << 8;
 ///
 /// * Dimension can be anything, including any ghost dimensions.
 ///
-/// * Primitive must be one of `U8`, `I8`, `U16`, `I16`, `U32`, `I32`, `U64`, `I64`.
+/// * Primitive must be one of `U8`, `U16`, `U32`, `U64`.
 ///
 /// All values in this table must be less than the length of the target table.
-/// For signed primitives negative values are not allowed.
 pub const TYPE_HINT_INDEX: u16 = 4 << 8;
 
 /// Data is pairs of `[start, end)` indices into another table.
@@ -211,10 +251,9 @@ pub const TYPE_HINT_INDEX: u16 = 4 << 8;
 /// * Dimensions can be anything except `TYPE_DIM_3D`.
 ///   Must have a single ghost dimension of length 2.
 ///
-/// * Primitive must be one of `U8`, `I8`, `U16`, `I16`, `U32`, `I32`, `U64`, `I64`.
+/// * Primitive must be one of `U8`, `U16`, `U32`, `U64`.
 ///
 /// All values in this table must be a valid range in the target table.
-/// For signed primitives negative values are not allowed.
 pub const TYPE_HINT_RANGE: u16 = 5 << 8;
 pub const TYPE_HINT_COORD: u16 = 6 << 8;
 pub const TYPE_HINT_HATCH: u16 = 7 << 8;
@@ -0,0 +1,120 @@
+use core::any;
+use alloc::borrow::Cow;
+use crate::*;
+
+/// Helper for converting `DataRef` back into data.
+///
+/// The inverse of [`AsDataRef`].
+///
+/// Borrowing types such as `&[T]` and `&str` reference the data directly and fail if the data is compressed.
+/// Owning types such as `Vec<T>` and `String` decompress the data when needed.
+///
+/// Arrays such as `Vec<[T; N]>` match the dimensions of the table,
+/// see [`DatasetRef::read_hint`] to read the ghost dimensions of a type hint as arrays.
+///
+/// Returns [`Error::TypeMismatch`] if the type info or shape does not match the requested type
+/// and [`Error::InvalidTableData`] if the data cannot be reinterpreted.
+pub trait FromDataRef<'a>: Sized {
+	/// Converts the data to `Self`.
+	fn from_data_ref(data: &DataRef<'a>) -> Result<Self, Error>;
+}
+
+// Borrowing variants cannot reference decompressed data
+fn borrowed<'a, T: ?Sized + ToOwned>(value: Cow<'a, T>) -> Result<&'a T, Error> {
+	match value {
+		Cow::Borrowed(value) => Ok(value),
+		Cow::Owned(_) => Err(Error::InvalidTableData { file_offset: format::FileOffset::NULL, key_name: 0 }),
+	}
+}
+
+fn scalar<T: Element>(data: &DataRef) -> Result<T, Error> {
+	if data.type_info & format::TYPE_DIM_MASK != format::TYPE_DIM_SCALAR {
+		return Err(element::mismatch(0, data, any::type_name::<T>()));
+	}
+	let values = element::read::<T>(0, *data)?;
+	match &*values {
+		&[value] => Ok(value),
+		_ => Err(element::mismatch(0, data, any::type_name::<T>())),
+	}
+}
+
+impl<'a, T: Element> FromDataRef<'a> for T {
+	#[inline]
+	fn from_data_ref(data: &DataRef<'a>) -> Result<T, Error> {
+		scalar(data)
+	}
+}
+impl<'a, T: Element> FromDataRef<'a> for &'a [T] {
+	#[inline]
+	fn from_data_ref(data: &DataRef<'a>) -> Result<&'a [T], Error> {
+		borrowed(element::read(0, *data)?)
+	}
+}
+impl<'a, T: Element> FromDataRef<'a> for Vec<T> {
+	#[inline]
+	fn from_data_ref(data: &DataRef<'a>) -> Result<Vec<T>, Error> {
+		Ok(element::read(0, *data)?.into_owned())
+	}
+}
+impl<'a, T: Element> FromDataRef<'a> for Cow<'a, [T]> {
+	#[inline]
+	fn from_data_ref(data: &DataRef<'a>) -> Result<Cow<'a, [T]>, Error> {
+		element::read(0, *data)
+	}
+}
+impl<'a, T: Element, const LEN: usize> FromDataRef<'a> for [T; LEN] {
+	#[inline]
+	fn from_data_ref(data: &DataRef<'a>) -> Result<[T; LEN], Error> {
+		let values = element::read::<T>(0, *data)?;
+		<[T; LEN]>::try_from(&*values).map_err(|_| element::mismatch(0, data, any::type_name::<[T; LEN]>()))
+	}
+}
+impl<'a, T: Element, const LEN: usize> FromDataRef<'a> for &'a [[T; LEN]] {
+	#[inline]
+	fn from_data_ref(data: &DataRef<'a>) -> Result<&'a [[T; LEN]], Error> {
+		borrowed(element::read_array::<T, _>(0, *data, &[LEN as u32])?)
+	}
+}
+impl<'a, T: Element, const LEN: usize> FromDataRef<'a> for Vec<[T; LEN]> {
+	#[inline]
+	fn from_data_ref(data: &DataRef<'a>) -> Result<Vec<[T; LEN]>, Error> {
+		Ok(element::read_array::<T, _>(0, *data, &[LEN as u32])?.into_owned())
+	}
+}
+impl<'a, T: Element, const N: usize, const M: usize> FromDataRef<'a> for &'a [[[T; M]; N]] {
+	#[inline]
+	fn from_data_ref(data: &DataRef<'a>) -> Result<&'a [[[T; M]; N]], Error> {
+		borrowed(element::read_array::<T, _>(0, *data, &[N as u32, M as u32])?)
+	}
+}
+impl<'a, T: Element, const N: usize, const M: usize> FromDataRef<'a> for Vec<[[T; M]; N]> {
+	#[inline]
+	fn from_data_ref(data: &DataRef<'a>) -> Result<Vec<[[T; M]; N]>, Error> {
+		Ok(element::read_array::<T, _>(0, *data, &[N as u32, M as u32])?.into_owned())
+	}
+}
+
+impl<'a> FromDataRef<'a> for &'a str {
+	#[inline]
+	fn from_data_ref(data: &DataRef<'a>) -> Result<&'a str, Error> {
+		borrowed(element::read_text(0, *data)?)
+	}
+}
+impl<'a> FromDataRef<'a> for Cow<'a, str> {
+	#[inline]
+	fn from_data_ref(data: &DataRef<'a>) -> Result<Cow<'a, str>, Error> {
+		element::read_text(0, *data)
+	}
+}
+impl<'a> FromDataRef<'a> for String {
+	#[inline]
+	fn from_data_ref(data: &DataRef<'a>) -> Result<String, Error> {
+		Ok(element::read_text(0, *data)?.into_owned())
+	}
+}
+impl<'a> FromDataRef<'a> for Vec<String> {
+	#[inline]
+	fn from_data_ref(data: &DataRef<'a>) -> Result<Vec<String>, Error> {
+		element::read_text_list(0, *data)
+	}
+}
@@ -0,0 +1,176 @@
+/*!
+Dereferencing index and range tables.
+
+Index and range tables refer to the rows of their target table named by `index_name`.
+The target table is looked up in the dataset containing the index table and its parents.
+*/
+
+use alloc::{borrow::Cow, format};
+use core::{any, slice};
+use crate::*;
+
+/// Index or range table dereferenced against its target table.
+///
+/// Every value of an index table selects a single element of the target, every range of a range table selects a sub-slice.
+/// The values are checked against the bounds of the target when constructed.
+///
+/// ```
+/// use udf::{format, AsDataRef};
+///
+/// let points = [format::Coord2F32 { x: 1.0, y: 2.0 }, format::Coord2F32 { x: 3.0, y: 4.0 }, format::Coord2F32 { x: 5.0, y: 6.0 }];
+/// let contours = [format::RangeU32 { start: 0, end: 2 }, format::RangeU32 { start: 2, end: 3 }];
+/// let mut ds = udf::Dataset::new();
+/// ds.insert("Points", points.as_data_ref());
+/// ds.insert("Contours", contours.as_data_ref()).index("Points");
+/// ds.finalize();
+///
+/// let ds = ds.as_ref();
+/// let contours = ds.get_indexed::<format::Coord2F32>("Contours").unwrap();
+/// assert_eq!(contours.len(), 2);
+/// assert_eq!(contours.get(1).unwrap()[0].x, 5.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Indexed<'a, T: Element> {
+	is_range: bool,
+	values: Vec<u64>,
+	target: Cow<'a, [T]>,
+}
+
+impl<'a, T: Element> Indexed<'a, T> {
+	/// Dereferences the index or range table with the given name.
+	///
+	/// The index table is found in the first dataset, its target table in the first dataset which contains it.
+	pub fn new<I>(datasets: I, name: &str) -> Result<Indexed<'a, T>, Error> where I: IntoIterator<Item = DatasetRef<'a>>, I::IntoIter: Clone {
+		let datasets = datasets.into_iter();
+		let Some(dataset) = datasets.clone().next() else {
+			return Err(Error::TableNotFound { file_offset: format::FileOffset::NULL, key_name: hash(name) });
+		};
+		let key_name = dataset.names.key_name(name);
+		let Some(table) = dataset.find_table(key_name) else {
+			return Err(Error::TableNotFound { file_offset: format::FileOffset::NULL, key_name });
+		};
+		let data = dataset.get_data_ref_verified(table)?;
+		new(datasets, table, &data)
+	}
+
+	/// Returns true if this is a range table.
+	#[inline]
+	pub fn is_range(&self) -> bool {
+		self.is_range
+	}
+
+	/// Returns the number of indices or ranges.
+	#[inline]
+	pub fn len(&self) -> usize {
+		if self.is_range { self.values.len() / 2 } else { self.values.len() }
+	}
+
+	/// Returns true if there are no indices or ranges.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.values.is_empty()
+	}
+
+	/// Returns the elements of the target table.
+	#[inline]
+	pub fn target(&self) -> &[T] {
+		&self.target
+	}
+
+	/// Returns the elements selected by the index or range at the given position.
+	///
+	/// Indices select a single element.
+	#[inline]
+	pub fn get(&self, index: usize) -> Option<&[T]> {
+		if self.is_range {
+			let start = index.checked_mul(2)?;
+			let range = self.values.get(start..start.checked_add(2)?)?;
+			Some(&self.target[range[0] as usize..range[1] as usize])
+		}
+		else {
+			let &value = self.values.get(index)?;
+			Some(slice::from_ref(&self.target[value as usize]))
+		}
+	}
+
+	/// Iterator over the elements selected by every index or range.
+	#[inline]
+	pub fn iter(&self) -> impl Iterator<Item = &[T]> + '_ {
+		(0..self.len()).filter_map(|index| self.get(index))
+	}
+
+	/// Gathers the selected elements into a single vector.
+	pub fn gather(&self) -> Vec<T> {
+		let mut elements = Vec::new();
+		for selected in self.iter() {
+			elements.extend_from_slice(selected);
+		}
+		elements
+	}
+}
+
+// Dereferences the table in the first dataset, the data may be compressed or a selection of its rows
+pub(crate) fn new<'a, T: Element>(datasets: impl IntoIterator<Item = DatasetRef<'a>>, table: &format::TableDesc, data: &DataRef) -> Result<Indexed<'a, T>, Error> {
+	let key_name = table.key_name;
+	let type_hint = table.type_info & format::TYPE_HINT_MASK;
+	if type_hint != format::TYPE_HINT_INDEX && type_hint != format::TYPE_HINT_RANGE {
+		return Err(Error::TypeMismatch { file_offset: format::FileOffset::NULL, key_name, type_info: table.type_info, shape: data.shape, expected: "index or range table" });
+	}
+	let is_range = type_hint == format::TYPE_HINT_RANGE;
+
+	// Read the index values
+	let invalid = || Error::InvalidTableData { file_offset: format::FileOffset::NULL, key_name };
+	let mut storage = Vec::new();
+	let data = data.decompress(&mut storage);
+	let Some(values) = index_values(&data) else {
+		return Err(invalid());
+	};
+	if data.is_compressed() || is_range && !values.len().is_multiple_of(2) {
+		return Err(invalid());
+	}
+
+	// Read the target table
+	if table.index_name == 0 {
+		return Err(Error::Custom { file_offset: format::FileOffset::NULL, key_name, message: "index table requires an index_name".into() });
+	}
+	let Some((dataset, target)) = resolve::find_table(datasets, table.index_name) else {
+		return Err(Error::TableNotFound { file_offset: format::FileOffset::NULL, key_name: table.index_name });
+	};
+	let target_data = dataset.get_data_ref_verified(target)?;
+	let target_len = target.data_shape[0] as usize;
+	let elements = element::read::<T>(target.key_name, target_data)?;
+	if target.type_info & format::TYPE_DIM_MASK != format::TYPE_DIM_1D || elements.len() != target_len {
+		return Err(Error::TypeMismatch {
+			file_offset: format::FileOffset::NULL,
+			key_name: target.key_name,
+			type_info: target.type_info,
+			shape: target_data.shape,
+			expected: any::type_name::<T>(),
+		});
+	}
+
+	// Check the index values against the target
+	let out_of_bounds = |message: String| Error::Custom { file_offset: format::FileOffset::NULL, key_name, message };
+	if is_range {
+		if let Some((index, range)) = values.chunks_exact(2).enumerate().find(|&(_, range)| range[0] > range[1] || range[1] > target_len as u64) {
+			return Err(out_of_bounds(format!("range {}..{} at index {} out of bounds of {} elements", range[0], range[1], index, target_len)));
+		}
+	}
+	else if let Some((index, value)) = values.iter().enumerate().find(|&(_, &value)| value >= target_len as u64) {
+		return Err(out_of_bounds(format!("index {} at index {} out of bounds of {} elements", value, index, target_len)));
+	}
+
+	Ok(Indexed { is_range, values, target: elements })
+}
+
+/// Reads the values of an uncompressed index or range table.
+pub(crate) fn index_values(data: &DataRef) -> Option<Vec<u64>> {
+	let values = match data.type_info & format::TYPE_PRIM_MASK {
+		format::TYPE_PRIM_U8 => data.as_slice::<u8>()?.iter().map(|&value| value as u64).collect(),
+		format::TYPE_PRIM_U16 => data.as_slice::<u16>()?.iter().map(|&value| value as u64).collect(),
+		format::TYPE_PRIM_U32 => data.as_slice::<u32>()?.iter().map(|&value| value as u64).collect(),
+		format::TYPE_PRIM_U64 => data.as_slice::<u64>()?.to_vec(),
+		_ => return None,
+	};
+	Some(values)
+}
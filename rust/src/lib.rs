@@ -1,7 +1,17 @@
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(not(feature = "std"))]
+use alloc::{borrow::ToOwned, string::String, vec::Vec};
+
 pub mod format;
 
+#[cfg(feature = "std")]
 mod fileio;
+#[cfg(feature = "std")]
+mod mapped;
 mod dataset_ref;
 mod dataset;
 mod table;
@@ -10,31 +20,66 @@ mod names;
 mod shape;
 mod data;
 mod asdata;
+mod fromdata;
+mod schema;
 mod hash;
+mod checksum;
 mod file_offset;
+mod error;
 mod print;
 mod print_float;
 mod path;
 mod walk;
+mod resolve;
+mod indexed;
+mod related;
+mod ndview;
 mod utils;
 mod string_array;
+#[cfg(feature = "std")]
+mod validate;
+#[cfg(feature = "std")]
+mod compact;
+mod element;
+mod commit;
+#[cfg(feature = "serde")]
+mod serde_dataset;
 
-pub use self::fileio::FileIO;
-pub use self::dataset_ref::DatasetRef;
+#[cfg(feature = "std")]
+pub use self::fileio::{FileIO, Backend, Stream};
+#[cfg(feature = "std")]
+pub use self::mapped::MappedFile;
+pub use self::dataset_ref::{DatasetRef, ParseMode};
 pub use self::dataset::Dataset;
-pub use self::table::TableRef;
+pub use self::table::{TableRef, TableBuilder};
 pub use self::names_ref::{NamesRef, NameOrHash};
 pub use self::names::Names;
 pub use self::shape::Shape;
 pub use self::data::DataRef;
 pub use self::asdata::AsDataRef;
+pub use self::fromdata::FromDataRef;
+pub use self::schema::UdfDataset;
 pub use self::hash::hash;
+pub use self::checksum::{Crc32c, crc32c};
 pub use self::file_offset::ParseError;
+pub use self::error::{Error, Field, Expected};
 pub use self::print::PrintArray;
 use self::print_float::{PrintF32, PrintF64};
-pub use self::path::PathEl;
-pub use self::walk::WalkRef;
+pub use self::path::{PathEl, Select};
+pub use self::walk::{WalkRef, Walk, WalkControl, PrintPath};
+pub use self::resolve::Resolved;
+pub use self::indexed::Indexed;
+pub use self::related::{Related, Row, Record, Records};
+pub use self::ndview::NdView;
 pub use self::utils::{PrintId, PrintTypeInfo, FileSize, PrintHex, Final};
 pub use self::string_array::build_string_array_utf8;
+#[cfg(feature = "std")]
+pub use self::validate::{validate, Report, Diagnostic, Severity, Rule};
+#[cfg(feature = "std")]
+pub use self::compact::{compact, Compaction};
+pub use self::element::Element;
+#[cfg(feature = "serde")]
+pub use self::serde_dataset::{to_file, from_file};
 
 pub mod compress;
+pub mod endian;
@@ -0,0 +1,110 @@
+/*!
+Memory mapped file reader.
+*/
+
+use std::{fs, slice};
+use std::path::Path;
+use crate::*;
+
+/// Read-only memory mapped UDF file.
+///
+/// Datasets are handed out by reference directly from the mapping without copying.
+///
+/// Datasets are stored little-endian and cannot be borrowed on big-endian hosts,
+/// [`read_dataset`](Self::read_dataset) returns [`Error::Unsupported`] there, use [`FileIO`] instead.
+pub struct MappedFile {
+	map: memmap2::Mmap,
+	header: format::UdfHeader,
+	mode: ParseMode,
+}
+
+impl MappedFile {
+	/// Opens and maps an UDF file in read-only mode.
+	///
+	/// # Safety
+	///
+	/// The file must not be modified or truncated while it is mapped, see [`memmap2::Mmap::map`].
+	#[inline]
+	pub unsafe fn open(path: impl AsRef<Path>) -> Result<MappedFile, Error> {
+		Self::open_(path.as_ref())
+	}
+	unsafe fn open_(path: &Path) -> Result<MappedFile, Error> {
+		let file = fs::File::open(path)?;
+		Self::map(&file)
+	}
+
+	/// Maps an opened UDF file.
+	///
+	/// # Safety
+	///
+	/// The file must not be modified or truncated while it is mapped, see [`memmap2::Mmap::map`].
+	pub unsafe fn map(file: &fs::File) -> Result<MappedFile, Error> {
+		let map = memmap2::Mmap::map(file)?;
+		fileio::check_header_bounds(map.len() as u64)?;
+		let header = dataview::DataView::from(&map[..]).read::<format::UdfHeader>(0);
+		fileio::check_magic(&header)?;
+		let mut header = endian::from_le(header);
+		let mut file = MappedFile { map, header, mode: ParseMode::Strict };
+		commit::recover(&mut header, file.map.len() as u64, |offset| file.read_commit(offset));
+		file.header = header;
+		Ok(file)
+	}
+
+	/// Returns the parse mode used to read datasets.
+	#[inline]
+	pub fn parse_mode(&self) -> ParseMode {
+		self.mode
+	}
+
+	/// Sets the parse mode used to read datasets.
+	///
+	/// Datasets are parsed in strict mode by default.
+	#[inline]
+	pub fn set_parse_mode(&mut self, mode: ParseMode) {
+		self.mode = mode;
+	}
+
+	/// Returns the file id.
+	#[inline]
+	pub fn id(&self) -> [u8; 4] {
+		self.header.id
+	}
+
+	/// Returns the root dataset.
+	#[inline]
+	pub fn root(&self) -> format::FileOffset {
+		self.header.root
+	}
+
+	/// Reads and verifies a commit record.
+	pub fn read_commit(&self, offset: u64) -> Result<format::CommitRecord, Error> {
+		commit::check_commit_bounds(offset, self.map.len() as u64)?;
+		let record = dataview::DataView::from(&self.map[..]).read::<format::CommitRecord>(offset as usize);
+		let record = endian::from_le(record);
+		commit::check_commit(&record, offset)?;
+		Ok(record)
+	}
+
+	/// Reads a dataset from the UDF file.
+	///
+	/// The dataset borrows directly from the mapping, no data is copied.
+	/// The same safety checks as [`FileIO::read_dataset`] are applied.
+	///
+	/// Returns [`Error::Unsupported`] on big-endian hosts as the datasets require conversion.
+	pub fn read_dataset(&self, fo: format::FileOffset) -> Result<DatasetRef<'_>, Error> {
+		if !endian::NATIVE {
+			return Err(Error::Unsupported { file_offset: fo, message: "borrowing datasets from a memory mapped file" });
+		}
+		fileio::check_file_offset(fo)?;
+
+		// The whole dataset must be contained in the file
+		fileio::check_file_bounds(fo, self.map.len() as u64)?;
+
+		// The mapping is page aligned and the file offset is 16-byte aligned
+		let bytes = &self.map[fo.offset as usize..(fo.offset + fo.size) as usize];
+		debug_assert_eq!(bytes.as_ptr() as usize % 8, 0);
+		let storage = unsafe { slice::from_raw_parts(bytes.as_ptr() as *const u64, bytes.len() / 8) };
+
+		DatasetRef::parse_with(storage, self.mode).map_err(|err| err.at(fo))
+	}
+}
@@ -1,4 +1,4 @@
-use std::{fmt, str};
+use core::{fmt, str};
 use crate::*;
 
 #[derive(Clone, Default)]
@@ -28,6 +28,24 @@ impl Names {
 		self.strings.extend_from_slice(name.as_bytes());
 	}
 
+	/// Adds the name unless it is already present.
+	///
+	/// Returns the hash of the name, panics if a different name with the same hash is present.
+	pub fn insert(&mut self, name: &str) -> u32 {
+		let hash = hash(name);
+		match self.entries.iter().find(|entry| entry.hash == hash) {
+			Some(entry) => {
+				let existing = self.strings.get(entry.offset as usize..entry.offset as usize + entry.len as usize);
+				if existing != Some(name.as_bytes()) {
+					let existing = existing.and_then(|bytes| str::from_utf8(bytes).ok()).unwrap_or("?");
+					panic!("name {:?} collides with {:?}, both hash to {:#010x}", name, existing, hash);
+				}
+			},
+			None => self.add(name, hash),
+		}
+		hash
+	}
+
 	pub fn finalize(&mut self) {
 		// Sort the names to enable binary search
 		self.entries.sort_unstable_by_key(|name| name.hash);
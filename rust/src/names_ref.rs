@@ -1,4 +1,6 @@
-use std::{fmt, io, mem, str};
+use core::{fmt, mem, str};
+#[cfg(feature = "std")]
+use std::io;
 use crate::*;
 
 /// Binary search-based names lookup table.
@@ -79,6 +81,14 @@ impl<'a> NamesRef<'a> {
 		return None;
 	}
 
+	/// Finds the hash for a given name string, falls back to the hash of the name if it isn't in this table.
+	///
+	/// The hash of a key name is not required to be the hash of its string.
+	#[inline]
+	pub fn key_name(&self, name: &str) -> u32 {
+		self.find(name).unwrap_or_else(|| hash(name))
+	}
+
 	/// Iterator over the name entries in this table.
 	#[inline]
 	pub fn iter(&self) -> impl 'a + Clone + Iterator<Item = (u32, Option<&'a str>)> {
@@ -99,6 +109,7 @@ impl<'a> NamesRef<'a> {
 		mem::size_of_val(self.entries) + mem::size_of_val(self.strings)
 	}
 
+	#[cfg(feature = "std")]
 	pub fn write(&self, w: &mut dyn io::Write) -> io::Result<()> {
 		w.write_all(dataview::bytes(self.entries))?;
 		w.write_all(self.strings)?;
@@ -0,0 +1,258 @@
+/*!
+N-dimensional views of table data.
+*/
+
+use core::{fmt, ops};
+use crate::*;
+
+/// N-dimensional view of table data, see [`DataRef::view`].
+///
+/// The axes are the logical dimensions given by the type info followed by the ghost dimensions of the type hint,
+/// eg. 1D coordinates with shape `10x2` have a logical axis of length 10 and a ghost axis of length 2.
+///
+/// ```
+/// use udf::{format, AsDataRef};
+///
+/// let grid = [[1.0f32, 2.0, 3.0], [4.0, 5.0, 6.0]];
+/// let data = grid.as_data_ref();
+/// let view = data.view::<f32>().unwrap();
+/// assert_eq!(view.axes(), &[2, 3]);
+/// assert_eq!(view[[1, 2]], 6.0);
+///
+/// let column = view.index_axis(1, 1).unwrap();
+/// assert_eq!(column.to_vec(), [2.0, 5.0]);
+///
+/// let points = data.with_type_hint(format::TYPE_HINT_COORD).view::<f32>().unwrap();
+/// assert_eq!((points.logical_axes(), points.ghost_axes()), (&[2][..], &[3][..]));
+/// ```
+pub struct NdView<'a, T> {
+	data: &'a [T],
+	offset: usize,
+	axes: [usize; 3],
+	strides: [usize; 3],
+	rank: u8,
+	logical: u8,
+}
+
+impl<'a, T> NdView<'a, T> {
+	/// Creates a view of the elements with the shape and the logical dimensions of the type info.
+	///
+	/// Returns `None` if the number of elements does not match the shape.
+	pub fn new(data: &'a [T], shape: Shape, type_info: u16) -> Option<NdView<'a, T>> {
+		if data.len() != shape.len() {
+			return None;
+		}
+		let (rank, axes) = match shape {
+			Shape::Scalar => (0, [1, 1, 1]),
+			Shape::D1(x) => (1, [x as usize, 1, 1]),
+			Shape::D2(x, y) => (2, [x as usize, y as usize, 1]),
+			Shape::D3(x, y, z) => (3, [x as usize, y as usize, z as usize]),
+		};
+		let strides = [axes[1] * axes[2], axes[2], 1];
+		let logical = u8::min(((type_info & format::TYPE_DIM_MASK) >> 4) as u8, rank);
+		Some(NdView { data, offset: 0, axes, strides, rank, logical })
+	}
+
+	/// Returns the number of axes, including the ghost dimensions.
+	#[inline]
+	pub fn rank(&self) -> usize {
+		self.rank as usize
+	}
+
+	/// Returns the lengths of the axes.
+	#[inline]
+	pub fn axes(&self) -> &[usize] {
+		&self.axes[..self.rank as usize]
+	}
+
+	/// Returns the lengths of the logical dimensions.
+	#[inline]
+	pub fn logical_axes(&self) -> &[usize] {
+		&self.axes[..self.logical as usize]
+	}
+
+	/// Returns the lengths of the ghost dimensions.
+	#[inline]
+	pub fn ghost_axes(&self) -> &[usize] {
+		&self.axes[self.logical as usize..self.rank as usize]
+	}
+
+	/// Returns the shape of the view.
+	#[inline]
+	pub fn shape(&self) -> Shape {
+		let [x, y, z] = self.axes;
+		match self.rank {
+			0 => Shape::Scalar,
+			1 => Shape::D1(x as u32),
+			2 => Shape::D2(x as u32, y as u32),
+			_ => Shape::D3(x as u32, y as u32, z as u8),
+		}
+	}
+
+	/// Returns the number of elements.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.axes().iter().product()
+	}
+
+	/// Returns true if the view has no elements.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Returns the element at the index, one index per axis.
+	#[inline]
+	pub fn get(&self, index: &[usize]) -> Option<&'a T> {
+		if index.len() != self.rank() {
+			return None;
+		}
+		let mut offset = self.offset;
+		for (axis, &i) in index.iter().enumerate() {
+			if i >= self.axes[axis] {
+				return None;
+			}
+			offset += i * self.strides[axis];
+		}
+		self.data.get(offset)
+	}
+
+	/// Returns the elements as a slice if they are contiguous.
+	#[inline]
+	pub fn as_slice(&self) -> Option<&'a [T]> {
+		let len = self.len();
+		let contiguous = (0..self.rank()).all(|axis| self.axes[axis] == 1 || self.strides[axis] == self.axes[axis + 1..self.rank()].iter().product());
+		if !contiguous {
+			return None;
+		}
+		self.data.get(self.offset..self.offset + len)
+	}
+
+	/// Returns the sub-view at the index along the axis, the axis is removed.
+	///
+	/// Removing a logical axis reduces the logical dimensions, removing a ghost axis reduces the ghost dimensions.
+	pub fn index_axis(&self, axis: usize, index: usize) -> Option<NdView<'a, T>> {
+		if axis >= self.rank() || index >= self.axes[axis] {
+			return None;
+		}
+		let mut view = *self;
+		view.offset += index * self.strides[axis];
+		for i in axis..2 {
+			view.axes[i] = view.axes[i + 1];
+			view.strides[i] = view.strides[i + 1];
+		}
+		view.axes[2] = 1;
+		view.strides[2] = 0;
+		view.rank -= 1;
+		if axis < self.logical as usize {
+			view.logical -= 1;
+		}
+		Some(view)
+	}
+
+	/// Returns the sub-view of a range of indices along the axis.
+	pub fn slice_axis(&self, axis: usize, range: ops::Range<usize>) -> Option<NdView<'a, T>> {
+		if axis >= self.rank() || range.start > range.end || range.end > self.axes[axis] {
+			return None;
+		}
+		let mut view = *self;
+		view.offset += range.start * self.strides[axis];
+		view.axes[axis] = range.end - range.start;
+		Some(view)
+	}
+
+	/// Iterator over the sub-views along the axis.
+	#[inline]
+	pub fn axis_iter(&self, axis: usize) -> impl Iterator<Item = NdView<'a, T>> + 'a {
+		let view = *self;
+		let len = if axis < self.rank() { self.axes[axis] } else { 0 };
+		(0..len).filter_map(move |index| view.index_axis(axis, index))
+	}
+
+	/// Iterator over the rows, the sub-views along the first axis.
+	#[inline]
+	pub fn rows(&self) -> impl Iterator<Item = NdView<'a, T>> + 'a {
+		self.axis_iter(0)
+	}
+
+	/// Iterator over the columns, the sub-views along the second axis.
+	#[inline]
+	pub fn columns(&self) -> impl Iterator<Item = NdView<'a, T>> + 'a {
+		self.axis_iter(1)
+	}
+
+	/// Iterator over the logical elements, the sub-views of the ghost dimensions.
+	///
+	/// Coordinates yield a view of their components, tables without ghost dimensions yield views of a single element.
+	pub fn elements(&self) -> impl Iterator<Item = NdView<'a, T>> + 'a {
+		let this = *self;
+		let logical = self.logical as usize;
+		let ghost = self.rank() - logical;
+		let len = self.logical_axes().iter().product::<usize>();
+		(0..len).map(move |mut n| {
+			let mut view = this;
+			for axis in (0..logical).rev() {
+				view.offset += n % this.axes[axis] * this.strides[axis];
+				n /= this.axes[axis];
+			}
+			view.axes = [1; 3];
+			view.strides = [0; 3];
+			view.axes[..ghost].copy_from_slice(&this.axes[logical..this.rank()]);
+			view.strides[..ghost].copy_from_slice(&this.strides[logical..this.rank()]);
+			view.rank = ghost as u8;
+			view.logical = 0;
+			view
+		})
+	}
+
+	/// Iterator over the elements in row-major order.
+	#[inline]
+	pub fn iter(&self) -> impl Iterator<Item = &'a T> + 'a {
+		let view = *self;
+		(0..self.len()).map(move |n| &view.data[view.offset_of(n)])
+	}
+
+	// Offset of the element at the row-major position
+	fn offset_of(&self, mut n: usize) -> usize {
+		let mut offset = self.offset;
+		for axis in (0..self.rank()).rev() {
+			offset += n % self.axes[axis] * self.strides[axis];
+			n /= self.axes[axis];
+		}
+		offset
+	}
+
+	/// Copies the elements in row-major order.
+	#[inline]
+	pub fn to_vec(&self) -> Vec<T> where T: Copy {
+		self.iter().copied().collect()
+	}
+}
+
+impl<T> Copy for NdView<'_, T> {}
+impl<T> Clone for NdView<'_, T> {
+	#[inline]
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<T, const N: usize> ops::Index<[usize; N]> for NdView<'_, T> {
+	type Output = T;
+
+	#[track_caller]
+	#[inline]
+	fn index(&self, index: [usize; N]) -> &T {
+		match self.get(&index) {
+			Some(value) => value,
+			None => panic!("index {:?} out of bounds of shape {}", index, self.shape()),
+		}
+	}
+}
+
+impl<T: fmt::Debug> fmt::Debug for NdView<'_, T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "NdView({}) ", self.shape())?;
+		f.debug_list().entries(self.iter()).finish()
+	}
+}
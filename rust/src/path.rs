@@ -1,9 +1,88 @@
-use std::str;
+/*!
+Paths to datasets and tables.
+
+A path is a sequence of elements separated by `.`, eg. `Slices[3].Points`:
+
+* `Name` refers to a table.
+* `Name[3]` selects the dataset at an index of a dataset table or a row of a table.
+* `Name[100:200]` selects a range of indices, either bound may be omitted.
+* `Name[*]` selects every index.
+* `Name[1,2]` selects along both axes of a 2D dataset table.
+* `"Name.With[Brackets]"` quotes names containing `.` or `[`, quoted names cannot contain `"`.
+*/
+
+use core::{fmt, ops, str};
 use crate::*;
 
-#[derive(Copy, Clone, Debug)]
+/// Selects indices along an axis.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Select {
+	/// A single index, eg. `[3]`.
+	Index(u32),
+	/// A range of indices, eg. `[100:200]`.
+	///
+	/// Defaults to the start and end of the axis respectively.
+	Range(Option<u32>, Option<u32>),
+	/// Every index, eg. `[*]`.
+	All,
+}
+
+impl Select {
+	/// Returns the selected range of indices of an axis with the given length.
+	///
+	/// Returns `None` if the selection is out of bounds.
+	pub fn range(&self, len: u32) -> Option<ops::Range<u32>> {
+		let range = match *self {
+			Select::Index(index) => index..index.checked_add(1)?,
+			Select::Range(start, end) => start.unwrap_or(0)..end.unwrap_or(len),
+			Select::All => 0..len,
+		};
+		if range.start > range.end || range.end > len {
+			return None;
+		}
+		Some(range)
+	}
+
+	fn parse(s: &str) -> Result<Select, ParseError> {
+		if s == "*" {
+			return Ok(Select::All);
+		}
+		if let Some((start, end)) = s.split_once(':') {
+			let start = if start.is_empty() { None } else { Some(start.parse()?) };
+			let end = if end.is_empty() { None } else { Some(end.parse()?) };
+			return Ok(Select::Range(start, end));
+		}
+		Ok(Select::Index(s.parse()?))
+	}
+}
+
+impl fmt::Display for Select {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match *self {
+			Select::Index(index) => write!(f, "{}", index),
+			Select::Range(start, end) => {
+				if let Some(start) = start {
+					write!(f, "{}", start)?;
+				}
+				f.write_str(":")?;
+				if let Some(end) = end {
+					write!(f, "{}", end)?;
+				}
+				Ok(())
+			},
+			Select::All => f.write_str("*"),
+		}
+	}
+}
+
+/// Element of a path.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum PathEl<'a> {
-	Dir { name: &'a str, index: u32 },
+	/// Selects datasets of a dataset table or rows of a table, eg. `Slices[3]`.
+	///
+	/// The second selection is only present for 2D dataset tables, eg. `Grid[1,2]`.
+	Dir { name: &'a str, index: Select, index2: Option<Select> },
+	/// Refers to a table.
 	Name(&'a str),
 }
 
@@ -16,48 +95,70 @@ impl<'a> PathEl<'a> {
 		}
 	}
 
+	/// Parses the next element and advances the path past it.
 	pub fn parse(state: &mut &'a str) -> Result<PathEl<'a>, ParseError> {
-		if state.is_empty() {
+		let string = *state;
+
+		// Parse the name, optionally quoted
+		let (name, rest) = if let Some(quoted) = string.strip_prefix('"') {
+			let end = quoted.find('"').ok_or(ParseError::InvalidFormat)?;
+			(&quoted[..end], &quoted[end + 1..])
+		}
+		else {
+			let end = string.find(['.', '[']).unwrap_or(string.len());
+			(&string[..end], &string[end..])
+		};
+		if name.is_empty() {
 			return Err(ParseError::InvalidFormat);
 		}
 
-		let mut string = *state;
+		// Parse the selection
+		let (el, rest) = if let Some(select) = rest.strip_prefix('[') {
+			let end = select.find(']').ok_or(ParseError::InvalidFormat)?;
+			let (index, index2) = match select[..end].split_once(',') {
+				Some((index, index2)) => (Select::parse(index)?, Some(Select::parse(index2)?)),
+				None => (Select::parse(&select[..end])?, None),
+			};
+			(PathEl::Dir { name, index, index2 }, &select[end + 1..])
+		}
+		else {
+			(PathEl::Name(name), rest)
+		};
 
-		let mut i = 0;
-		let mut j = None;
-		loop {
-			if i < string.len() {
-				if string.as_bytes()[i] == b'[' {
-					if j.is_some() {
-						// Already found a `[` which is invalid
-						return Err(ParseError::InvalidFormat);
-					}
-					j = Some(i);
-				}
-				if string.as_bytes()[i] == b'.' {
-					*state = &string[i + 1..];
-					string = &string[..i];
-					break;
-				}
-				i += 1;
-			}
-			else {
-				*state = &string[..0];
-				break;
+		// Elements are separated by a dot
+		*state = match rest.strip_prefix('.') {
+			Some(next) if !next.is_empty() => next,
+			None if rest.is_empty() => rest,
+			_ => return Err(ParseError::InvalidFormat),
+		};
+		Ok(el)
+	}
+}
+
+impl fmt::Display for PathEl<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		PrintName(self.name()).fmt(f)?;
+		if let PathEl::Dir { index, index2, .. } = self {
+			write!(f, "[{}", index)?;
+			if let Some(index2) = index2 {
+				write!(f, ",{}", index2)?;
 			}
+			f.write_str("]")?;
 		}
+		Ok(())
+	}
+}
 
-		if let Some(j) = j {
-			if string.as_bytes().last() != Some(&b']') {
-				return Err(ParseError::InvalidFormat);
-			}
-			let name = &string[..j];
-			let index = &string[j + 1..string.len() - 1];
-			let index = index.parse()?;
-			return Ok(PathEl::Dir { name, index });
+// Quotes names which cannot be parsed otherwise
+struct PrintName<'a>(&'a str);
+
+impl fmt::Display for PrintName<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.0.is_empty() || self.0.starts_with('"') || self.0.contains(['.', '[']) {
+			write!(f, "\"{}\"", self.0)
 		}
 		else {
-			return Ok(PathEl::Name(string));
+			f.write_str(self.0)
 		}
 	}
 }
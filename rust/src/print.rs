@@ -1,4 +1,4 @@
-use std::fmt;
+use core::fmt;
 
 use crate::*;
 
@@ -21,6 +21,25 @@ impl PrintArray {
 		PrintArray { strings: String::new(), indices: Vec::new(), element_width: 0, line_width: 75, shape }
 	}
 
+	/// Creates a printable array of the elements of the view.
+	///
+	/// ```
+	/// use udf::AsDataRef;
+	///
+	/// let data = [[1u8, 2, 3], [4, 5, 6]].as_data_ref();
+	/// let view = data.view::<u8>().unwrap();
+	/// let column = view.index_axis(1, 2).unwrap();
+	/// assert_eq!(udf::PrintArray::from_view(&column).unwrap().to_string(), "[3, 6]");
+	/// ```
+	pub fn from_view<T: fmt::Display>(view: &NdView<'_, T>) -> Result<PrintArray, fmt::Error> {
+		let mut pa = PrintArray::new(view.shape());
+		pa.reserve(view.len(), 2);
+		for item in view.iter() {
+			pa.push_fmt(format_args!("{}", item))?;
+		}
+		Ok(pa)
+	}
+
 	/// Reserve capacity for the formatted elements.
 	#[inline]
 	pub fn reserve(&mut self, len: usize, avg_size: usize) {
@@ -1,4 +1,4 @@
-use std::{fmt, mem};
+use core::{fmt, mem};
 
 // This file exists to print floats in a format nicer for consumption
 // Rust's standard float formatter does not print the decimal separator (followed by `0`) if it's an integer
@@ -60,7 +60,7 @@ impl PrintF32 {
 impl fmt::Display for PrintF32 {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		let mut wrapf = PrintFloat { inner: f, seen_dot: false };
-		use std::fmt::Write;
+		use core::fmt::Write;
 		write!(wrapf, "{}", self.0)?;
 		if !wrapf.seen_dot {
 			f.write_str(".0")?;
@@ -82,7 +82,7 @@ impl PrintF64 {
 impl fmt::Display for PrintF64 {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		let mut wrapf = PrintFloat { inner: f, seen_dot: false };
-		use std::fmt::Write;
+		use core::fmt::Write;
 		write!(wrapf, "{}", self.0)?;
 		if !wrapf.seen_dot {
 			f.write_str(".0")?;
@@ -0,0 +1,307 @@
+/*!
+Records of related tables.
+
+Tables related by `related_name` are the columns of a structure of arrays, every row of the related tables forms a record.
+*/
+
+use alloc::{borrow::Cow, format, vec};
+use core::any;
+use crate::*;
+
+/// Related tables of a dataset, see [`DatasetRef::related`].
+///
+/// The first table of the chain has no related name, the other tables are related to it or to each other.
+///
+/// ```
+/// use udf::{format, AsDataRef};
+///
+/// let contours = [format::RangeU32 { start: 0, end: 2 }, format::RangeU32 { start: 2, end: 5 }];
+/// let mut ds = udf::Dataset::new();
+/// ds.insert("Contours", contours.as_data_ref());
+/// ds.insert("Attributes", [7u32, 9].as_data_ref()).related("Contours");
+/// ds.finalize();
+///
+/// let ds = ds.as_ref();
+/// let related = ds.related("Attributes").unwrap();
+/// assert_eq!(related.len(), 2);
+/// let records: Vec<(format::RangeU32, u32)> = related.records(&["Contours", "Attributes"]).unwrap().collect();
+/// assert_eq!((records[1].0.end, records[1].1), (5, 9));
+/// ```
+pub struct Related<'a> {
+	columns: Vec<Column<'a>>,
+	len: u32,
+	names: NamesRef<'a>,
+}
+
+struct Column<'a> {
+	table: &'a format::TableDesc,
+	data: DataRef<'a>,
+	// Decompressed data bytes if the data is compressed
+	storage: Vec<u64>,
+	size: usize,
+}
+
+impl Column<'_> {
+	fn data(&self) -> DataRef<'_> {
+		if !self.data.is_compressed() {
+			return self.data;
+		}
+		DataRef {
+			bytes: &dataview::bytes(self.storage.as_slice())[..self.size],
+			compress_info: format::COMPRESS_NONE,
+			..self.data
+		}
+	}
+}
+
+impl<'a> Related<'a> {
+	/// Returns the number of rows.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.len as usize
+	}
+
+	/// Returns true if there are no rows.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Iterator over the related tables, starting with the first table of the chain.
+	#[inline]
+	pub fn tables(&self) -> impl Iterator<Item = &'a format::TableDesc> + '_ {
+		self.columns.iter().map(|column| column.table)
+	}
+
+	/// Returns the decompressed data of the related table at the given position.
+	#[inline]
+	pub fn data(&self, column: usize) -> Option<DataRef<'_>> {
+		Some(self.columns.get(column)?.data())
+	}
+
+	/// Returns the row at the given index.
+	#[inline]
+	pub fn row(&self, index: usize) -> Option<Row<'_>> {
+		if index >= self.len() {
+			return None;
+		}
+		Some(Row { columns: &self.columns, index: index as u32 })
+	}
+
+	/// Iterator over the rows.
+	#[inline]
+	pub fn rows(&self) -> impl Iterator<Item = Row<'_>> + '_ {
+		(0..self.len).map(|index| Row { columns: &self.columns, index })
+	}
+
+	/// Iterator over the rows of the named tables as tuples of elements.
+	///
+	/// Every table must hold a single element of its type per row, see [`DatasetRef::get`] for the requirements of the element types.
+	pub fn records<'r, R: Record<'r>>(&'r self, names: &[&str]) -> Result<Records<'r, R>, Error> {
+		if names.len() != R::LEN {
+			let message = format!("expected {} related table names, got {}", R::LEN, names.len());
+			return Err(Error::Custom { file_offset: format::FileOffset::NULL, key_name: 0, message });
+		}
+		let mut columns = Vec::with_capacity(names.len());
+		for name in names {
+			let key_name = self.names.key_name(name);
+			let Some(column) = self.columns.iter().find(|column| column.table.key_name == key_name) else {
+				return Err(Error::TableNotFound { file_offset: format::FileOffset::NULL, key_name });
+			};
+			columns.push((key_name, column.data()));
+		}
+		let columns = R::read(&columns, self.len())?;
+		Ok(Records { columns, index: 0, len: self.len() })
+	}
+}
+
+/// Row of related tables, see [`Related::row`].
+#[derive(Copy, Clone)]
+pub struct Row<'a> {
+	columns: &'a [Column<'a>],
+	index: u32,
+}
+
+impl<'a> Row<'a> {
+	/// Returns the index of the row.
+	#[inline]
+	pub fn index(&self) -> usize {
+		self.index as usize
+	}
+
+	/// Returns the number of related tables.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.columns.len()
+	}
+
+	/// Returns true if there are no related tables.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.columns.is_empty()
+	}
+
+	/// Returns the data of this row of the related table at the given position.
+	///
+	/// The first axis of the shape has length one, scalar tables return their data as is.
+	pub fn get(&self, column: usize) -> Option<DataRef<'a>> {
+		let column = self.columns.get(column)?;
+		let data = column.data();
+		if column.table.type_info & format::TYPE_DIM_MASK == format::TYPE_DIM_SCALAR {
+			return Some(data);
+		}
+		data.rows(self.index..self.index + 1)
+	}
+
+	/// Finds the data of this row of a related table by its key name.
+	#[inline]
+	pub fn find(&self, key_name: u32) -> Option<DataRef<'a>> {
+		let column = self.columns.iter().position(|column| column.table.key_name == key_name)?;
+		self.get(column)
+	}
+
+	/// Iterator over the related tables and the data of this row.
+	#[inline]
+	pub fn iter(&self) -> impl Iterator<Item = (&'a format::TableDesc, DataRef<'a>)> + '_ {
+		self.columns.iter().enumerate().filter_map(|(index, column)| Some((column.table, self.get(index)?)))
+	}
+}
+
+/// Tuples of elements read from the rows of related tables, see [`Related::records`].
+pub trait Record<'a>: Sized {
+	#[doc(hidden)]
+	type Columns;
+	#[doc(hidden)]
+	const LEN: usize;
+	#[doc(hidden)]
+	fn read(columns: &[(u32, DataRef<'a>)], len: usize) -> Result<Self::Columns, Error>;
+	#[doc(hidden)]
+	fn get(columns: &Self::Columns, index: usize) -> Self;
+}
+
+macro_rules! impl_record {
+	($len:literal; $($ty:ident $index:tt),+) => {
+		impl<'a, $($ty: Element),+> Record<'a> for ($($ty,)+) {
+			type Columns = ($(Cow<'a, [$ty]>,)+);
+			const LEN: usize = $len;
+			fn read(columns: &[(u32, DataRef<'a>)], len: usize) -> Result<Self::Columns, Error> {
+				Ok(($(read_column::<$ty>(columns[$index], len)?,)+))
+			}
+			#[inline]
+			fn get(columns: &Self::Columns, index: usize) -> Self {
+				($(columns.$index[index],)+)
+			}
+		}
+	};
+}
+
+impl_record!(1; A 0);
+impl_record!(2; A 0, B 1);
+impl_record!(3; A 0, B 1, C 2);
+impl_record!(4; A 0, B 1, C 2, D 3);
+impl_record!(5; A 0, B 1, C 2, D 3, E 4);
+impl_record!(6; A 0, B 1, C 2, D 3, E 4, F 5);
+
+// Reads a column with a single element per row
+fn read_column<T: Element>((key_name, data): (u32, DataRef<'_>), len: usize) -> Result<Cow<'_, [T]>, Error> {
+	let values = element::read::<T>(key_name, data)?;
+	if values.len() != len {
+		return Err(Error::TypeMismatch { file_offset: format::FileOffset::NULL, key_name, type_info: data.type_info, shape: data.shape, expected: any::type_name::<T>() });
+	}
+	Ok(values)
+}
+
+/// Iterator over the records of related tables, see [`Related::records`].
+pub struct Records<'a, R: Record<'a>> {
+	columns: R::Columns,
+	index: usize,
+	len: usize,
+}
+
+impl<'a, R: Record<'a>> Iterator for Records<'a, R> {
+	type Item = R;
+
+	#[inline]
+	fn next(&mut self) -> Option<R> {
+		if self.index >= self.len {
+			return None;
+		}
+		let record = R::get(&self.columns, self.index);
+		self.index += 1;
+		Some(record)
+	}
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let len = self.len - self.index;
+		(len, Some(len))
+	}
+}
+
+impl<'a, R: Record<'a>> ExactSizeIterator for Records<'a, R> {}
+
+// Follows the related names to the first table of the chain and collects every table related to the chain
+fn chain<'a>(dataset: &DatasetRef<'a>, key_name: u32) -> Result<Vec<&'a format::TableDesc>, Error> {
+	let not_found = |key_name| Error::TableNotFound { file_offset: format::FileOffset::NULL, key_name };
+	let mut table = dataset.find_table(key_name).ok_or_else(|| not_found(key_name))?;
+
+	// Follow the related names to the first table of the chain
+	let mut visited = vec![table.key_name];
+	while table.related_name != 0 && table.related_name != table.key_name {
+		table = dataset.find_table(table.related_name).ok_or_else(|| not_found(table.related_name))?;
+		if visited.contains(&table.key_name) {
+			let message = format!("related tables of {} form a cycle", NameOrHash(dataset.names.lookup(key_name)));
+			return Err(Error::Custom { file_offset: format::FileOffset::NULL, key_name, message });
+		}
+		visited.push(table.key_name);
+	}
+
+	// Collect the tables related to any table of the chain
+	let mut tables = vec![table];
+	let mut index = 0;
+	while let Some(&table) = tables.get(index) {
+		for related in dataset.tables {
+			if related.related_name == table.key_name && !tables.iter().any(|table| table.key_name == related.key_name) {
+				tables.push(related);
+			}
+		}
+		index += 1;
+	}
+	Ok(tables)
+}
+
+pub(crate) fn related<'a>(dataset: &DatasetRef<'a>, key_name: u32) -> Result<Related<'a>, Error> {
+	let tables = chain(dataset, key_name)?;
+
+	// Related tables must have the same shape
+	let first = tables[0];
+	let shape = Shape::from_type_info(first.type_info, first.data_shape);
+	for &table in &tables[1..] {
+		let related_shape = Shape::from_type_info(table.type_info, table.data_shape);
+		if related_shape != shape {
+			let message = format!("related tables {} ~ {} do not have the same shape, {} != {}",
+				NameOrHash(dataset.names.lookup(table.key_name)), NameOrHash(dataset.names.lookup(first.key_name)), related_shape, shape);
+			return Err(Error::Custom { file_offset: format::FileOffset::NULL, key_name: table.key_name, message });
+		}
+	}
+	let len = match shape {
+		Shape::Scalar => 1,
+		Shape::D1(x) | Shape::D2(x, _) | Shape::D3(x, _, _) => x,
+	};
+
+	let mut columns = Vec::with_capacity(tables.len());
+	for table in tables {
+		let invalid = || Error::InvalidTableData { file_offset: format::FileOffset::NULL, key_name: table.key_name };
+		let data = dataset.get_data_ref_verified(table)?;
+		let mut storage = Vec::new();
+		let decompressed = data.decompress(&mut storage);
+		// The rows must split the data evenly
+		let size = decompressed.bytes.len();
+		if decompressed.is_compressed() || len > 0 && !size.is_multiple_of(len as usize) {
+			return Err(invalid());
+		}
+		let column = Column { table, data, storage, size };
+		columns.push(column);
+	}
+	Ok(Related { columns, len, names: dataset.names })
+}
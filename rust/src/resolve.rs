@@ -0,0 +1,295 @@
+/*!
+Resolving paths to datasets and tables.
+
+Names which are not found in a dataset are looked up in its parent datasets.
+*/
+
+use core::ops;
+use alloc::sync::Arc;
+use crate::*;
+
+/// Datasets along a resolved path, see [`FileIO::resolve`].
+#[derive(Clone)]
+pub struct Resolved {
+	// Never empty, starts with the root dataset
+	datasets: Vec<(format::FileOffset, Arc<Dataset>)>,
+	key_name: u32,
+	rows: Option<ops::Range<u32>>,
+	path: String,
+}
+
+impl Resolved {
+	/// Returns the file offsets and datasets along the path, starting with the root dataset.
+	#[inline]
+	pub fn datasets(&self) -> impl ExactSizeIterator<Item = (format::FileOffset, DatasetRef<'_>)> + Clone + '_ {
+		self.datasets.iter().map(|(fo, dataset)| (*fo, Dataset::as_ref(dataset)))
+	}
+
+	/// Returns the key name of the table at the end of the path, zero if the path refers to a dataset.
+	#[inline]
+	pub fn key_name(&self) -> u32 {
+		self.key_name
+	}
+
+	/// Returns the rows selected from the table at the end of the path, `None` if the whole table is selected.
+	#[inline]
+	pub fn rows(&self) -> Option<ops::Range<u32>> {
+		self.rows.clone()
+	}
+
+	/// Returns the path with the selections resolved to indices, eg. `Slices[3].Points`.
+	#[inline]
+	pub fn path(&self) -> &str {
+		&self.path
+	}
+
+	/// Returns the file offset of the dataset at the end of the path.
+	#[inline]
+	pub fn file_offset(&self) -> format::FileOffset {
+		self.datasets[self.datasets.len() - 1].0
+	}
+
+	/// Returns the dataset at the end of the path.
+	#[inline]
+	pub fn dataset(&self) -> DatasetRef<'_> {
+		Dataset::as_ref(&self.datasets[self.datasets.len() - 1].1)
+	}
+
+	/// Iterator over the dataset at the end of the path and its parents.
+	#[inline]
+	pub fn ancestors(&self) -> impl Iterator<Item = DatasetRef<'_>> + Clone + '_ {
+		self.datasets.iter().rev().map(|(_, dataset)| Dataset::as_ref(dataset))
+	}
+
+	/// Returns the table the path refers to.
+	#[inline]
+	pub fn table(&self) -> Option<&format::TableDesc> {
+		if self.key_name == 0 {
+			return None;
+		}
+		self.dataset().find_table(self.key_name)
+	}
+
+	/// Returns the data of the table the path refers to.
+	///
+	/// The data is decompressed into the storage if necessary and only the selected rows are returned.
+	pub fn data<'a>(&'a self, storage: &'a mut Vec<u64>) -> Result<DataRef<'a>, Error> {
+		let fo = self.file_offset();
+		let Some(table) = self.table() else {
+			return Err(Error::TableNotFound { file_offset: fo, key_name: self.key_name });
+		};
+		let data = self.dataset().get_data_ref_verified(table).map_err(|err| err.at(fo))?;
+		let data = data.decompress(storage);
+		let Some(rows) = self.rows() else {
+			return Ok(data);
+		};
+		data.rows(rows).ok_or(Error::InvalidTableData { file_offset: fo, key_name: self.key_name })
+	}
+
+	/// Dereferences the index or range table the path refers to against its target table.
+	///
+	/// Only the selected rows of the index table are dereferenced, the target table is found in the dataset at the end of the path or its parents.
+	pub fn get_indexed<T: Element>(&self) -> Result<Indexed<'_, T>, Error> {
+		let fo = self.file_offset();
+		let Some(table) = self.table() else {
+			return Err(Error::TableNotFound { file_offset: fo, key_name: self.key_name });
+		};
+		let mut storage = Vec::new();
+		let data = self.data(&mut storage)?;
+		indexed::new(self.ancestors(), table, &data).map_err(|err| err.at(fo))
+	}
+
+	/// Looks up the name of a hash in the dataset at the end of the path and its parents.
+	#[inline]
+	pub fn lookup(&self, hash: u32) -> Result<&str, u32> {
+		lookup_name(self.ancestors(), hash)
+	}
+
+	/// Finds a table by its key name in the dataset at the end of the path and its parents.
+	#[inline]
+	pub fn find_table(&self, key_name: u32) -> Option<(DatasetRef<'_>, &format::TableDesc)> {
+		find_table(self.ancestors(), key_name)
+	}
+
+	/// Finds the target table of an index or range table in the dataset at the end of the path and its parents.
+	///
+	/// Returns `None` if the table has no index name or the target table is not found.
+	#[inline]
+	pub fn index_table(&self, table: &format::TableDesc) -> Option<(DatasetRef<'_>, &format::TableDesc)> {
+		if table.index_name == 0 {
+			return None;
+		}
+		self.find_table(table.index_name)
+	}
+}
+
+// Looks up the name starting with the innermost dataset
+pub(crate) fn lookup_name<'a>(datasets: impl IntoIterator<Item = DatasetRef<'a>>, hash: u32) -> Result<&'a str, u32> {
+	for dataset in datasets {
+		if let Ok(name) = dataset.names.lookup(hash) {
+			return Ok(name);
+		}
+	}
+	Err(hash)
+}
+
+// Finds the key name of the name starting with the innermost dataset, falls back to the hash of the name
+#[cfg(feature = "std")]
+fn find_name<'a>(datasets: impl IntoIterator<Item = DatasetRef<'a>>, name: &str) -> u32 {
+	for dataset in datasets {
+		if let Some(key_name) = dataset.names.find(name) {
+			return key_name;
+		}
+	}
+	hash(name)
+}
+
+// Finds the table starting with the innermost dataset
+pub(crate) fn find_table<'a>(datasets: impl IntoIterator<Item = DatasetRef<'a>>, key_name: u32) -> Option<(DatasetRef<'a>, &'a format::TableDesc)> {
+	for dataset in datasets {
+		if let Some(table) = dataset.find_table(key_name) {
+			return Some((dataset, table));
+		}
+	}
+	None
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn resolve<B: Backend>(file: &mut FileIO<B>, root: format::FileOffset, path: &str) -> Result<Resolved, Error> {
+	let mut matches = resolve_all(file, root, path)?;
+	if matches.len() != 1 {
+		let message = format!("path {:?} matches {} datasets or tables", path, matches.len());
+		return Err(Error::Custom { file_offset: root, key_name: 0, message });
+	}
+	Ok(matches.remove(0))
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn resolve_all<B: Backend>(file: &mut FileIO<B>, root: format::FileOffset, path: &str) -> Result<Vec<Resolved>, Error> {
+	let resolved = Resolved {
+		datasets: vec![(root, Arc::new(file.read_dataset(root)?))],
+		key_name: 0,
+		rows: None,
+		path: String::new(),
+	};
+	let mut matches = Vec::new();
+	resolve_rec(file, resolved, path, path, &mut matches)?;
+	Ok(matches)
+}
+
+#[cfg(feature = "std")]
+fn resolve_rec<B: Backend>(file: &mut FileIO<B>, mut resolved: Resolved, path: &str, mut rest: &str, matches: &mut Vec<Resolved>) -> Result<(), Error> {
+	use std::fmt::Write;
+
+	if rest.is_empty() {
+		matches.push(resolved);
+		return Ok(());
+	}
+
+	let fo = resolved.file_offset();
+	let invalid_path = |message: String| Error::Custom { file_offset: fo, key_name: 0, message };
+
+	let el = PathEl::parse(&mut rest).map_err(|err| invalid_path(format!("invalid path {:?}: {}", path, err)))?;
+	let key_name = find_name(resolved.ancestors(), el.name());
+	let dataset = resolved.dataset();
+	let Some(table) = dataset.find_table(key_name) else {
+		return Err(Error::TableNotFound { file_offset: fo, key_name });
+	};
+	let shape = Shape::from_shape(table.type_info, table.data_shape);
+	let push_path = |resolved: &mut Resolved, el: PathEl| {
+		if !resolved.path.is_empty() {
+			resolved.path.push('.');
+		}
+		let _ = write!(resolved.path, "{}", el);
+	};
+
+	let (index, index2) = match el {
+		PathEl::Name(_) if rest.is_empty() => {
+			resolved.key_name = key_name;
+			push_path(&mut resolved, el);
+			matches.push(resolved);
+			return Ok(());
+		},
+		PathEl::Name(name) => return Err(invalid_path(format!("invalid path {:?}: {} is not followed by an index", path, name))),
+		PathEl::Dir { index, index2, .. } => (index, index2),
+	};
+
+	if !walk::is_dataset_table(table) {
+		// Select the rows of the table at the end of the path
+		if rest.is_empty() && index2.is_none() && shape != Shape::Scalar {
+			let len = shape.encode()[0];
+			let Some(rows) = index.range(len) else {
+				return Err(Error::Custom { file_offset: fo, key_name, message: format!("rows [{}] out of bounds of {} rows", index, len) });
+			};
+			resolved.key_name = key_name;
+			resolved.rows = Some(rows);
+			push_path(&mut resolved, el);
+			matches.push(resolved);
+			return Ok(());
+		}
+		return Err(Error::TypeMismatch { file_offset: fo, key_name, type_info: table.type_info, shape, expected: "dataset" });
+	}
+
+	let Some(data) = dataset.get_data_ref(table) else {
+		return Err(Error::OutOfBounds {
+			field: Field::TableData,
+			file_offset: fo,
+			end: table.mem_end as u64 * 8,
+			size: dataset.storage.len() as u64 * 8,
+		});
+	};
+	let Some(children) = data.as_slice::<format::FileOffset>() else {
+		return Err(Error::InvalidTableData { file_offset: fo, key_name });
+	};
+
+	// Dataset tables have up to two axes, scalar dataset tables have a single row
+	let (rows, cols) = match (table.type_info & format::TYPE_DIM_MASK, index2) {
+		(format::TYPE_DIM_SCALAR, None) => (1, None),
+		(format::TYPE_DIM_1D, None) => (children.len() as u32, None),
+		(format::TYPE_DIM_2D, _) => (table.data_shape[0], Some(table.data_shape[1] & 0xffffff)),
+		_ => return Err(Error::TypeMismatch { file_offset: fo, key_name, type_info: table.type_info, shape, expected: "dataset table with matching axes" }),
+	};
+	if rows as usize * cols.unwrap_or(1) as usize != children.len() {
+		return Err(Error::InvalidTableData { file_offset: fo, key_name });
+	}
+
+	let out_of_bounds = |select: Select, len: u32| Error::Custom { file_offset: fo, key_name, message: format!("index [{}] out of bounds of {} datasets", select, len) };
+	let row_range = index.range(rows).ok_or_else(|| out_of_bounds(index, rows))?;
+	let col_range = match cols {
+		Some(cols) => {
+			let index2 = index2.unwrap_or(Select::All);
+			index2.range(cols).ok_or_else(|| out_of_bounds(index2, cols))?
+		},
+		None => 0..1,
+	};
+
+	// Null datasets are only an error when selected explicitly
+	let explicit = matches!((index, cols, index2), (Select::Index(_), None, _) | (Select::Index(_), Some(_), Some(Select::Index(_))));
+
+	let mut selected = Vec::new();
+	for row in row_range {
+		for col in col_range.clone() {
+			let child = children[row as usize * cols.unwrap_or(1) as usize + col as usize];
+			let el = PathEl::Dir {
+				name: el.name(),
+				index: Select::Index(row),
+				index2: cols.map(|_| Select::Index(col)),
+			};
+			if child.is_null() {
+				if explicit {
+					return Err(Error::Custom { file_offset: fo, key_name, message: format!("{} is a null dataset", el) });
+				}
+				continue;
+			}
+			selected.push((el, child));
+		}
+	}
+
+	for (el, child) in selected {
+		let mut resolved = resolved.clone();
+		push_path(&mut resolved, el);
+		resolved.datasets.push((child, Arc::new(file.read_dataset(child)?)));
+		resolve_rec(file, resolved, path, rest, matches)?;
+	}
+	Ok(())
+}
@@ -0,0 +1,16 @@
+/*!
+Dataset schemas.
+*/
+
+use crate::*;
+
+/// Types mapped to datasets with a table for every field.
+///
+/// Implemented with `#[derive(UdfDataset)]` from the `udf-derive` crate.
+pub trait UdfDataset<'a>: Sized {
+	/// Creates a dataset with the tables of the fields.
+	fn to_dataset(&self) -> Dataset;
+
+	/// Reads the fields from the tables of the dataset.
+	fn from_dataset_ref(dataset: &DatasetRef<'a>) -> Result<Self, Error>;
+}
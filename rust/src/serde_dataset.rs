@@ -0,0 +1,597 @@
+/*!
+Serde support mapping structs to datasets.
+
+Requires the `serde` feature.
+*/
+
+use std::{fmt, io, vec};
+use serde::{de, ser};
+use serde::de::IntoDeserializer;
+use crate::*;
+
+/// Serializes the value as a dataset in the UDF file.
+///
+/// The value must be a struct, its fields are written as datatables named after the fields:
+///
+/// * Numbers, `bool` and `char` become scalar tables.
+/// * Sequences of numbers become 1D tables, sequences of arrays `[T; N]` become 2D tables and so on up to 3D tables.
+/// * Strings become `TYPE_HINT_TEXT` tables, sequences of strings become text tables padded with nul bytes.
+/// * Unit enum variants become `TYPE_HINT_TEXT` tables with the name of the variant.
+/// * Nested structs and sequences of structs become child datasets referenced by `TYPE_HINT_DATASET` tables.
+///   `None` elements in a sequence of structs are written as null file offsets.
+/// * `None` and unit fields are left out.
+///
+/// Child datasets are written before their parent.
+/// Returns the file offset of the dataset, which can be committed as the root with [`FileIO::commit`].
+///
+/// ```
+/// #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+/// struct Job {
+///     name: String,
+///     heights: Vec<f32>,
+///     points: Vec<[f32; 2]>,
+/// }
+///
+/// let mut file = udf::FileIO::create_with(std::io::Cursor::new(Vec::new()), *b"JOB0").unwrap();
+/// let job = Job { name: "Example".into(), heights: vec![0.1, 0.2], points: vec![[1.0, 2.0], [3.0, 4.0]] };
+/// let fo = udf::to_file(&mut file, &job).unwrap();
+/// assert_eq!(udf::from_file::<Job, _>(&mut file, fo).unwrap(), job);
+/// ```
+pub fn to_file<T: ?Sized + ser::Serialize, B: Backend + io::Write>(file: &mut FileIO<B>, value: &T) -> Result<format::FileOffset, Error> {
+	match value.serialize(ValueSerializer)? {
+		Value::Struct(fields) => write_struct(file, fields),
+		_ => Err(unsupported("only structs")),
+	}
+}
+
+/// Deserializes the dataset at the file offset.
+///
+/// The inverse of [`to_file`], the value must be a struct.
+/// Missing tables deserialize as `None` for optional fields.
+pub fn from_file<T: de::DeserializeOwned, B: Backend>(file: &mut FileIO<B>, fo: format::FileOffset) -> Result<T, Error> {
+	let dataset = file.read_dataset(fo)?;
+	T::deserialize(DatasetDeserializer { file, dataset }).map_err(|err| err.at(fo))
+}
+
+impl ser::Error for Error {
+	fn custom<T: fmt::Display>(msg: T) -> Error {
+		Error::Custom { file_offset: format::FileOffset::NULL, key_name: 0, message: msg.to_string() }
+	}
+}
+
+impl de::Error for Error {
+	fn custom<T: fmt::Display>(msg: T) -> Error {
+		Error::Custom { file_offset: format::FileOffset::NULL, key_name: 0, message: msg.to_string() }
+	}
+
+	fn missing_field(field: &'static str) -> Error {
+		Error::TableNotFound { file_offset: format::FileOffset::NULL, key_name: hash(field) }
+	}
+}
+
+fn unsupported(what: &str) -> Error {
+	Error::Custom {
+		file_offset: format::FileOffset::NULL,
+		key_name: 0,
+		message: format!("{} can be mapped to datasets", what),
+	}
+}
+
+//----------------------------------------------------------------
+// Values
+
+macro_rules! prims {
+	($($name:ident($ty:ty) = $prim:ident, $visit:ident;)*) => {
+		#[derive(Copy, Clone, Debug)]
+		enum Prim {
+			$($name($ty),)*
+		}
+		impl Prim {
+			fn prim_type(self) -> u16 {
+				match self {
+					$(Prim::$name(_) => format::$prim,)*
+				}
+			}
+			fn extend(self, bytes: &mut Vec<u8>) {
+				match self {
+					$(Prim::$name(value) => bytes.extend_from_slice(&value.to_ne_bytes()),)*
+				}
+			}
+			fn is_zero(self) -> bool {
+				match self {
+					$(Prim::$name(value) => value as f64 == 0.0,)*
+				}
+			}
+			fn size_of(prim_type: u16) -> Option<usize> {
+				match prim_type {
+					$(format::$prim => Some(std::mem::size_of::<$ty>()),)*
+					_ => None,
+				}
+			}
+			fn read(prim_type: u16, bytes: &[u8]) -> Option<Prim> {
+				match prim_type {
+					$(format::$prim => Some(Prim::$name(<$ty>::from_ne_bytes(bytes.try_into().ok()?))),)*
+					_ => None,
+				}
+			}
+			fn visit<'de, V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+				match self {
+					$(Prim::$name(value) => visitor.$visit(value),)*
+				}
+			}
+		}
+	};
+}
+
+prims! {
+	U8(u8) = TYPE_PRIM_U8, visit_u8;
+	I8(i8) = TYPE_PRIM_I8, visit_i8;
+	U16(u16) = TYPE_PRIM_U16, visit_u16;
+	I16(i16) = TYPE_PRIM_I16, visit_i16;
+	U32(u32) = TYPE_PRIM_U32, visit_u32;
+	I32(i32) = TYPE_PRIM_I32, visit_i32;
+	U64(u64) = TYPE_PRIM_U64, visit_u64;
+	I64(i64) = TYPE_PRIM_I64, visit_i64;
+	F32(f32) = TYPE_PRIM_F32, visit_f32;
+	F64(f64) = TYPE_PRIM_F64, visit_f64;
+}
+
+// Intermediate representation of a datatable
+#[derive(Debug)]
+enum Value {
+	None,
+	Prim(Prim),
+	Text(String),
+	Seq(Vec<Value>),
+	Struct(Vec<(&'static str, Value)>),
+	Dataset(format::FileOffset),
+}
+
+//----------------------------------------------------------------
+// Serialization
+
+fn write_struct<B: Backend + io::Write>(file: &mut FileIO<B>, fields: Vec<(&'static str, Value)>) -> Result<format::FileOffset, Error> {
+	let mut ds = Dataset::new();
+	for (name, value) in fields {
+		write_table(file, &mut ds, name, value).map_err(|err| err.with_key_name(hash(name)))?;
+	}
+	file.add_dataset(&ds.finalize())
+}
+
+fn write_table<B: Backend + io::Write>(file: &mut FileIO<B>, ds: &mut Dataset, name: &str, value: Value) -> Result<(), Error> {
+	match value {
+		Value::None => {},
+		Value::Prim(prim) => {
+			let mut bytes = Vec::new();
+			prim.extend(&mut bytes);
+			ds.insert(name, DataRef {
+				bytes: &bytes,
+				type_info: format::TYPE_HINT_NONE | format::TYPE_DIM_SCALAR | prim.prim_type(),
+				compress_info: format::COMPRESS_NONE,
+				shape: Shape::Scalar,
+			});
+		},
+		Value::Text(text) => {
+			ds.insert(name, text.as_data_ref());
+		},
+		Value::Struct(fields) => {
+			let fo = write_struct(file, fields)?;
+			ds.insert(name, fo.as_data_ref());
+		},
+		Value::Dataset(fo) => {
+			ds.insert(name, fo.as_data_ref());
+		},
+		Value::Seq(items) => match items.iter().find(|item| !matches!(item, Value::None)).or(items.first()) {
+			// Missing items are null datasets, sequences of other kinds cannot represent them
+			Some(Value::Prim(_) | Value::Text(_) | Value::Seq(_)) if items.iter().any(|item| matches!(item, Value::None)) => {
+				return Err(unsupported("only sequences of structs with missing items"));
+			},
+			Some(Value::Text(_)) => {
+				let mut texts = Vec::with_capacity(items.len());
+				for item in &items {
+					let Value::Text(text) = item else {
+						return Err(unsupported("only sequences of the same kind"));
+					};
+					texts.push(text.as_str());
+				}
+				let mut data = Vec::new();
+				ds.insert(name, build_string_array_utf8(texts.iter().copied(), &mut data));
+			},
+			Some(Value::Struct(_) | Value::None | Value::Dataset(_)) => {
+				let mut children = Vec::with_capacity(items.len());
+				for item in items {
+					let fo = match item {
+						Value::Struct(fields) => write_struct(file, fields)?,
+						Value::Dataset(fo) => fo,
+						Value::None => format::FileOffset::NULL,
+						_ => return Err(unsupported("only sequences of the same kind")),
+					};
+					children.push(fo);
+				}
+				ds.insert(name, children.as_data_ref());
+			},
+			_ => {
+				let (type_info, shape, bytes) = write_array(&items)?;
+				ds.insert(name, DataRef { bytes: &bytes, type_info, compress_info: format::COMPRESS_NONE, shape });
+			},
+		},
+	}
+	Ok(())
+}
+
+// Flattens nested sequences of numbers with equal lengths
+fn write_array(items: &[Value]) -> Result<(u16, Shape, Vec<u8>), Error> {
+	let mut axes = vec![items.len()];
+	let mut first = items.first();
+	while let Some(Value::Seq(inner)) = first {
+		axes.push(inner.len());
+		first = inner.first();
+	}
+	let prim_type = match first {
+		Some(Value::Prim(prim)) => prim.prim_type(),
+		Some(_) => return Err(unsupported("only sequences of numbers, strings or structs")),
+		// Empty sequences have no element type
+		None => format::TYPE_PRIM_U8,
+	};
+	let (dims, shape) = match axes[..] {
+		[x] => (format::TYPE_DIM_1D, Shape::D1(x as u32)),
+		[x, y] if y < 0x1000000 => (format::TYPE_DIM_2D, Shape::D2(x as u32, y as u32)),
+		[x, y, z] if y < 0x1000000 && z < 0x100 => (format::TYPE_DIM_3D, Shape::D3(x as u32, y as u32, z as u8)),
+		_ => return Err(unsupported("only sequences of up to 3 dimensions")),
+	};
+
+	fn flatten(items: &[Value], axes: &[usize], prim_type: u16, bytes: &mut Vec<u8>) -> bool {
+		if items.len() != axes[0] {
+			return false;
+		}
+		for item in items {
+			match item {
+				Value::Prim(prim) if axes.len() == 1 && prim.prim_type() == prim_type => prim.extend(bytes),
+				Value::Seq(inner) if axes.len() > 1 => {
+					if !flatten(inner, &axes[1..], prim_type, bytes) {
+						return false;
+					}
+				},
+				_ => return false,
+			}
+		}
+		true
+	}
+
+	let mut bytes = Vec::new();
+	if !flatten(items, &axes, prim_type, &mut bytes) {
+		return Err(unsupported("only sequences with equal lengths and element types"));
+	}
+	Ok((format::TYPE_HINT_NONE | dims | prim_type, shape, bytes))
+}
+
+struct ValueSerializer;
+
+struct SeqSerializer {
+	items: Vec<Value>,
+}
+
+struct StructSerializer {
+	fields: Vec<(&'static str, Value)>,
+}
+
+impl ser::Serializer for ValueSerializer {
+	type Ok = Value;
+	type Error = Error;
+	type SerializeSeq = SeqSerializer;
+	type SerializeTuple = SeqSerializer;
+	type SerializeTupleStruct = SeqSerializer;
+	type SerializeTupleVariant = ser::Impossible<Value, Error>;
+	type SerializeMap = ser::Impossible<Value, Error>;
+	type SerializeStruct = StructSerializer;
+	type SerializeStructVariant = ser::Impossible<Value, Error>;
+
+	fn serialize_bool(self, v: bool) -> Result<Value, Error> { Ok(Value::Prim(Prim::U8(v as u8))) }
+	fn serialize_i8(self, v: i8) -> Result<Value, Error> { Ok(Value::Prim(Prim::I8(v))) }
+	fn serialize_i16(self, v: i16) -> Result<Value, Error> { Ok(Value::Prim(Prim::I16(v))) }
+	fn serialize_i32(self, v: i32) -> Result<Value, Error> { Ok(Value::Prim(Prim::I32(v))) }
+	fn serialize_i64(self, v: i64) -> Result<Value, Error> { Ok(Value::Prim(Prim::I64(v))) }
+	fn serialize_u8(self, v: u8) -> Result<Value, Error> { Ok(Value::Prim(Prim::U8(v))) }
+	fn serialize_u16(self, v: u16) -> Result<Value, Error> { Ok(Value::Prim(Prim::U16(v))) }
+	fn serialize_u32(self, v: u32) -> Result<Value, Error> { Ok(Value::Prim(Prim::U32(v))) }
+	fn serialize_u64(self, v: u64) -> Result<Value, Error> { Ok(Value::Prim(Prim::U64(v))) }
+	fn serialize_f32(self, v: f32) -> Result<Value, Error> { Ok(Value::Prim(Prim::F32(v))) }
+	fn serialize_f64(self, v: f64) -> Result<Value, Error> { Ok(Value::Prim(Prim::F64(v))) }
+	fn serialize_char(self, v: char) -> Result<Value, Error> { Ok(Value::Prim(Prim::U32(v as u32))) }
+	fn serialize_str(self, v: &str) -> Result<Value, Error> { Ok(Value::Text(String::from(v))) }
+	fn serialize_bytes(self, v: &[u8]) -> Result<Value, Error> {
+		Ok(Value::Seq(v.iter().map(|&byte| Value::Prim(Prim::U8(byte))).collect()))
+	}
+	fn serialize_none(self) -> Result<Value, Error> { Ok(Value::None) }
+	fn serialize_some<T: ?Sized + ser::Serialize>(self, value: &T) -> Result<Value, Error> {
+		value.serialize(self)
+	}
+	fn serialize_unit(self) -> Result<Value, Error> { Ok(Value::None) }
+	fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, Error> { Ok(Value::None) }
+	fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<Value, Error> {
+		Ok(Value::Text(String::from(variant)))
+	}
+	fn serialize_newtype_struct<T: ?Sized + ser::Serialize>(self, _name: &'static str, value: &T) -> Result<Value, Error> {
+		value.serialize(self)
+	}
+	fn serialize_newtype_variant<T: ?Sized + ser::Serialize>(self, _name: &'static str, _index: u32, _variant: &'static str, _value: &T) -> Result<Value, Error> {
+		Err(unsupported("only unit enum variants"))
+	}
+	fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, Error> {
+		Ok(SeqSerializer { items: Vec::with_capacity(len.unwrap_or(0)) })
+	}
+	fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, Error> {
+		Ok(SeqSerializer { items: Vec::with_capacity(len) })
+	}
+	fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SeqSerializer, Error> {
+		Ok(SeqSerializer { items: Vec::with_capacity(len) })
+	}
+	fn serialize_tuple_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant, Error> {
+		Err(unsupported("only unit enum variants"))
+	}
+	fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+		Err(unsupported("only structs, not maps,"))
+	}
+	fn serialize_struct(self, _name: &'static str, len: usize) -> Result<StructSerializer, Error> {
+		Ok(StructSerializer { fields: Vec::with_capacity(len) })
+	}
+	fn serialize_struct_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant, Error> {
+		Err(unsupported("only unit enum variants"))
+	}
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+	type Ok = Value;
+	type Error = Error;
+	fn serialize_element<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<(), Error> {
+		self.items.push(value.serialize(ValueSerializer)?);
+		Ok(())
+	}
+	fn end(self) -> Result<Value, Error> {
+		Ok(Value::Seq(self.items))
+	}
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+	type Ok = Value;
+	type Error = Error;
+	fn serialize_element<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<(), Error> {
+		ser::SerializeSeq::serialize_element(self, value)
+	}
+	fn end(self) -> Result<Value, Error> {
+		ser::SerializeSeq::end(self)
+	}
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+	type Ok = Value;
+	type Error = Error;
+	fn serialize_field<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<(), Error> {
+		ser::SerializeSeq::serialize_element(self, value)
+	}
+	fn end(self) -> Result<Value, Error> {
+		ser::SerializeSeq::end(self)
+	}
+}
+
+impl ser::SerializeStruct for StructSerializer {
+	type Ok = Value;
+	type Error = Error;
+	fn serialize_field<T: ?Sized + ser::Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+		let value = value.serialize(ValueSerializer).map_err(|err| err.with_key_name(hash(key)))?;
+		self.fields.push((key, value));
+		Ok(())
+	}
+	fn skip_field(&mut self, _key: &'static str) -> Result<(), Error> {
+		Ok(())
+	}
+	fn end(self) -> Result<Value, Error> {
+		Ok(Value::Struct(self.fields))
+	}
+}
+
+//----------------------------------------------------------------
+// Deserialization
+
+fn read_table(ds: &DatasetRef, table: &format::TableDesc) -> Result<Value, Error> {
+	let key_name = table.key_name;
+	let data = ds.get_data_ref_verified(table)?;
+	let dims = data.type_info & format::TYPE_DIM_MASK;
+	match data.type_info & format::TYPE_HINT_MASK {
+		format::TYPE_HINT_TEXT if dims == format::TYPE_DIM_SCALAR => {
+			Ok(Value::Text(element::read_text(key_name, data)?.into_owned()))
+		},
+		format::TYPE_HINT_TEXT => {
+			let texts = element::read_text_list(key_name, data)?;
+			Ok(Value::Seq(texts.into_iter().map(Value::Text).collect()))
+		},
+		format::TYPE_HINT_DATASET => {
+			let children = element::read::<format::FileOffset>(key_name, data)?;
+			if dims == format::TYPE_DIM_SCALAR {
+				let &[fo] = &*children else {
+					return Err(Error::InvalidTableData { file_offset: format::FileOffset::NULL, key_name });
+				};
+				return Ok(Value::Dataset(fo));
+			}
+			Ok(Value::Seq(children.iter().map(|&fo| Value::Dataset(fo)).collect()))
+		},
+		_ => {
+			let prim_type = data.type_info & format::TYPE_PRIM_MASK;
+			let Some(size) = Prim::size_of(prim_type) else {
+				return Err(element::mismatch(key_name, &data, "serde value"));
+			};
+			let mut storage = Vec::new();
+			let data = data.decompress(&mut storage);
+			let len = data.shape.len();
+			let bytes = match data.bytes.get(..len * size) {
+				Some(bytes) if !data.is_compressed() => bytes,
+				_ => return Err(Error::InvalidTableData { file_offset: format::FileOffset::NULL, key_name }),
+			};
+			let mut prims = bytes.chunks_exact(size).filter_map(|bytes| Prim::read(prim_type, bytes));
+			let value = match data.shape {
+				Shape::Scalar => nest(&mut prims, &[]),
+				Shape::D1(x) => nest(&mut prims, &[x]),
+				Shape::D2(x, y) => nest(&mut prims, &[x, y]),
+				Shape::D3(x, y, z) => nest(&mut prims, &[x, y, z as u32]),
+			};
+			Ok(value)
+		},
+	}
+}
+
+// Nests the numbers in sequences along the axes
+fn nest(prims: &mut dyn Iterator<Item = Prim>, axes: &[u32]) -> Value {
+	match axes.split_first() {
+		Some((&len, axes)) => Value::Seq((0..len).map(|_| nest(prims, axes)).collect()),
+		None => prims.next().map_or(Value::None, Value::Prim),
+	}
+}
+
+struct DatasetDeserializer<'a, B> {
+	file: &'a mut FileIO<B>,
+	dataset: Dataset,
+}
+
+impl<'de, 'a, B: Backend> de::Deserializer<'de> for DatasetDeserializer<'a, B> {
+	type Error = Error;
+
+	fn deserialize_any<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+		Err(unsupported("only structs"))
+	}
+	fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_some(self)
+	}
+	fn deserialize_newtype_struct<V: de::Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_newtype_struct(self)
+	}
+	fn deserialize_struct<V: de::Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+		let dataset = self.dataset.as_ref();
+		visitor.visit_map(StructAccess { file: self.file, dataset: &dataset, fields: fields.iter(), value: None }).map_err(|err| match err {
+			// Missing fields are reported without the dataset, find their key names in the names lookup
+			Error::TableNotFound { file_offset, key_name } => {
+				let key_name = fields.iter().find(|&&field| hash(field) == key_name).map_or(key_name, |field| dataset.names.key_name(field));
+				Error::TableNotFound { file_offset, key_name }
+			},
+			err => err,
+		})
+	}
+
+	serde::forward_to_deserialize_any! {
+		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+		bytes byte_buf unit unit_struct seq tuple tuple_struct map enum identifier ignored_any
+	}
+}
+
+struct StructAccess<'a, 'd, B> {
+	file: &'a mut FileIO<B>,
+	dataset: &'a DatasetRef<'d>,
+	fields: std::slice::Iter<'static, &'static str>,
+	value: Option<(u32, Value)>,
+}
+
+impl<'de, 'a, 'd, B: Backend> de::MapAccess<'de> for StructAccess<'a, 'd, B> {
+	type Error = Error;
+
+	fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+		// Skip the fields without table, they are deserialized as missing
+		for &field in self.fields.by_ref() {
+			let key_name = self.dataset.names.key_name(field);
+			if let Some(table) = self.dataset.find_table(key_name) {
+				let value = read_table(self.dataset, table).map_err(|err| err.with_key_name(key_name))?;
+				self.value = Some((key_name, value));
+				return seed.deserialize(field.into_deserializer()).map(Some);
+			}
+		}
+		Ok(None)
+	}
+
+	fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+		let Some((key_name, value)) = self.value.take() else {
+			return Err(de::Error::custom("value is missing"));
+		};
+		seed.deserialize(ValueDeserializer { file: self.file, value }).map_err(|err| err.with_key_name(key_name))
+	}
+}
+
+struct ValueDeserializer<'a, B> {
+	file: &'a mut FileIO<B>,
+	value: Value,
+}
+
+impl<'de, 'a, B: Backend> de::Deserializer<'de> for ValueDeserializer<'a, B> {
+	type Error = Error;
+
+	fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		match self.value {
+			Value::None => visitor.visit_unit(),
+			Value::Prim(prim) => prim.visit(visitor),
+			Value::Text(text) => visitor.visit_string(text),
+			Value::Seq(items) => visitor.visit_seq(SeqAccess { file: self.file, items: items.into_iter() }),
+			Value::Struct(_) | Value::Dataset(_) => Err(unsupported("only structs")),
+		}
+	}
+	fn deserialize_bool<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		match self.value {
+			Value::Prim(prim) => visitor.visit_bool(!prim.is_zero()),
+			_ => self.deserialize_any(visitor),
+		}
+	}
+	fn deserialize_char<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		match self.value {
+			Value::Prim(Prim::U32(chr)) => match char::from_u32(chr) {
+				Some(chr) => visitor.visit_char(chr),
+				None => Err(de::Error::invalid_value(de::Unexpected::Unsigned(chr as u64), &"a char")),
+			},
+			_ => self.deserialize_any(visitor),
+		}
+	}
+	fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		match self.value {
+			Value::None => visitor.visit_none(),
+			Value::Dataset(fo) if fo.is_null() => visitor.visit_none(),
+			_ => visitor.visit_some(self),
+		}
+	}
+	fn deserialize_newtype_struct<V: de::Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_newtype_struct(self)
+	}
+	fn deserialize_struct<V: de::Visitor<'de>>(self, name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+		match self.value {
+			Value::Dataset(fo) if !fo.is_null() => {
+				let dataset = self.file.read_dataset(fo)?;
+				DatasetDeserializer { file: self.file, dataset }.deserialize_struct(name, fields, visitor).map_err(|err| err.at(fo))
+			},
+			_ => self.deserialize_any(visitor),
+		}
+	}
+	fn deserialize_enum<V: de::Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+		match self.value {
+			Value::Text(text) => visitor.visit_enum(text.into_deserializer()),
+			_ => Err(unsupported("only unit enum variants")),
+		}
+	}
+
+	serde::forward_to_deserialize_any! {
+		i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 str string
+		bytes byte_buf unit unit_struct seq tuple tuple_struct map identifier ignored_any
+	}
+}
+
+struct SeqAccess<'a, B> {
+	file: &'a mut FileIO<B>,
+	items: vec::IntoIter<Value>,
+}
+
+impl<'de, 'a, B: Backend> de::SeqAccess<'de> for SeqAccess<'a, B> {
+	type Error = Error;
+
+	fn next_element_seed<T: de::DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+		match self.items.next() {
+			Some(value) => seed.deserialize(ValueDeserializer { file: self.file, value }).map(Some),
+			None => Ok(None),
+		}
+	}
+	fn size_hint(&self) -> Option<usize> {
+		Some(self.items.len())
+	}
+}
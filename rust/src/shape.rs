@@ -1,4 +1,4 @@
-use std::{fmt, str};
+use core::{fmt, str};
 use crate::*;
 use format::{TYPE_DIM_MASK, TYPE_DIM_SCALAR, TYPE_DIM_1D, TYPE_DIM_2D, TYPE_DIM_3D};
 
@@ -60,6 +60,15 @@ impl Shape {
 		return Shape::D3(x, y, z);
 	}
 
+	// Decodes the number of logical dimensions and the lengths of all axes
+	// The axes following the logical dimensions are the ghost dimensions
+	#[inline]
+	pub(crate) fn decode_axes(type_info: u16, shape: [u32; 2]) -> (usize, [u32; 3]) {
+		let dims = ((type_info & TYPE_DIM_MASK) >> 4) as usize;
+		let axes = [shape[0], shape[1] & 0xffffff, shape[1] >> 24];
+		(dims, axes)
+	}
+
 	/// Returns the total number of elements.
 	#[inline]
 	pub fn len(&self) -> usize {
@@ -10,3 +10,34 @@ pub struct TableRef<'a> {
 
 impl<'a> TableRef<'a> {
 }
+
+/// Sets the optional names of a table inserted with [`Dataset::insert`].
+///
+/// The names are hashed and registered in the names lookup of the dataset.
+pub struct TableBuilder<'a> {
+	pub(crate) dataset: &'a mut Dataset,
+	pub(crate) index: usize,
+}
+
+impl<'a> TableBuilder<'a> {
+	/// Sets the index name.
+	pub fn index(self, name: &str) -> TableBuilder<'a> {
+		let hash = self.dataset.names.insert(name);
+		self.dataset.descs[self.index].index_name = hash;
+		self
+	}
+
+	/// Sets the related name.
+	pub fn related(self, name: &str) -> TableBuilder<'a> {
+		let hash = self.dataset.names.insert(name);
+		self.dataset.descs[self.index].related_name = hash;
+		self
+	}
+
+	/// Sets the type name.
+	pub fn type_name(self, name: &str) -> TableBuilder<'a> {
+		let hash = self.dataset.names.insert(name);
+		self.dataset.descs[self.index].type_name = hash;
+		self
+	}
+}
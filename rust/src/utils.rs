@@ -1,4 +1,4 @@
-use std::{fmt, mem, str};
+use core::{fmt, mem, str};
 use crate::*;
 
 /// File size formatter.
@@ -58,7 +58,7 @@ pub struct PrintId(pub [u8; 4]);
 
 impl fmt::Display for PrintId {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		use std::fmt::Write;
+		use core::fmt::Write;
 
 		// Shorten the code if terminated with nul bytes
 		let mut bytes = self.0.as_slice();
@@ -105,6 +105,12 @@ impl str::FromStr for PrintId {
 	}
 }
 
+// Identifiers are printable ascii padded with nul bytes
+pub(crate) fn is_printable_id(id: &[u8; 4]) -> bool {
+	let len = id.iter().position(|&chr| chr == 0).unwrap_or(id.len());
+	id[..len].iter().all(|chr| (0x20..0x7f).contains(chr)) && id[len..].iter().all(|&chr| chr == 0)
+}
+
 /// Type info formatter.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, dataview::Pod)]
 #[repr(transparent)]
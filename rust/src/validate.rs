@@ -0,0 +1,749 @@
+/*!
+UDF file validation.
+*/
+
+use std::{char, fmt, mem, str};
+use std::collections::{HashMap, HashSet};
+use crate::*;
+
+/// Validates the UDF file.
+///
+/// Walks all the datasets reachable from the root dataset and checks them for errors.
+pub fn validate<B: Backend>(file: &mut FileIO<B>) -> Report {
+	// Datasets are parsed leniently to diagnose their headers in detail
+	let mode = file.parse_mode();
+	file.set_parse_mode(ParseMode::Lenient);
+
+	let mut validator = Validator { set: HashSet::new(), report: Report::default() };
+	validator.validate_file_header(file);
+	// Errors walking the datasets are reported as diagnostics
+	let _ = walk::walk(file, file.root(), u32::MAX, &mut validator);
+
+	file.set_parse_mode(mode);
+	validator.report
+}
+
+/// Diagnostic severity.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Severity {
+	Warning,
+	Error,
+}
+
+impl fmt::Display for Severity {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Severity::Warning => f.pad("warn"),
+			Severity::Error => f.pad("err"),
+		}
+	}
+}
+
+/// Validation rules.
+///
+/// Each diagnostic refers to the rule that was violated.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Rule {
+	/// Reserved field or bits are not zero.
+	ReservedField,
+	/// Identifier contains characters other than printable ASCII.
+	InvalidId,
+	/// File offset is not valid.
+	InvalidFileOffset,
+	/// Commit record is not valid.
+	InvalidCommit,
+	/// Dataset file offset is null.
+	NullDataset,
+	/// Dataset file offset is not 16-byte aligned.
+	UnalignedDataset,
+	/// Dataset is too large.
+	LargeDataset,
+	/// Dataset refers back to itself.
+	CyclicDataset,
+	/// Dataset failed to read or parse.
+	InvalidDataset,
+	/// Dataset has no tables.
+	EmptyDataset,
+	/// Dataset check value is incorrect.
+	CheckValue,
+	/// Dataset header size does not match its contents.
+	HeaderSize,
+	/// Names lookup entries are not valid.
+	InvalidLookup,
+	/// Dataset header checksum does not match.
+	HeaderChecksum,
+	/// Dataset is flagged sorted but its tables are not sorted by key name.
+	UnsortedTables,
+	/// Table key name is null.
+	NullKeyName,
+	/// Table key name is not unique.
+	DuplicateKeyName,
+	/// Name is not found in the names lookup.
+	InvalidName,
+	/// Table primitive type is unknown.
+	UnknownPrimitive,
+	/// Table compression is unknown.
+	UnknownCompression,
+	/// Table type hint is unknown.
+	UnknownHint,
+	/// Table primitive type is not allowed by its type hint.
+	HintPrimitive,
+	/// Table ghost dimensions are not allowed by its type hint.
+	GhostDimensions,
+	/// Table memory range is not valid.
+	MemoryRange,
+	/// Table shape does not match the data size.
+	ShapeMismatch,
+	/// Table data checksum does not match.
+	TableChecksum,
+	/// Table index name is invalid.
+	InvalidIndex,
+	/// Table index or range values are out of bounds.
+	IndexBounds,
+	/// Table related name is invalid.
+	InvalidRelated,
+	/// Related tables do not have the same shape.
+	RelatedShape,
+	/// Table text is not valid.
+	InvalidText,
+	/// Table JSON is not valid.
+	InvalidJson,
+}
+
+impl Rule {
+	/// Returns the stable code for this rule.
+	pub const fn code(self) -> &'static str {
+		match self {
+			Rule::ReservedField => "reserved-field",
+			Rule::InvalidId => "invalid-id",
+			Rule::InvalidFileOffset => "invalid-file-offset",
+			Rule::InvalidCommit => "invalid-commit",
+			Rule::NullDataset => "null-dataset",
+			Rule::UnalignedDataset => "unaligned-dataset",
+			Rule::LargeDataset => "large-dataset",
+			Rule::CyclicDataset => "cyclic-dataset",
+			Rule::InvalidDataset => "invalid-dataset",
+			Rule::EmptyDataset => "empty-dataset",
+			Rule::CheckValue => "check-value",
+			Rule::HeaderSize => "header-size",
+			Rule::InvalidLookup => "invalid-lookup",
+			Rule::HeaderChecksum => "header-checksum",
+			Rule::UnsortedTables => "unsorted-tables",
+			Rule::NullKeyName => "null-key-name",
+			Rule::DuplicateKeyName => "duplicate-key-name",
+			Rule::InvalidName => "invalid-name",
+			Rule::UnknownPrimitive => "unknown-primitive",
+			Rule::UnknownCompression => "unknown-compression",
+			Rule::UnknownHint => "unknown-hint",
+			Rule::HintPrimitive => "hint-primitive",
+			Rule::GhostDimensions => "ghost-dimensions",
+			Rule::MemoryRange => "memory-range",
+			Rule::ShapeMismatch => "shape-mismatch",
+			Rule::TableChecksum => "table-checksum",
+			Rule::InvalidIndex => "invalid-index",
+			Rule::IndexBounds => "index-bounds",
+			Rule::InvalidRelated => "invalid-related",
+			Rule::RelatedShape => "related-shape",
+			Rule::InvalidText => "invalid-text",
+			Rule::InvalidJson => "invalid-json",
+		}
+	}
+}
+
+impl fmt::Display for Rule {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.pad(self.code())
+	}
+}
+
+/// Validation diagnostic.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+	pub severity: Severity,
+	pub rule: Rule,
+	/// File offset of the dataset being validated.
+	pub file_offset: format::FileOffset,
+	/// Name of the table being validated, if any.
+	pub table: Option<String>,
+	/// Human readable description.
+	pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}[{}]: {}", self.severity, self.rule, self.message)
+	}
+}
+
+/// Validation report.
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+	/// Number of datasets processed.
+	pub datasets: usize,
+	/// Diagnostics in the order they were found.
+	pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Report {
+	/// Returns the number of warnings.
+	pub fn warnings(&self) -> usize {
+		self.diagnostics.iter().filter(|diag| diag.severity == Severity::Warning).count()
+	}
+
+	/// Returns the number of errors.
+	pub fn errors(&self) -> usize {
+		self.diagnostics.iter().filter(|diag| diag.severity == Severity::Error).count()
+	}
+
+	/// Returns `true` if no errors were found.
+	pub fn is_ok(&self) -> bool {
+		self.errors() == 0
+	}
+}
+
+struct Validator {
+	set: HashSet<format::FileOffset>,
+	report: Report,
+}
+
+impl Validator {
+	fn push(&mut self, severity: Severity, rule: Rule, fo: format::FileOffset, table: Option<&NameOrHash>, message: fmt::Arguments) {
+		self.report.diagnostics.push(Diagnostic {
+			severity,
+			rule,
+			file_offset: fo,
+			table: table.map(|name| name.to_string()),
+			message: message.to_string(),
+		});
+	}
+
+	fn validate_file_header<B: Backend>(&mut self, file: &mut FileIO<B>) {
+		let header = *file.header();
+		let fo = format::FileOffset::NULL;
+
+		if header.reserved != [0; 4] {
+			self.push(Severity::Error, Rule::ReservedField, fo, None, format_args!("file header reserved field is not zero"));
+		}
+		if !utils::is_printable_id(&header.id) {
+			self.push(Severity::Error, Rule::InvalidId, fo, None, format_args!("file id {:?} is not printable ascii", PrintId(header.id)));
+		}
+		if header.root.offset == 0 && header.root.size != 0 {
+			self.push(Severity::Error, Rule::InvalidFileOffset, fo, None, format_args!("root {} has non-zero size", header.root));
+		}
+		if header.next != 0 {
+			if let Err(err) = file.read_commit(header.next) {
+				self.push(Severity::Error, Rule::InvalidCommit, fo, None, format_args!("{}", err));
+			}
+		}
+	}
+
+	fn validate_header(&mut self, fo: format::FileOffset, dataset: &DatasetRef<'_>) {
+		let header = dataset.header;
+
+		if header.check != format::DatasetHeader::CHECK {
+			self.push(Severity::Error, Rule::CheckValue, fo, None, format_args!("dataset {} check value {:#010x} is incorrect", fo, header.check));
+		}
+		if !utils::is_printable_id(&header.id) {
+			self.push(Severity::Error, Rule::InvalidId, fo, None, format_args!("dataset {} id {:?} is not printable ascii", fo, PrintId(header.id)));
+		}
+		if !header.string_len.is_multiple_of(8) {
+			self.push(Severity::Error, Rule::HeaderSize, fo, None, format_args!("dataset {} string_len {} is not a multiple of 8", fo, header.string_len));
+		}
+		let size = mem::size_of::<format::DatasetHeader>()
+			+ header.descs_len as usize * mem::size_of::<format::TableDesc>()
+			+ header.lookup_len as usize * mem::size_of::<format::LookupEntry>()
+			+ header.string_len as usize;
+		if header.size as usize != size {
+			self.push(Severity::Error, Rule::HeaderSize, fo, None, format_args!("dataset {} header_size {:#x} does not match its contents {:#x}", fo, header.size, size));
+		}
+		if header.flags & !format::DatasetHeader::FLAGS_MASK != 0 {
+			self.push(Severity::Error, Rule::ReservedField, fo, None, format_args!("dataset {} flags {:#06x} has reserved bits set", fo, header.flags));
+		}
+		if header.reserved != [0; 1] {
+			self.push(Severity::Error, Rule::ReservedField, fo, None, format_args!("dataset {} reserved field is not zero", fo));
+		}
+		if dataset.is_sorted() && !dataset_ref::is_sorted(dataset.tables) {
+			self.push(Severity::Error, Rule::UnsortedTables, fo, None, format_args!("dataset {} is flagged sorted but its tables are not sorted by key name", fo));
+		}
+	}
+
+	// Lookup entries must be sorted by their non-zero hash and refer to valid strings
+	fn validate_lookup(&mut self, fo: format::FileOffset, names: &NamesRef<'_>) {
+		let mut prev = 0;
+		for (index, entry) in names.entries.iter().enumerate() {
+			if entry.hash == 0 {
+				self.push(Severity::Error, Rule::InvalidLookup, fo, None, format_args!("lookup entry (index={}) has zero hash", index));
+			}
+			else if entry.hash <= prev {
+				self.push(Severity::Error, Rule::InvalidLookup, fo, None, format_args!("lookup entry (index={} hash={:#010x}) is not sorted", index, entry.hash));
+			}
+			prev = entry.hash;
+
+			let start = entry.offset as usize;
+			let end = start + entry.len as usize;
+			match names.strings.get(start..end) {
+				Some(bytes) => if let Err(err) = str::from_utf8(bytes) {
+					self.push(Severity::Error, Rule::InvalidLookup, fo, None, format_args!("lookup entry (index={} hash={:#010x}) invalid utf8: {}", index, entry.hash, err));
+				},
+				None => {
+					self.push(Severity::Error, Rule::InvalidLookup, fo, None, format_args!("lookup entry (index={} hash={:#010x}) string out of bounds", index, entry.hash));
+				},
+			}
+		}
+	}
+
+	fn validate_type_info(&mut self, fo: format::FileOffset, key_name: &NameOrHash, table: &format::TableDesc) {
+		if table.type_info & TYPE_RESERVED_MASK != 0 {
+			self.push(Severity::Error, Rule::ReservedField, fo, Some(key_name), format_args!("table {} type_info {:#06x} has reserved bits set", key_name, table.type_info));
+		}
+
+		match table.compress_info {
+			format::COMPRESS_NONE |
+			format::COMPRESS_SIMPLE_U16 |
+			format::COMPRESS_SIMPLE_U32 |
+			format::COMPRESS_SIMPLE_U64 |
+			format::COMPRESS_SIMPLE_F32 |
+			format::COMPRESS_SIMPLE_F64 => (),
+			compress_info => {
+				self.push(Severity::Warning, Rule::UnknownCompression, fo, Some(key_name), format_args!("table {} unknown compression {}", key_name, compress_info));
+			},
+		}
+
+		// Not required to be zero but it may confuse older readers
+		if table.reserved != [0; 1] {
+			self.push(Severity::Warning, Rule::ReservedField, fo, Some(key_name), format_args!("table {} reserved field is not zero", key_name));
+		}
+	}
+
+	// Check that the data is within the memory blocks of the storage
+	fn validate_memory(&mut self, fo: format::FileOffset, dataset: &DatasetRef<'_>, key_name: &NameOrHash, table: &format::TableDesc) -> bool {
+		if table.mem_end < table.mem_start {
+			self.push(Severity::Error, Rule::MemoryRange, fo, Some(key_name), format_args!("table {} mem_end {} is less than mem_start {}", key_name, table.mem_end, table.mem_start));
+			return false;
+		}
+		if table.mem_end as usize > dataset.storage.len() {
+			self.push(Severity::Error, Rule::MemoryRange, fo, Some(key_name), format_args!("table {} mem_end {} is out of bounds of the storage {}", key_name, table.mem_end, dataset.storage.len()));
+			return false;
+		}
+		let capacity = (table.mem_end - table.mem_start) as u64 * 8;
+		if table.data_size as u64 > capacity {
+			self.push(Severity::Error, Rule::MemoryRange, fo, Some(key_name), format_args!("table {} data_size {:#x} does not fit in {:#x} bytes", key_name, table.data_size, capacity));
+			return false;
+		}
+		true
+	}
+
+	fn validate_data<'a>(&mut self, fo: format::FileOffset, dataset: &DatasetRef<'a>, key_name: &NameOrHash, table: &format::TableDesc) -> Option<DataRef<'a>> {
+		match dataset.get_data_ref_verified(table) {
+			Ok(data) => Some(data),
+			Err(Error::ChecksumMismatch { .. }) => {
+				self.push(Severity::Error, Rule::TableChecksum, fo, Some(key_name), format_args!("table {} checksum mismatch!", key_name));
+				dataset.get_data_ref(table)
+			},
+			Err(_) => None,
+		}
+	}
+
+	// Check that the shape matches the data size
+	fn validate_shape(&mut self, fo: format::FileOffset, key_name: &NameOrHash, table: &format::TableDesc) {
+		let prim_type = table.type_info & format::TYPE_PRIM_MASK;
+		let shape = Shape::from_shape(table.type_info, table.data_shape);
+		let data_size = table.data_size as usize;
+		let shape_len = shape.len();
+
+		// Compressed data size has no relationship with the shape
+		if table.compress_info != format::COMPRESS_NONE {
+			return;
+		}
+
+		let success = match prim_type {
+			// Cannot verify the relationship between data size and shape
+			format::TYPE_PRIM_CUSTOM => {
+				return;
+			},
+			format::TYPE_PRIM_I8 | format::TYPE_PRIM_U8 => {
+				shape_len == data_size
+			},
+			format::TYPE_PRIM_I16 | format::TYPE_PRIM_U16 => {
+				data_size.is_multiple_of(2) && shape_len == data_size / 2
+			},
+			format::TYPE_PRIM_I32 | format::TYPE_PRIM_U32 | format::TYPE_PRIM_F32 => {
+				data_size.is_multiple_of(4) && shape_len == data_size / 4
+			},
+			format::TYPE_PRIM_I64 | format::TYPE_PRIM_U64 | format::TYPE_PRIM_F64 => {
+				data_size.is_multiple_of(8) && shape_len == data_size / 8
+			},
+			_ => {
+				self.push(Severity::Warning, Rule::UnknownPrimitive, fo, Some(key_name), format_args!("table {} unknown primitive type {:#x}!", key_name, prim_type));
+				return;
+			},
+		};
+
+		if !success {
+			let prim_name = PrintTypeInfo::prim(prim_type).unwrap_or("?");
+			self.push(Severity::Error, Rule::ShapeMismatch, fo, Some(key_name), format_args!("table {} has shape {} with {} elements of {} but data size {:#x} does not match!",
+				key_name, shape, shape_len, prim_name, data_size));
+		}
+	}
+
+	fn validate_prim(&mut self, fo: format::FileOffset, key_name: &NameOrHash, table: &format::TableDesc, allowed: &[u16]) -> bool {
+		let prim_type = table.type_info & format::TYPE_PRIM_MASK;
+		if !allowed.contains(&prim_type) {
+			let hint_name = PrintTypeInfo::hint(table.type_info).unwrap_or("?");
+			let prim_name = PrintTypeInfo::prim(prim_type).unwrap_or("?");
+			self.push(Severity::Error, Rule::HintPrimitive, fo, Some(key_name), format_args!("table {} type hint {} does not allow primitive type {}", key_name, hint_name, prim_name));
+			return false;
+		}
+		true
+	}
+
+	// Check the number of ghost dimensions and returns the first ghost dimension
+	//
+	// Ghost dimensions of length zero are indistinguishable from absent dimensions.
+	fn validate_ghost(&mut self, fo: format::FileOffset, key_name: &NameOrHash, table: &format::TableDesc, count: usize) -> Option<u32> {
+		let (dims, axes) = Shape::decode_axes(table.type_info, table.data_shape);
+		if dims + count > 3 || axes[dims + count..].iter().any(|&len| len != 0) {
+			let hint_name = PrintTypeInfo::hint(table.type_info).unwrap_or("?");
+			let shape = Shape::from_shape(table.type_info, table.data_shape);
+			self.push(Severity::Error, Rule::GhostDimensions, fo, Some(key_name), format_args!("table {} with type hint {} requires {} ghost dimensions but has shape {}", key_name, hint_name, count, shape));
+			return None;
+		}
+		axes.get(dims).copied()
+	}
+
+	fn validate_hint_none(&mut self, fo: format::FileOffset, key_name: &NameOrHash, table: &format::TableDesc) {
+		self.validate_ghost(fo, key_name, table, 0);
+	}
+
+	fn validate_hint_text(&mut self, fo: format::FileOffset, key_name: &NameOrHash, table: &format::TableDesc, data: Option<&DataRef>) {
+		let prim_ok = self.validate_prim(fo, key_name, table, &[format::TYPE_PRIM_U8, format::TYPE_PRIM_I8, format::TYPE_PRIM_U16, format::TYPE_PRIM_U32]);
+		let Some(width) = self.validate_ghost(fo, key_name, table, 1) else { return };
+		let Some(data) = data else { return };
+		if !prim_ok || data.is_compressed() {
+			return;
+		}
+
+		// Each string element is validated separately
+		let (dims, _) = Shape::decode_axes(table.type_info, table.data_shape);
+		let width = if dims == 0 { usize::MAX } else { usize::max(width as usize, 1) };
+
+		match table.type_info & format::TYPE_PRIM_MASK {
+			format::TYPE_PRIM_I8 | format::TYPE_PRIM_U8 => {
+				for (index, chunk) in data.bytes.chunks(width).enumerate() {
+					if let Err(err) = str::from_utf8(chunk) {
+						self.push(Severity::Error, Rule::InvalidText, fo, Some(key_name), format_args!("table {} (index={}) invalid utf8: {}", key_name, index, err));
+						return;
+					}
+				}
+			},
+			format::TYPE_PRIM_U16 => {
+				let words = data.as_slice::<u16>().unwrap_or(&[]);
+				for (index, chunk) in words.chunks(width).enumerate() {
+					if !char::decode_utf16(chunk.iter().copied()).all(|x| x.is_ok()) {
+						self.push(Severity::Error, Rule::InvalidText, fo, Some(key_name), format_args!("table {} (index={}) invalid utf16!", key_name, index));
+						return;
+					}
+				}
+			},
+			format::TYPE_PRIM_U32 => {
+				let chars = data.as_slice::<u32>().unwrap_or(&[]);
+				for (index, chunk) in chars.chunks(width).enumerate() {
+					if !chunk.iter().all(|&chr| char::from_u32(chr).is_some()) {
+						self.push(Severity::Error, Rule::InvalidText, fo, Some(key_name), format_args!("table {} (index={}) invalid utf32!", key_name, index));
+						return;
+					}
+				}
+			},
+			_ => (),
+		}
+	}
+
+	fn validate_hint_json(&mut self, fo: format::FileOffset, key_name: &NameOrHash, table: &format::TableDesc, data: Option<&DataRef>) {
+		self.validate_prim(fo, key_name, table, &[format::TYPE_PRIM_CUSTOM]);
+		self.validate_ghost(fo, key_name, table, 0);
+		let Some(data) = data else { return };
+		if data.is_compressed() {
+			return;
+		}
+		if let Err(err) = str::from_utf8(data.bytes) {
+			self.push(Severity::Error, Rule::InvalidJson, fo, Some(key_name), format_args!("table {} invalid utf8: {}", key_name, err));
+		}
+	}
+
+	fn validate_hint_dataset(&mut self, fo: format::FileOffset, key_name: &NameOrHash, table: &format::TableDesc) {
+		self.validate_prim(fo, key_name, table, &[format::TYPE_PRIM_U64]);
+		// File offsets to the child datasets cannot be compressed
+		if table.compress_info != format::COMPRESS_NONE {
+			self.push(Severity::Error, Rule::InvalidFileOffset, fo, Some(key_name), format_args!("table {} file offsets are compressed", key_name));
+		}
+		if let Some(len) = self.validate_ghost(fo, key_name, table, 1) {
+			if len != 2 {
+				self.push(Severity::Error, Rule::GhostDimensions, fo, Some(key_name), format_args!("table {} ghost dimension {} must be 2", key_name, len));
+			}
+		}
+	}
+
+	fn validate_hint_index(&mut self, fo: format::FileOffset, key_name: &NameOrHash, table: &format::TableDesc) {
+		self.validate_prim(fo, key_name, table, INDEX_PRIMS);
+	}
+
+	fn validate_hint_range(&mut self, fo: format::FileOffset, key_name: &NameOrHash, table: &format::TableDesc) {
+		self.validate_prim(fo, key_name, table, INDEX_PRIMS);
+		if let Some(len) = self.validate_ghost(fo, key_name, table, 1) {
+			if len != 2 {
+				self.push(Severity::Error, Rule::GhostDimensions, fo, Some(key_name), format_args!("table {} ghost dimension {} must be 2", key_name, len));
+			}
+		}
+	}
+
+	fn validate_hint_coord(&mut self, fo: format::FileOffset, key_name: &NameOrHash, table: &format::TableDesc) {
+		self.validate_prim(fo, key_name, table, &[format::TYPE_PRIM_I8, format::TYPE_PRIM_I16, format::TYPE_PRIM_I32, format::TYPE_PRIM_I64, format::TYPE_PRIM_F32, format::TYPE_PRIM_F64]);
+		// The ghost dimension is the number of dimensions of the coordinate
+		if let Some(0) = self.validate_ghost(fo, key_name, table, 1) {
+			self.push(Severity::Error, Rule::GhostDimensions, fo, Some(key_name), format_args!("table {} coordinates have zero dimensions", key_name));
+		}
+	}
+
+	fn validate_hint_line(&mut self, fo: format::FileOffset, key_name: &NameOrHash, table: &format::TableDesc) {
+		self.validate_prim(fo, key_name, table, &[format::TYPE_PRIM_F32, format::TYPE_PRIM_F64]);
+	}
+
+	fn validate_hint_transform(&mut self, fo: format::FileOffset, key_name: &NameOrHash, table: &format::TableDesc) {
+		self.validate_prim(fo, key_name, table, &[format::TYPE_PRIM_F32, format::TYPE_PRIM_F64]);
+		self.validate_ghost(fo, key_name, table, 2);
+	}
+
+	fn validate_hint_rgb(&mut self, fo: format::FileOffset, key_name: &NameOrHash, table: &format::TableDesc) {
+		self.validate_prim(fo, key_name, table, &[format::TYPE_PRIM_U8, format::TYPE_PRIM_F32]);
+		if let Some(len) = self.validate_ghost(fo, key_name, table, 1) {
+			if len != 3 && len != 4 {
+				self.push(Severity::Error, Rule::GhostDimensions, fo, Some(key_name), format_args!("table {} ghost dimension {} must be 3 or 4", key_name, len));
+			}
+		}
+	}
+
+	// Validate the index relationship and the index values
+	fn validate_index(&mut self, fo: format::FileOffset, walk: &Walk<'_>, key_name: &NameOrHash, table: &format::TableDesc, data: Option<&DataRef>) {
+		let type_hint = table.type_info & format::TYPE_HINT_MASK;
+		let is_index = type_hint == format::TYPE_HINT_INDEX || type_hint == format::TYPE_HINT_RANGE;
+
+		if table.index_name == 0 {
+			if is_index {
+				self.push(Severity::Error, Rule::InvalidIndex, fo, Some(key_name), format_args!("table {} requires an index_name", key_name));
+			}
+			return;
+		}
+
+		// The name may be defined by the parent dataset containing the target table
+		let datasets = walk.ancestors().map(|walk| *walk.dataset);
+		let index_name = NameOrHash(resolve::lookup_name(datasets.clone(), table.index_name));
+		if index_name.0.is_err() {
+			self.push(Severity::Error, Rule::InvalidIndex, fo, Some(key_name), format_args!("table {} index_name={} not found", key_name, index_name));
+		}
+		if table.key_name == table.index_name {
+			self.push(Severity::Error, Rule::InvalidIndex, fo, Some(key_name), format_args!("table {} index into itself", key_name));
+			return;
+		}
+		if !is_index {
+			self.push(Severity::Error, Rule::InvalidIndex, fo, Some(key_name), format_args!("table {} has index_name={} without index type hint", key_name, index_name));
+			return;
+		}
+
+		// Find the target table in this dataset or its parents
+		let Some((_, target)) = resolve::find_table(datasets, table.index_name) else {
+			self.push(Severity::Error, Rule::InvalidIndex, fo, Some(key_name), format_args!("table {} index table {} not found!", key_name, index_name));
+			return;
+		};
+
+		if target.type_info & format::TYPE_DIM_MASK != format::TYPE_DIM_1D {
+			self.push(Severity::Error, Rule::InvalidIndex, fo, Some(key_name), format_args!("table {} index table {} is not 1D", key_name, index_name));
+			return;
+		}
+		let target_len = target.data_shape[0] as u64;
+
+		// Check the index values
+		let Some(data) = data else { return };
+		let mut storage = Vec::new();
+		let data = data.decompress(&mut storage);
+		let Some(values) = indexed::index_values(&data) else { return };
+
+		if type_hint == format::TYPE_HINT_INDEX {
+			if let Some((index, &value)) = values.iter().enumerate().find(|&(_, &value)| value >= target_len) {
+				self.push(Severity::Error, Rule::IndexBounds, fo, Some(key_name), format_args!("table {} (index={}) value {} out of bounds of {} with length {}", key_name, index, value, index_name, target_len));
+			}
+		}
+		else {
+			if let Some((index, range)) = values.chunks_exact(2).enumerate().find(|&(_, range)| range[0] > range[1] || range[1] > target_len) {
+				self.push(Severity::Error, Rule::IndexBounds, fo, Some(key_name), format_args!("table {} (index={}) range {}..{} out of bounds of {} with length {}", key_name, index, range[0], range[1], index_name, target_len));
+			}
+		}
+	}
+
+	fn validate_related(&mut self, fo: format::FileOffset, dataset: &DatasetRef<'_>, names: &NamesRef<'_>, table: &format::TableDesc) {
+		let key_name = NameOrHash(names.lookup(table.key_name));
+
+		// Name must exist
+		let related_name = NameOrHash(names.lookup(table.related_name));
+		if related_name.0.is_err() {
+			self.push(Severity::Error, Rule::InvalidRelated, fo, Some(&key_name), format_args!("table {} related {} name not found!", key_name, related_name));
+		}
+
+		// Should not be related to itself
+		if table.key_name == table.related_name {
+			self.push(Severity::Warning, Rule::InvalidRelated, fo, Some(&key_name), format_args!("table {} related to itself", key_name));
+			return;
+		}
+
+		// Find the related table
+		let related_table = match dataset.find_table(table.related_name) {
+			Some(related_table) => related_table,
+			None => {
+				self.push(Severity::Error, Rule::InvalidRelated, fo, Some(&key_name), format_args!("table {} related table {} not found!", key_name, related_name));
+				return;
+			},
+		};
+
+		// Related tables must have the same shape
+		let shape = Shape::from_type_info(table.type_info, table.data_shape);
+		let related_shape = Shape::from_type_info(related_table.type_info, related_table.data_shape);
+
+		if shape != related_shape {
+			self.push(Severity::Error, Rule::RelatedShape, fo, Some(&key_name), format_args!("related tables {} ~ {} do not have the same shape, {} != {}", key_name, related_name, shape, related_shape));
+		}
+	}
+}
+
+impl walk::Visitor for Validator {
+	fn enter(&mut self, fo: format::FileOffset) -> bool {
+		// A non-zero size is not valid when the offset is zero
+		if fo.offset == 0 && fo.size != 0 {
+			self.push(Severity::Error, Rule::InvalidFileOffset, fo, None, format_args!("dataset {} has non-zero size", fo));
+			return false;
+		}
+
+		// Null datasets are not necessary an error, may happen due to incremental writing
+		if fo.is_null() {
+			self.push(Severity::Warning, Rule::NullDataset, fo, None, format_args!("null dataset {}", fo));
+			return false;
+		}
+
+		// Datasets shared between multiple parents are only validated once
+		if !self.set.insert(fo) {
+			return false;
+		}
+
+		self.report.datasets += 1;
+
+		// Datasets must be properly aligned.
+		if !fo.is_aligned() {
+			self.push(Severity::Error, Rule::UnalignedDataset, fo, None, format_args!("unaligned dataset {}", fo));
+			return false;
+		}
+
+		// Check for big datasets
+		if fo.size >= 0x100000000 {
+			self.push(Severity::Error, Rule::LargeDataset, fo, None, format_args!("large dataset {}", fo));
+			return false;
+		}
+		// Warn if dataset is larger than 1 GiB
+		if fo.size > 0x40000000 {
+			self.push(Severity::Warning, Rule::LargeDataset, fo, None, format_args!("large dataset {}", fo));
+		}
+		true
+	}
+
+	fn visit(&mut self, walk: &Walk<'_>) -> WalkControl {
+		let fo = walk.file_offset;
+		let dataset = *walk.dataset;
+
+		self.validate_header(fo, &dataset);
+
+		if dataset.tables.is_empty() {
+			self.push(Severity::Warning, Rule::EmptyDataset, fo, None, format_args!("empty dataset {}", fo));
+			return WalkControl::Continue;
+		}
+
+		// Verify the header checksum if present
+		if dataset.verify_checksum().is_err() {
+			self.push(Severity::Error, Rule::HeaderChecksum, fo, None, format_args!("dataset {} header checksum mismatch!", fo));
+		}
+
+		let names = &dataset.names;
+		self.validate_lookup(fo, names);
+
+		let mut unique_names = HashMap::new();
+
+		for (index, table) in dataset.tables.iter().enumerate() {
+			let key_name = NameOrHash(names.lookup(table.key_name));
+
+			if table.key_name == 0 {
+				self.push(Severity::Error, Rule::NullKeyName, fo, None, format_args!("table (index={}) has null key_name!", index));
+			}
+			else if let Some(other_index) = unique_names.insert(table.key_name, index) {
+				self.push(Severity::Error, Rule::DuplicateKeyName, fo, Some(&key_name), format_args!("table (index={} key_name={:#x}) with the same name already exists at index={}", index, table.key_name, other_index));
+			}
+
+			if key_name.0.is_err() {
+				self.push(Severity::Error, Rule::InvalidName, fo, Some(&key_name), format_args!("table {} invalid name!", key_name));
+			}
+
+			self.validate_type_info(fo, &key_name, table);
+
+			let data = if self.validate_memory(fo, &dataset, &key_name, table) {
+				self.validate_shape(fo, &key_name, table);
+				self.validate_data(fo, &dataset, &key_name, table)
+			}
+			else {
+				None
+			};
+
+			self.validate_index(fo, walk, &key_name, table, data.as_ref());
+
+			match table.type_info & format::TYPE_HINT_MASK {
+				format::TYPE_HINT_NONE => self.validate_hint_none(fo, &key_name, table),
+				format::TYPE_HINT_TEXT => self.validate_hint_text(fo, &key_name, table, data.as_ref()),
+				format::TYPE_HINT_JSON => self.validate_hint_json(fo, &key_name, table, data.as_ref()),
+				format::TYPE_HINT_DATASET => self.validate_hint_dataset(fo, &key_name, table),
+				format::TYPE_HINT_INDEX => self.validate_hint_index(fo, &key_name, table),
+				format::TYPE_HINT_RANGE => self.validate_hint_range(fo, &key_name, table),
+				format::TYPE_HINT_COORD => self.validate_hint_coord(fo, &key_name, table),
+				format::TYPE_HINT_HATCH => self.validate_hint_line(fo, &key_name, table),
+				format::TYPE_HINT_TRANSFORM => self.validate_hint_transform(fo, &key_name, table),
+				format::TYPE_HINT_RGB => self.validate_hint_rgb(fo, &key_name, table),
+				type_hint => {
+					// Type hints above 32 can be used freely
+					if type_hint < 32 << 8 {
+						self.push(Severity::Warning, Rule::UnknownHint, fo, Some(&key_name), format_args!("table {} has reserved type hint {}", key_name, type_hint >> 8));
+					}
+				},
+			}
+
+			// Validate the related table
+			if table.related_name != 0 {
+				self.validate_related(fo, &dataset, names, table);
+			}
+		}
+		WalkControl::Continue
+	}
+
+	fn error(&mut self, fo: format::FileOffset, err: Error) -> Result<(), Error> {
+		match err {
+			Error::CyclicDataset { .. } => {
+				self.push(Severity::Error, Rule::CyclicDataset, fo, None, format_args!("cyclic dataset {}", fo));
+			},
+			// Table data of dataset tables is validated with the other tables
+			Error::OutOfBounds { field: Field::TableData, .. } | Error::InvalidTableData { .. } => (),
+			err => {
+				self.push(Severity::Error, Rule::InvalidDataset, fo, None, format_args!("dataset {}", err));
+			},
+		}
+		Ok(())
+	}
+}
+
+// Reserved bits of the type info
+const TYPE_RESERVED_MASK: u16 = 0xc040;
+
+static INDEX_PRIMS: &[u16] = &[format::TYPE_PRIM_U8, format::TYPE_PRIM_U16, format::TYPE_PRIM_U32, format::TYPE_PRIM_U64];
+
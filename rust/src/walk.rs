@@ -1,3 +1,9 @@
+/*!
+Walking the dataset tree.
+*/
+
+use core::fmt;
+use crate::*;
 
 /// Helper for tracking parents when recursively walking datasets.
 #[derive(Debug)]
@@ -5,3 +11,194 @@ pub struct WalkRef<'a, T> {
 	pub parent: Option<&'a WalkRef<'a, T>>,
 	pub instance: &'a T,
 }
+
+/// Controls the walk after visiting a dataset.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum WalkControl {
+	/// Continue with the children of the dataset.
+	Continue,
+	/// Skip the children of the dataset.
+	Prune,
+	/// Stop walking.
+	Stop,
+}
+
+/// Dataset visited by [`FileIO::walk`].
+#[derive(Copy, Clone)]
+pub struct Walk<'a> {
+	/// File offset of the dataset.
+	pub file_offset: format::FileOffset,
+	/// The dataset.
+	pub dataset: &'a DatasetRef<'a>,
+	/// Key name of the parent's table and the index of the file offset referring to this dataset.
+	///
+	/// `None` for the root of the walk.
+	pub table: Option<(u32, u32)>,
+	/// Number of parents.
+	pub depth: u32,
+	/// The parent dataset.
+	pub parent: Option<&'a Walk<'a>>,
+}
+
+impl<'a> Walk<'a> {
+	/// Iterator over the dataset and its parents, starting with this dataset.
+	pub fn ancestors(&self) -> impl Iterator<Item = &Walk<'a>> + Clone + '_ {
+		let mut link = Some(self);
+		core::iter::from_fn(move || {
+			let walk = link?;
+			link = walk.parent;
+			Some(walk)
+		})
+	}
+
+	/// Looks up the name of a hash in the dataset and its parents.
+	#[inline]
+	pub fn lookup(&self, hash: u32) -> Result<&'a str, u32> {
+		resolve::lookup_name(self.ancestors().map(|walk| *walk.dataset), hash)
+	}
+
+	/// Finds a table by its key name in the dataset and its parents.
+	#[inline]
+	pub fn find_table(&self, key_name: u32) -> Option<(DatasetRef<'a>, &'a format::TableDesc)> {
+		resolve::find_table(self.ancestors().map(|walk| *walk.dataset), key_name)
+	}
+
+	/// Dereferences an index or range table of the dataset against its target table in the dataset or its parents.
+	#[inline]
+	pub fn get_indexed<T: Element>(&self, name: &str) -> Result<Indexed<'a, T>, Error> {
+		Indexed::new(self.ancestors().map(|walk| *walk.dataset), name).map_err(|err| err.at(self.file_offset))
+	}
+
+	/// Returns the path from the root of the walk to this dataset, eg. `Children[2].Meshes[0]`.
+	///
+	/// Names missing from the names lookup are printed as their hash.
+	#[inline]
+	pub fn path(&self) -> PrintPath<'_> {
+		PrintPath(self)
+	}
+}
+
+/// Prints the path of a walked dataset.
+pub struct PrintPath<'a>(&'a Walk<'a>);
+
+impl fmt::Display for PrintPath<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let walk = self.0;
+		if let (Some(parent), Some((key_name, index))) = (walk.parent, walk.table) {
+			if parent.parent.is_some() {
+				write!(f, "{}.", parent.path())?;
+			}
+			let name = NameOrHash(parent.lookup(key_name));
+			// Children of 2D dataset tables are indexed by row and column
+			let table = parent.dataset.find_table(key_name).filter(|table| table.type_info & format::TYPE_DIM_MASK == format::TYPE_DIM_2D);
+			match table.map(|table| table.data_shape[1] & 0xffffff) {
+				Some(cols) if cols != 0 => write!(f, "{}[{},{}]", name, index / cols, index % cols)?,
+				_ => write!(f, "{}[{}]", name, index)?,
+			}
+		}
+		Ok(())
+	}
+}
+
+// File offsets to other datasets, compressed tables are invalid table data
+#[cfg(feature = "std")]
+pub(crate) fn is_dataset_table(table: &format::TableDesc) -> bool {
+	table.type_info & (format::TYPE_HINT_MASK | format::TYPE_PRIM_MASK) == format::TYPE_HINT_DATASET | format::TYPE_PRIM_U64
+}
+
+// Hooks of the recursive walk
+#[cfg(feature = "std")]
+pub(crate) trait Visitor {
+	// Called before reading a dataset, returns false to skip it
+	fn enter(&mut self, fo: format::FileOffset) -> bool {
+		// Null file offsets are placeholders for missing datasets
+		!fo.is_null()
+	}
+	fn visit(&mut self, walk: &Walk<'_>) -> WalkControl;
+	// Called after walking the children of a dataset
+	fn leave(&mut self, _walk: &Walk<'_>) -> Result<(), Error> {
+		Ok(())
+	}
+	// Returns the error to stop walking, or skips the dataset or table which failed
+	fn error(&mut self, _fo: format::FileOffset, err: Error) -> Result<(), Error> {
+		Err(err)
+	}
+}
+
+#[cfg(feature = "std")]
+impl<F: FnMut(&Walk<'_>) -> WalkControl> Visitor for F {
+	#[inline]
+	fn visit(&mut self, walk: &Walk<'_>) -> WalkControl {
+		self(walk)
+	}
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn walk<B: Backend>(file: &mut FileIO<B>, root: format::FileOffset, max_depth: u32, visitor: &mut dyn Visitor) -> Result<(), Error> {
+	walk_rec(file, root, None, None, max_depth, visitor)?;
+	Ok(())
+}
+
+#[cfg(feature = "std")]
+fn walk_rec<B: Backend>(file: &mut FileIO<B>, fo: format::FileOffset, table: Option<(u32, u32)>, parent: Option<&Walk<'_>>, max_depth: u32, visitor: &mut dyn Visitor) -> Result<WalkControl, Error> {
+	// Detect cyclic datasets
+	let mut link = parent;
+	while let Some(walk) = link {
+		if walk.file_offset == fo {
+			visitor.error(fo, Error::CyclicDataset { file_offset: fo })?;
+			return Ok(WalkControl::Continue);
+		}
+		link = walk.parent;
+	}
+
+	if !visitor.enter(fo) {
+		return Ok(WalkControl::Continue);
+	}
+
+	let dataset = match file.read_dataset(fo) {
+		Ok(dataset) => dataset,
+		Err(err) => {
+			visitor.error(fo, err)?;
+			return Ok(WalkControl::Continue);
+		},
+	};
+	let dataset = dataset.as_ref();
+	let walk = Walk {
+		file_offset: fo,
+		dataset: &dataset,
+		table,
+		depth: parent.map_or(0, |parent| parent.depth + 1),
+		parent,
+	};
+	match visitor.visit(&walk) {
+		WalkControl::Continue if walk.depth < max_depth => (),
+		WalkControl::Stop => return Ok(WalkControl::Stop),
+		_ => return Ok(WalkControl::Continue),
+	}
+
+	for table in dataset.tables {
+		if !is_dataset_table(table) {
+			continue;
+		}
+		let Some(data) = dataset.get_data_ref(table) else {
+			visitor.error(fo, Error::OutOfBounds {
+				field: Field::TableData,
+				file_offset: fo,
+				end: table.mem_end as u64 * 8,
+				size: dataset.storage.len() as u64 * 8,
+			})?;
+			continue;
+		};
+		let Some(children) = data.as_slice::<format::FileOffset>() else {
+			visitor.error(fo, Error::InvalidTableData { file_offset: fo, key_name: table.key_name })?;
+			continue;
+		};
+		for (index, &child) in children.iter().enumerate() {
+			if walk_rec(file, child, Some((table.key_name, index as u32)), Some(&walk), max_depth, visitor)? == WalkControl::Stop {
+				return Ok(WalkControl::Stop);
+			}
+		}
+	}
+	visitor.leave(&walk)?;
+	Ok(WalkControl::Continue)
+}
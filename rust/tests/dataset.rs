@@ -0,0 +1,394 @@
+use udf::{format, AsDataRef};
+
+static FLOATS: [f32; 4] = [0.0, 1.0, 2.5, -4.0];
+
+fn floats(ds: &udf::Dataset, name: &str) -> Vec<f32> {
+	let ds = ds.as_ref();
+	let table = ds.find_table(udf::hash(name)).unwrap();
+	ds.get_data_ref(table).unwrap().as_slice::<f32>().unwrap().to_vec()
+}
+
+#[test]
+fn unique_key_names() {
+	let mut ds = udf::Dataset::new();
+	assert!(ds.add_table(udf::TableRef { key_name: udf::hash!("A"), data: FLOATS.as_data_ref(), ..Default::default() }));
+	assert!(!ds.add_table(udf::TableRef { key_name: udf::hash!("A"), data: 1.0f32.as_data_ref(), ..Default::default() }));
+	assert_eq!(ds.len(), 1);
+	assert_eq!(ds.storage.len(), 2);
+
+	// Inserting by name replaces the existing table
+	ds.insert("A", [5.0f32].as_data_ref());
+	assert_eq!(ds.len(), 1);
+	assert_eq!(ds.storage.len(), 1);
+	assert_eq!(floats(&ds, "A"), [5.0]);
+}
+
+#[test]
+#[should_panic(expected = "collides")]
+fn colliding_names() {
+	// A different name registered with the hash of the inserted name
+	let mut ds = udf::Dataset::new();
+	ds.names.add("B", udf::hash!("A"));
+	ds.insert("A", FLOATS.as_data_ref());
+}
+
+#[test]
+fn edit_tables() {
+	let indices = [format::IndexU32(0), format::IndexU32(3)];
+	let mut ds = udf::Dataset::new();
+	ds.insert("A", FLOATS.as_data_ref());
+	ds.insert("B", FLOATS.as_data_ref());
+	ds.insert("C", FLOATS[..2].as_data_ref());
+	ds.insert("Indices", indices.as_data_ref()).index("A");
+	ds.finalize();
+
+	// Edit a copy of the parsed dataset
+	let mut bytes = Vec::new();
+	ds.as_ref().write(&mut bytes).unwrap();
+	let storage: Vec<u64> = bytes.chunks(8).map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap())).collect();
+	let mut ds = udf::DatasetRef::parse(&storage).unwrap().to_owned();
+
+	assert!(ds.remove_table(udf::hash!("B")));
+	assert!(!ds.remove_table(udf::hash!("B")));
+	assert_eq!(ds.storage.len(), 2 + 1 + 1);
+	assert_eq!(floats(&ds, "C"), FLOATS[..2]);
+
+	let data = [1.0f32; 6];
+	assert!(ds.replace_table(udf::TableRef { key_name: udf::hash!("A"), data: data.as_data_ref(), ..Default::default() }));
+	assert!(!ds.replace_table(udf::TableRef { key_name: udf::hash!("B"), data: data.as_data_ref(), ..Default::default() }));
+	assert_eq!(ds.storage.len(), 1 + 1 + 3);
+	assert_eq!(floats(&ds, "A"), data);
+	assert_eq!(floats(&ds, "C"), FLOATS[..2]);
+
+	// References to the renamed table are updated
+	assert!(!ds.rename_table(udf::hash!("A"), udf::hash!("C")));
+	assert!(ds.rename_table(udf::hash!("A"), udf::hash!("Floats")));
+	ds.names.insert("Floats");
+	assert_eq!(ds.as_ref().find_table(udf::hash!("Indices")).unwrap().index_name, udf::hash!("Floats"));
+	assert_eq!(floats(&ds, "Floats"), data);
+
+	let mut file = udf::FileIO::create_with(std::io::Cursor::new(Vec::new()), [0; 4]).unwrap();
+	let fo = file.add_dataset(&ds.finalize()).unwrap();
+	file.set_root(fo);
+	let report = udf::validate(&mut file);
+	assert_eq!(report.errors(), 0, "{:#?}", report.diagnostics);
+}
+
+#[test]
+fn sorted_tables() {
+	let mut ds = udf::Dataset::new();
+	for name in ["Points", "Contours", "Attributes", "Metadata", "PartIndex", "Height"] {
+		ds.insert(name, FLOATS.as_data_ref());
+	}
+	assert!(!ds.as_ref().is_sorted());
+	ds.finalize();
+	assert!(ds.as_ref().is_sorted());
+	assert!(ds.descs.windows(2).all(|pair| pair[0].key_name < pair[1].key_name));
+	for name in ["Points", "Contours", "Attributes", "Metadata", "PartIndex", "Height"] {
+		assert_eq!(floats(&ds, name), FLOATS);
+	}
+	assert!(ds.as_ref().find_table(udf::hash!("Missing")).is_none());
+
+	// Adding tables clears the sorted flag until finalized again
+	ds.insert("Zzz", FLOATS.as_data_ref());
+	assert!(!ds.as_ref().is_sorted());
+	assert_eq!(floats(&ds, "Zzz"), FLOATS);
+	ds.finalize();
+
+	let mut bytes = Vec::new();
+	ds.as_ref().write(&mut bytes).unwrap();
+	let storage: Vec<u64> = bytes.chunks(8).map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap())).collect();
+	assert!(udf::DatasetRef::parse(&storage).unwrap().is_sorted());
+
+	// Older files without the flag are scanned linearly
+	let mut unflagged = storage.clone();
+	unflagged[2] &= !(1 << 32);
+	let ds = udf::DatasetRef::parse(&unflagged).unwrap();
+	assert!(!ds.is_sorted());
+	assert!(ds.find_table(udf::hash!("Zzz")).is_some());
+
+	// Flagged sorted but not sorted is rejected in strict mode
+	let mut unsorted = storage.clone();
+	let (a, b) = unsorted[3..3 + 12].split_at_mut(6);
+	a.swap_with_slice(b);
+	let err = udf::DatasetRef::parse(&unsorted).err().unwrap();
+	assert_eq!(err.field(), Some(udf::Field::Flags));
+	assert!(udf::DatasetRef::parse_with(&unsorted, udf::ParseMode::Lenient).is_ok());
+
+	// Undefined flags are rejected in strict mode
+	let mut flags = storage.clone();
+	flags[2] |= 0x8000 << 32;
+	assert_eq!(udf::DatasetRef::parse(&flags).err().unwrap().field(), Some(udf::Field::Flags));
+}
+
+#[test]
+fn typed_accessors() {
+	let points = [format::Coord2F32 { x: 1.0, y: 2.0 }, format::Coord2F32 { x: 3.0, y: 4.0 }];
+	let children = [format::FileOffset { offset: 0x40, size: 0x20 }];
+	let indices: Vec<u32> = (0..100).map(|i| i % 7).collect();
+	let mut stream = Vec::new();
+	udf::compress::SimpleU32.compress(&mut stream, &indices);
+	let utf16 = "Hé".encode_utf16().collect::<Vec<u16>>();
+
+	let mut ds = udf::Dataset::new();
+	ds.insert("Points", points.as_data_ref());
+	ds.insert("Height", 1.5f32.as_data_ref());
+	ds.insert("Children", children.as_data_ref());
+	ds.insert("Text", "Hello".as_data_ref());
+	ds.insert("Utf16", udf::DataRef {
+		bytes: dataview::bytes(utf16.as_slice()),
+		type_info: format::TYPE_HINT_TEXT | format::TYPE_DIM_SCALAR | format::TYPE_PRIM_U16,
+		compress_info: format::COMPRESS_NONE,
+		shape: udf::Shape::D1(utf16.len() as u32),
+	});
+	ds.insert("Signed", udf::DataRef {
+		type_info: format::TYPE_HINT_TEXT | format::TYPE_DIM_SCALAR | format::TYPE_PRIM_I8,
+		.."Hé".as_data_ref()
+	});
+	ds.insert("Compressed", udf::DataRef {
+		bytes: &stream,
+		type_info: format::TYPE_HINT_NONE | format::TYPE_DIM_1D | format::TYPE_PRIM_U32,
+		compress_info: format::COMPRESS_SIMPLE_U32,
+		shape: udf::Shape::D1(indices.len() as u32),
+	});
+	ds.finalize();
+	let ds = ds.as_ref();
+
+	let values = ds.get::<format::Coord2F32>("Points").unwrap();
+	assert!(matches!(values, std::borrow::Cow::Borrowed(_)));
+	assert_eq!((values[0].x, values[1].y), (1.0, 4.0));
+	assert_eq!(ds.get_scalar::<f32>("Height").unwrap(), 1.5);
+	assert_eq!(&*ds.get::<format::FileOffset>("Children").unwrap(), &children);
+	assert_eq!(ds.get_text("Text").unwrap(), "Hello");
+	assert_eq!(ds.get_text("Utf16").unwrap(), "Hé");
+	assert_eq!(ds.get_text("Signed").unwrap(), "Hé");
+
+	// Compressed data is decompressed transparently
+	let values = ds.get::<u32>("Compressed").unwrap();
+	assert!(matches!(values, std::borrow::Cow::Owned(_)));
+	assert_eq!(&*values, &indices[..]);
+
+	// Mismatched types are rejected
+	assert!(matches!(ds.get::<f64>("Points"), Err(udf::Error::TypeMismatch { .. })));
+	assert!(matches!(ds.get::<format::Coord3F32>("Points"), Err(udf::Error::TypeMismatch { .. })));
+	assert!(matches!(ds.get::<format::RangeU32>("Children"), Err(udf::Error::TypeMismatch { .. })));
+	assert!(matches!(ds.get_scalar::<format::Coord2F32>("Points"), Err(udf::Error::TypeMismatch { .. })));
+	assert!(matches!(ds.get_text("Points"), Err(udf::Error::TypeMismatch { .. })));
+	let err = ds.get::<f32>("Missing").err().unwrap();
+	assert!(matches!(err, udf::Error::TableNotFound { key_name, .. } if key_name == udf::hash!("Missing")));
+	assert_eq!(err.to_string(), format!("table {:#010x} not found", udf::hash!("Missing")));
+}
+
+#[test]
+fn from_data_ref() {
+	use udf::FromDataRef;
+
+	let grid = [[1u16, 2, 3], [4, 5, 6]];
+	let cube = [[[1u8, 2], [3, 4]], [[5, 6], [7, 8]]];
+	let mut names = Vec::new();
+	let names = udf::build_string_array_utf8(["one", "three", ""], &mut names);
+
+	let mut ds = udf::Dataset::new();
+	ds.insert("Grid", grid.as_data_ref());
+	ds.insert("Cube", cube.as_data_ref());
+	ds.insert("Names", names);
+	ds.insert("Signed", udf::DataRef { type_info: names.type_info & !format::TYPE_PRIM_MASK | format::TYPE_PRIM_I8, ..names });
+	ds.insert("Text", "Hello".as_data_ref());
+	ds.insert("Scale", 2.5f64.as_data_ref());
+	ds.insert("Range", [format::RangeU32 { start: 1, end: 4 }].as_data_ref());
+	ds.finalize();
+	let ds = ds.as_ref();
+
+	assert_eq!(ds.read::<&[[u16; 3]]>("Grid").unwrap(), &grid);
+	assert_eq!(ds.read::<Vec<[u16; 3]>>("Grid").unwrap(), grid);
+	assert_eq!(ds.read::<Vec<u16>>("Grid").unwrap(), [1, 2, 3, 4, 5, 6]);
+	assert_eq!(ds.read::<[u16; 6]>("Grid").unwrap(), [1, 2, 3, 4, 5, 6]);
+	assert_eq!(ds.read::<Vec<[[u8; 2]; 2]>>("Cube").unwrap(), cube);
+	assert_eq!(ds.read::<Vec<String>>("Names").unwrap(), ["one", "three", ""]);
+	assert_eq!(ds.read::<Vec<String>>("Signed").unwrap(), ["one", "three", ""]);
+	assert_eq!(ds.read::<&str>("Text").unwrap(), "Hello");
+	assert_eq!(ds.read::<String>("Text").unwrap(), "Hello");
+	assert_eq!(ds.read::<f64>("Scale").unwrap(), 2.5);
+	let range = ds.read::<&[format::RangeU32]>("Range").unwrap();
+	assert_eq!((range[0].start, range[0].end), (1, 4));
+
+	// The data ref can be converted directly
+	let table = ds.find_table(udf::hash!("Grid")).unwrap();
+	let data = ds.get_data_ref(table).unwrap();
+	assert_eq!(<Vec<[u16; 3]>>::from_data_ref(&data).unwrap(), grid);
+
+	// Mismatched type info or shape is rejected with the key name
+	let err = ds.read::<Vec<[u16; 2]>>("Grid").err().unwrap();
+	assert!(matches!(err, udf::Error::TypeMismatch { key_name, .. } if key_name == udf::hash!("Grid")));
+	assert!(matches!(ds.read::<Vec<[u32; 3]>>("Grid"), Err(udf::Error::TypeMismatch { .. })));
+	assert!(matches!(ds.read::<[u16; 4]>("Grid"), Err(udf::Error::TypeMismatch { .. })));
+	assert!(matches!(ds.read::<Vec<[[u8; 2]; 2]>>("Grid"), Err(udf::Error::TypeMismatch { .. })));
+	assert!(matches!(ds.read::<u16>("Grid"), Err(udf::Error::TypeMismatch { .. })));
+	assert!(matches!(ds.read::<Vec<String>>("Text"), Err(udf::Error::TypeMismatch { .. })));
+	assert!(matches!(ds.read::<String>("Names"), Err(udf::Error::TypeMismatch { .. })));
+	assert!(matches!(ds.read::<Vec<[u32; 2]>>("Range"), Err(udf::Error::TypeMismatch { .. })));
+}
+
+#[test]
+fn read_hint() {
+	let mut ds = udf::Dataset::new();
+	ds.insert("Range", [format::RangeU32 { start: 1, end: 4 }].as_data_ref());
+	ds.insert("Grid", [[1u32, 4]].as_data_ref());
+	ds.finalize();
+	let ds = ds.as_ref();
+
+	// Ghost dimensions of the type hint are read as arrays
+	assert_eq!(ds.read_hint::<Vec<[u32; 2]>>("Range", format::TYPE_HINT_RANGE).unwrap(), [[1, 4]]);
+	assert_eq!(ds.read_hint::<&[u32]>("Range", format::TYPE_HINT_RANGE).unwrap(), [1, 4]);
+	assert_eq!(ds.read_hint::<Vec<[u32; 2]>>("Grid", format::TYPE_HINT_NONE).unwrap(), [[1, 4]]);
+	assert!(matches!(ds.read_hint::<Vec<[u32; 3]>>("Range", format::TYPE_HINT_RANGE), Err(udf::Error::TypeMismatch { .. })));
+
+	// The table must have the type hint
+	let err = ds.read_hint::<Vec<[u32; 2]>>("Grid", format::TYPE_HINT_RANGE).err().unwrap();
+	assert!(matches!(err, udf::Error::TypeMismatch { key_name, .. } if key_name == udf::hash!("Grid")));
+	assert!(matches!(ds.read_hint::<Vec<[u32; 2]>>("Range", format::TYPE_HINT_INDEX), Err(udf::Error::TypeMismatch { .. })));
+}
+
+#[test]
+fn custom_key_names() {
+	// The key names are found in the names lookup, they are not required to be the hash of the name
+	let points = [format::Coord2F32 { x: 1.0, y: 2.0 }, format::Coord2F32 { x: 3.0, y: 4.0 }];
+	let contours = [format::RangeU32 { start: 0, end: 1 }, format::RangeU32 { start: 1, end: 2 }];
+	let mut ds = udf::Dataset::new();
+	ds.add_table(udf::TableRef { key_name: 0x100, data: points.as_data_ref(), ..Default::default() });
+	ds.add_table(udf::TableRef { key_name: 0x200, data: contours.as_data_ref(), index_name: 0x100, ..Default::default() });
+	ds.add_table(udf::TableRef { key_name: 0x300, data: [0.5f32, 1.5].as_data_ref(), related_name: 0x200, ..Default::default() });
+	ds.add_table(udf::TableRef { key_name: 0x400, data: "Hello".as_data_ref(), ..Default::default() });
+	ds.names.add("Points", 0x100);
+	ds.names.add("Contours", 0x200);
+	ds.names.add("Heights", 0x300);
+	ds.names.add("Name", 0x400);
+	ds.finalize();
+	let ds = ds.as_ref();
+
+	assert_eq!(ds.names.key_name("Points"), 0x100);
+	assert_eq!(ds.names.key_name("Missing"), udf::hash!("Missing"));
+	assert_eq!(ds.get::<format::Coord2F32>("Points").unwrap()[1].x, 3.0);
+	assert_eq!(ds.read_hint::<Vec<[f32; 2]>>("Points", format::TYPE_HINT_COORD).unwrap(), [[1.0, 2.0], [3.0, 4.0]]);
+	assert_eq!(ds.read::<&str>("Name").unwrap(), "Hello");
+	assert_eq!(ds.get_text("Name").unwrap(), "Hello");
+	assert_eq!(ds.get_indexed::<format::Coord2F32>("Contours").unwrap().get(1).unwrap()[0].y, 4.0);
+	let related = ds.related("Heights").unwrap();
+	let records: Vec<(format::RangeU32, f32)> = related.records(&["Contours", "Heights"]).unwrap().collect();
+	assert_eq!(records[1].1, 1.5);
+	assert!(matches!(ds.get::<f32>("Missing"), Err(udf::Error::TableNotFound { key_name, .. }) if key_name == udf::hash!("Missing")));
+}
+
+#[test]
+fn indexed_tables() {
+	let points: Vec<format::Coord2F32> = (0..5).map(|i| format::Coord2F32 { x: i as f32, y: 0.0 }).collect();
+	let contours = [format::RangeU32 { start: 0, end: 3 }, format::RangeU32 { start: 3, end: 3 }, format::RangeU32 { start: 3, end: 5 }];
+	let indices = [format::IndexU32(4), format::IndexU32(0), format::IndexU32(4)];
+	let invalid = [format::RangeU32 { start: 2, end: 6 }];
+
+	let mut parent = udf::Dataset::new();
+	parent.insert("Points", points.as_data_ref());
+	parent.finalize();
+	let mut child = udf::Dataset::new();
+	child.insert("Contours", contours.as_data_ref()).index("Points");
+	child.insert("Indices", indices.as_data_ref()).index("Points");
+	child.insert("Invalid", invalid.as_data_ref()).index("Points");
+	child.insert("Floats", FLOATS.as_data_ref()).index("Points");
+	child.finalize();
+
+	// The target table is found in the parent dataset
+	let datasets = [child.as_ref(), parent.as_ref()];
+	let contours = udf::Indexed::<format::Coord2F32>::new(datasets, "Contours").unwrap();
+	assert!(contours.is_range());
+	assert_eq!(contours.len(), 3);
+	let lens: Vec<usize> = contours.iter().map(|contour| contour.len()).collect();
+	assert_eq!(lens, [3, 0, 2]);
+	assert_eq!(contours.get(2).unwrap()[1].x, 4.0);
+	assert!(contours.get(3).is_none());
+	assert!(contours.get(usize::MAX).is_none());
+	assert!(contours.get(usize::MAX / 2).is_none());
+	assert_eq!(contours.gather().len(), 5);
+
+	let indices = udf::Indexed::<format::Coord2F32>::new(datasets, "Indices").unwrap();
+	assert!(!indices.is_range());
+	let xs: Vec<f32> = indices.gather().iter().map(|point| point.x).collect();
+	assert_eq!(xs, [4.0, 0.0, 4.0]);
+
+	// Values are checked against the bounds of the target
+	let err = udf::Indexed::<format::Coord2F32>::new(datasets, "Invalid").err().unwrap();
+	assert!(matches!(err, udf::Error::Custom { key_name, .. } if key_name == udf::hash!("Invalid")));
+	assert!(matches!(udf::Indexed::<format::Coord2F32>::new(datasets, "Floats"), Err(udf::Error::TypeMismatch { .. })));
+	assert!(matches!(udf::Indexed::<format::Coord3F32>::new(datasets, "Contours"), Err(udf::Error::TypeMismatch { .. })));
+	assert!(matches!(udf::Indexed::<f32>::new(datasets, "Contours"), Err(udf::Error::TypeMismatch { .. })));
+
+	// Without the parent the target table is not found
+	let err = child.as_ref().get_indexed::<format::Coord2F32>("Contours").err().unwrap();
+	assert!(matches!(err, udf::Error::TableNotFound { key_name, .. } if key_name == udf::hash!("Points")));
+}
+
+#[test]
+fn related_tables() {
+	let contours = [format::RangeU32 { start: 0, end: 2 }, format::RangeU32 { start: 2, end: 3 }, format::RangeU32 { start: 3, end: 7 }];
+	let heights = [0.5f32, 1.0, 1.5];
+	let colors = [[255u8, 0, 0], [0, 255, 0], [0, 0, 255]];
+	let attributes: Vec<u32> = vec![7, 8, 9];
+	let mut stream = Vec::new();
+	udf::compress::SimpleU32.compress(&mut stream, &attributes);
+
+	let mut ds = udf::Dataset::new();
+	ds.insert("Colors", colors.as_data_ref().with_type_hint(format::TYPE_HINT_RGB)).related("Heights");
+	ds.insert("Contours", contours.as_data_ref());
+	ds.insert("Heights", heights.as_data_ref()).related("Contours");
+	ds.insert("Attributes", udf::DataRef {
+		bytes: &stream,
+		type_info: format::TYPE_HINT_NONE | format::TYPE_DIM_1D | format::TYPE_PRIM_U32,
+		compress_info: format::COMPRESS_SIMPLE_U32,
+		shape: udf::Shape::D1(attributes.len() as u32),
+	}).related("Contours");
+	ds.insert("Short", FLOATS[..2].as_data_ref()).related("Contours");
+	ds.insert("Cycle", FLOATS.as_data_ref()).related("Loop");
+	ds.insert("Loop", FLOATS.as_data_ref()).related("Cycle");
+	ds.insert("Other", FLOATS.as_data_ref());
+	ds.finalize();
+	let ds = ds.as_ref();
+
+	// Related tables must have the same shape
+	let err = ds.related("Colors").err().unwrap();
+	assert!(matches!(err, udf::Error::Custom { key_name, .. } if key_name == udf::hash!("Short")));
+	let mut ds = ds.to_owned();
+	assert!(ds.remove_table(udf::hash!("Short")));
+	ds.finalize();
+	let ds = ds.as_ref();
+
+	// The chain is collected starting from any member
+	let names = |related: &udf::Related| related.tables().map(|table| ds.names.lookup(table.key_name).unwrap()).collect::<Vec<_>>();
+	for name in ["Contours", "Heights", "Colors", "Attributes"] {
+		let mut names = names(&ds.related(name).unwrap());
+		assert_eq!(names[0], "Contours");
+		assert_eq!(names.last(), Some(&"Colors"));
+		names.sort();
+		assert_eq!(names, ["Attributes", "Colors", "Contours", "Heights"]);
+	}
+	assert_eq!(names(&ds.related("Other").unwrap()), ["Other"]);
+	assert!(ds.related("Cycle").is_err());
+	assert!(matches!(ds.related("Missing"), Err(udf::Error::TableNotFound { .. })));
+
+	// Rows as tuples
+	let related = ds.related("Heights").unwrap();
+	assert_eq!(related.len(), 3);
+	let records: Vec<(format::RangeU32, f32, u32)> = related.records(&["Contours", "Heights", "Attributes"]).unwrap().collect();
+	assert_eq!(records.iter().map(|record| (record.0.end, record.1, record.2)).collect::<Vec<_>>(), [(2, 0.5, 7), (3, 1.0, 8), (7, 1.5, 9)]);
+	assert!(matches!(related.records::<(f32,)>(&["Colors"]), Err(udf::Error::TypeMismatch { .. })));
+	assert!(matches!(related.records::<(f32,)>(&["Other"]), Err(udf::Error::TableNotFound { .. })));
+	assert!(related.records::<(f32, f32)>(&["Heights"]).is_err());
+
+	// Rows as dynamic rows
+	let row = related.row(2).unwrap();
+	assert_eq!(row.len(), 4);
+	assert_eq!(row.find(udf::hash!("Colors")).unwrap().as_slice::<u8>(), Some(&[0, 0, 255][..]));
+	assert_eq!(row.find(udf::hash!("Attributes")).unwrap().as_slice::<u32>(), Some(&[9][..]));
+	assert_eq!(row.get(0).unwrap().shape, udf::Shape::D2(1, 2));
+	assert_eq!(row.get(3).unwrap().shape, udf::Shape::D2(1, 3));
+	assert!(related.row(3).is_none());
+	assert_eq!(related.rows().map(|row| row.iter().count()).sum::<usize>(), 12);
+}
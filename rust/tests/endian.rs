@@ -0,0 +1,89 @@
+use udf::{endian, format, AsDataRef};
+
+fn sample() -> Vec<u64> {
+	let indices: Vec<u32> = (0..100).map(|i| i % 7).collect();
+	let mut stream = Vec::new();
+	udf::compress::SimpleU32.compress(&mut stream, &indices);
+	let utf16 = "Hé".encode_utf16().collect::<Vec<u16>>();
+
+	let mut ds = udf::Dataset::new();
+	ds.checksums = true;
+	ds.insert("Doubles", [1.0f64, -2.5, 1e100].as_data_ref());
+	ds.insert("Points", [[1.0f32, 2.0], [3.0, 4.0]].as_data_ref());
+	ds.insert("Text", udf::DataRef {
+		bytes: dataview::bytes(utf16.as_slice()),
+		type_info: format::TYPE_HINT_TEXT | format::TYPE_DIM_SCALAR | format::TYPE_PRIM_U16,
+		compress_info: format::COMPRESS_NONE,
+		shape: udf::Shape::D1(utf16.len() as u32),
+	});
+	ds.insert("Compressed", udf::DataRef {
+		bytes: &stream,
+		type_info: format::TYPE_HINT_NONE | format::TYPE_DIM_1D | format::TYPE_PRIM_U32,
+		compress_info: format::COMPRESS_SIMPLE_U32,
+		shape: udf::Shape::D1(indices.len() as u32),
+	});
+	ds.finalize();
+
+	let mut bytes = Vec::new();
+	ds.as_ref().write(&mut bytes).unwrap();
+	bytes.chunks(8).map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap())).collect()
+}
+
+#[test]
+fn swap_dataset() {
+	let storage = sample();
+	let mut swapped = storage.clone();
+	endian::swap_dataset(&mut swapped).unwrap();
+	assert_ne!(swapped, storage);
+	assert!(udf::DatasetRef::parse(&swapped).is_err());
+
+	// The descriptors and table data are swapped field by field
+	let ds = udf::DatasetRef::parse(&storage).unwrap();
+	let view = dataview::DataView::from(swapped.as_slice());
+	let header = view.read::<format::DatasetHeader>(0);
+	assert_eq!(header.size, ds.header.size.swap_bytes());
+	assert_eq!(header.checksum, ds.header.checksum.swap_bytes());
+	for (i, table) in ds.tables.iter().enumerate() {
+		let desc = view.read::<format::TableDesc>(size_of::<format::DatasetHeader>() + i * size_of::<format::TableDesc>());
+		assert_eq!(desc.key_name, table.key_name.swap_bytes());
+		assert_eq!(desc.data_shape[0], table.data_shape[0].swap_bytes());
+
+		let data = ds.get_data_ref(table).unwrap();
+		let start = ds.header.size as usize + table.mem_start as usize * 8;
+		let bytes = &dataview::bytes(swapped.as_slice())[start..start + data.bytes.len()];
+		let mut copy = Vec::new();
+		assert_eq!(data.swap_bytes(&mut copy).bytes, bytes);
+	}
+
+	// Swapping back restores the dataset
+	endian::swap_dataset(&mut swapped).unwrap();
+	assert_eq!(swapped, storage);
+	assert!(udf::DatasetRef::parse(&swapped).unwrap().verify_checksums().is_ok());
+
+	// Invalid datasets are left untouched
+	let mut invalid = storage.clone();
+	invalid[0] ^= 1;
+	assert_eq!(endian::swap_dataset(&mut invalid).err().unwrap().field(), Some(udf::Field::Check));
+	let mut truncated = storage[..storage.len() - 1].to_vec();
+	assert!(matches!(endian::swap_dataset(&mut truncated), Err(udf::Error::OutOfBounds { field: udf::Field::TableData, .. })));
+	assert_eq!(truncated, storage[..storage.len() - 1]);
+}
+
+#[test]
+fn swap_data() {
+	let values = [0x0102u16, 0x0304];
+	let data = values.as_data_ref();
+	let mut storage = Vec::new();
+	let swapped = data.swap_bytes(&mut storage);
+	assert_eq!(swapped.as_slice::<u16>(), Some(&[0x0201u16, 0x0403][..]));
+	assert_eq!(swapped.shape, data.shape);
+
+	let mut bytes = [1u8, 2, 3, 4, 5, 6, 7, 8];
+	endian::swap_data(format::TYPE_PRIM_U32, &mut bytes);
+	assert_eq!(bytes, [4, 3, 2, 1, 8, 7, 6, 5]);
+	endian::swap_data(format::TYPE_PRIM_U8, &mut bytes);
+	assert_eq!(bytes, [4, 3, 2, 1, 8, 7, 6, 5]);
+
+	let header = format::FileOffset { offset: 0x10, size: 0x20 };
+	assert_eq!(endian::from_le(header), header);
+}
@@ -0,0 +1,670 @@
+use udf::AsDataRef;
+
+static FLOATS: [f32; 4] = [0.0, 1.0, 2.5, -4.0];
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+	std::env::temp_dir().join(format!("udf-test-{}-{}", std::process::id(), name))
+}
+
+#[test]
+fn mapped_file() {
+	let path = temp_path("mapped.udf");
+
+	let fo = {
+		let mut file = udf::FileIO::create(&path, *b"TEST").unwrap();
+		let mut ds = udf::Dataset::new();
+		ds.names.add("Floats", udf::hash("Floats"));
+		ds.add_table(udf::TableRef {
+			key_name: udf::hash!("Floats"),
+			data: FLOATS.as_data_ref(),
+			..Default::default()
+		});
+		let fo = file.add_dataset(&ds.finalize()).unwrap();
+		file.set_root(fo);
+		file.write_header().unwrap();
+		fo
+	};
+
+	let file = unsafe { udf::MappedFile::open(&path) }.unwrap();
+	assert_eq!(file.id(), *b"TEST");
+	assert_eq!(file.root(), fo);
+
+	let ds = file.read_dataset(file.root()).unwrap();
+	let table = ds.find_table(udf::hash!("Floats")).unwrap();
+	let data = ds.get_data_ref(table).unwrap();
+	assert_eq!(data.as_slice::<f32>(), Some(&FLOATS[..]));
+
+	// Out of bounds and misaligned file offsets are rejected
+	assert!(file.read_dataset(udf::format::FileOffset { offset: fo.offset, size: fo.size + 0x10 }).is_err());
+	assert!(file.read_dataset(udf::format::FileOffset { offset: fo.offset + 8, size: fo.size }).is_err());
+	drop(file);
+
+	// Files too short for the header are rejected
+	std::fs::write(&path, [0u8; 0x20]).unwrap();
+	let err = unsafe { udf::MappedFile::open(&path) }.err().unwrap();
+	assert!(matches!(err, udf::Error::OutOfBounds { field: udf::Field::Header, end: 0x40, size: 0x20, .. }));
+
+	let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn memory_backend() {
+	let mut file = udf::FileIO::create_with(std::io::Cursor::new(Vec::new()), *b"MEM0").unwrap();
+	let mut ds = udf::Dataset::new();
+	ds.names.add("Floats", udf::hash("Floats"));
+	ds.add_table(udf::TableRef {
+		key_name: udf::hash!("Floats"),
+		data: FLOATS.as_data_ref(),
+		..Default::default()
+	});
+	let fo = file.add_dataset(&ds.finalize()).unwrap();
+	file.set_root(fo);
+	file.write_header().unwrap();
+
+	// Reopen the buffer as a read-only stream
+	let bytes = file.into_inner().into_inner();
+	assert_eq!(bytes.len() as u64, fo.offset + fo.size);
+	let mut file = udf::FileIO::open_with(udf::Stream(std::io::Cursor::new(&bytes[..]))).unwrap();
+	assert_eq!(file.id(), *b"MEM0");
+	let ds = file.read_dataset(file.root()).unwrap();
+	let ds = ds.as_ref();
+	let data = ds.get_data_ref(ds.find_table(udf::hash!("Floats")).unwrap()).unwrap();
+	assert_eq!(data.as_slice::<f32>(), Some(&FLOATS[..]));
+}
+
+#[test]
+fn checksums() {
+	let mut ds = udf::Dataset::new();
+	ds.checksums = true;
+	ds.names.add("Floats", udf::hash("Floats"));
+	ds.add_table(udf::TableRef {
+		key_name: udf::hash!("Floats"),
+		data: FLOATS.as_data_ref(),
+		..Default::default()
+	});
+	let ds = ds.finalize();
+
+	let mut file = udf::FileIO::create_with(std::io::Cursor::new(Vec::new()), [0; 4]).unwrap();
+	let fo = file.add_dataset(&ds).unwrap();
+	let mut bytes = file.into_inner().into_inner();
+
+	let mut file = udf::FileIO::open_with(std::io::Cursor::new(&bytes)).unwrap();
+	let ds = file.read_dataset(fo).unwrap();
+	let ds = ds.as_ref();
+	assert_ne!(ds.header.checksum, 0);
+	assert_ne!(ds.tables[0].checksum, 0);
+	assert!(ds.verify_checksums().is_ok());
+	let header_size = ds.header.size as usize;
+	let start = fo.offset as usize;
+	assert_eq!(ds.header.checksum, udf::crc32c(&bytes[start + 8..start + header_size]));
+
+	// The header checksum covers the raw bytes up to the header size
+	let mut storage = vec![0u64; fo.size as usize / 8];
+	dataview::bytes_mut(storage.as_mut_slice()).copy_from_slice(&bytes[start..start + fo.size as usize]);
+	dataview::bytes_mut(storage.as_mut_slice())[0xc] += 8;
+	let padded = udf::DatasetRef::parse_with(&storage, udf::ParseMode::Lenient).unwrap();
+	assert_eq!(padded.header_checksum(), udf::crc32c(&dataview::bytes(storage.as_slice())[8..header_size + 8]));
+	// The padding is not part of the strings
+	assert_eq!(padded.names.strings, ds.names.strings);
+	assert_eq!(padded.padding.len(), 8);
+	assert_eq!(padded.to_owned().names.strings, ds.names.strings);
+	let mut written = Vec::new();
+	padded.write(&mut written).unwrap();
+	assert_eq!(written.len(), padded.file_size());
+	assert_eq!(written, dataview::bytes(storage.as_slice())[..written.len()]);
+
+	// Flip a bit in the table data
+	bytes[fo.offset as usize + header_size] ^= 1;
+	let mut file = udf::FileIO::open_with(std::io::Cursor::new(&bytes)).unwrap();
+	let ds = file.read_dataset(fo).unwrap();
+	let ds = ds.as_ref();
+	assert!(ds.verify_checksum().is_ok());
+	let err = ds.get_data_ref_verified(&ds.tables[0]).err().unwrap();
+	assert!(matches!(err, udf::Error::ChecksumMismatch { field: udf::Field::TableData, key_name, .. } if key_name == udf::hash!("Floats")));
+
+	// Flip a bit in the table descriptor
+	bytes[fo.offset as usize + 0x18 + 0x10] ^= 1;
+	let mut file = udf::FileIO::open_with(std::io::Cursor::new(&bytes)).unwrap();
+	let ds = file.read_dataset(fo).unwrap();
+	assert_eq!(ds.as_ref().verify_checksum().unwrap_err().field(), Some(udf::Field::Checksum));
+}
+
+#[test]
+fn errors() {
+	let mut ds = udf::Dataset::new();
+	ds.names.add("Floats", udf::hash("Floats"));
+	ds.add_table(udf::TableRef {
+		key_name: udf::hash!("Floats"),
+		data: FLOATS.as_data_ref(),
+		..Default::default()
+	});
+	let mut file = udf::FileIO::create_with(std::io::Cursor::new(Vec::new()), [0; 4]).unwrap();
+	let fo = file.add_dataset(&ds.finalize()).unwrap();
+
+	// Misaligned file offset
+	let bad_fo = udf::format::FileOffset { offset: fo.offset + 8, size: fo.size };
+	match file.read_dataset(bad_fo) {
+		Err(udf::Error::InvalidField { field: udf::Field::FileOffset, file_offset, expected: udf::Expected::MultipleOf(16), actual }) => {
+			assert_eq!(file_offset, bad_fo);
+			assert_eq!(actual, bad_fo.offset);
+		},
+		_ => panic!("expected misaligned file offset"),
+	}
+
+	// File offset beyond the end of the file
+	let bad_fo = udf::format::FileOffset { offset: fo.offset, size: fo.size + 0x10 };
+	assert!(matches!(file.read_dataset(bad_fo), Err(udf::Error::OutOfBounds { field: udf::Field::FileOffset, .. })));
+
+	// Corrupt the number of descriptors
+	let mut bytes = file.into_inner().into_inner();
+	bytes[fo.offset as usize + 0xE] = 0xff;
+	let mut file = udf::FileIO::open_with(std::io::Cursor::new(&bytes)).unwrap();
+	assert_eq!(file.read_dataset(fo).err().unwrap().field(), Some(udf::Field::HeaderSize));
+	file.set_parse_mode(udf::ParseMode::Lenient);
+	let err = file.read_dataset(fo).err().unwrap();
+	assert_eq!(err.field(), Some(udf::Field::Descriptors));
+	assert_eq!(err.file_offset(), Some(fo));
+	assert_eq!(std::io::Error::from(err).kind(), std::io::ErrorKind::UnexpectedEof);
+
+	// Truncated file header
+	let err = udf::FileIO::open_with(std::io::Cursor::new(&bytes[..0x20])).err().unwrap();
+	assert!(matches!(err, udf::Error::OutOfBounds { field: udf::Field::Header, end: 0x40, size: 0x20, .. }));
+
+	// Corrupt the magic
+	bytes[3] = b'X';
+	let err = udf::FileIO::open_with(std::io::Cursor::new(&bytes)).err().unwrap();
+	assert!(matches!(err, udf::Error::InvalidField { field: udf::Field::Magic, .. }));
+	assert_eq!(err.to_string(), "0x0:0x40: invalid magic: expected 0x30464455 but found 0x58464455");
+
+	// Unsupported operations map to their io error kind
+	let err = udf::Error::Unsupported { file_offset: fo, message: "borrowing datasets from a memory mapped file" };
+	assert_eq!(err.file_offset(), Some(fo));
+	assert!(err.to_string().ends_with("borrowing datasets from a memory mapped file is not supported on this host"));
+	assert_eq!(std::io::Error::from(err).kind(), std::io::ErrorKind::Unsupported);
+}
+
+#[test]
+fn parse_mode() {
+	let mut ds = udf::Dataset::new();
+	ds.names.add("Floats", udf::hash("Floats"));
+	ds.add_table(udf::TableRef {
+		key_name: udf::hash!("Floats"),
+		data: FLOATS.as_data_ref(),
+		..Default::default()
+	});
+	let storage = {
+		ds.finalize();
+		let mut bytes = Vec::new();
+		ds.as_ref().write(&mut bytes).unwrap();
+		bytes.chunks(8).map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap())).collect::<Vec<u64>>()
+	};
+	assert!(udf::DatasetRef::parse(&storage).is_ok());
+
+	let field = |storage: &[u64]| udf::DatasetRef::parse(storage).err().and_then(|err| err.field());
+
+	// Check value
+	let mut bad = storage.clone();
+	bad[0] ^= 1;
+	assert_eq!(field(&bad), Some(udf::Field::Check));
+	assert!(udf::DatasetRef::parse_with(&bad, udf::ParseMode::Lenient).is_ok());
+
+	// Reserved field
+	let mut bad = storage.clone();
+	bad[2] |= 1 << 48;
+	assert_eq!(field(&bad), Some(udf::Field::Reserved));
+	assert!(udf::DatasetRef::parse_with(&bad, udf::ParseMode::Lenient).is_ok());
+
+	// Lookup entries inconsistent with the header size
+	let mut bad = storage.clone();
+	bad[2] -= 1;
+	assert_eq!(field(&bad), Some(udf::Field::HeaderSize));
+	assert!(udf::DatasetRef::parse_with(&bad, udf::ParseMode::Lenient).is_ok());
+
+	// Arbitrary bytes are rejected
+	let mut garbage = vec![0u64; 16];
+	garbage[1] = 0x18 << 32;
+	assert!(udf::DatasetRef::parse_with(&garbage, udf::ParseMode::Lenient).is_ok());
+	assert_eq!(field(&garbage), Some(udf::Field::Check));
+}
+
+#[test]
+fn validate_report() {
+	let mut file = udf::FileIO::create_with(std::io::Cursor::new(Vec::new()), [0; 4]).unwrap();
+	let mut ds = udf::Dataset::new();
+	ds.names.add("Floats", udf::hash("Floats"));
+	ds.add_table(udf::TableRef {
+		key_name: udf::hash!("Floats"),
+		data: FLOATS.as_data_ref(),
+		..Default::default()
+	});
+	// Forgot to add the name of this table
+	ds.add_table(udf::TableRef {
+		key_name: udf::hash!("Unnamed"),
+		data: FLOATS.as_data_ref(),
+		..Default::default()
+	});
+	let fo = file.add_dataset(&ds.finalize()).unwrap();
+	file.set_root(fo);
+
+	let report = udf::validate(&mut file);
+	assert_eq!(report.datasets, 1);
+	assert_eq!(report.errors(), 1);
+	let diag = &report.diagnostics[0];
+	assert_eq!(diag.rule, udf::Rule::InvalidName);
+	assert_eq!(diag.file_offset, fo);
+	assert_eq!(diag.table.as_deref(), Some("0x6d294645"));
+}
+
+#[test]
+fn compact() {
+	let mut file = udf::FileIO::create_with(std::io::Cursor::new(Vec::new()), *b"CMPT").unwrap();
+
+	let mut child = udf::Dataset::new();
+	child.names.add("Floats", udf::hash("Floats"));
+	child.add_table(udf::TableRef {
+		key_name: udf::hash!("Floats"),
+		data: FLOATS.as_data_ref(),
+		..Default::default()
+	});
+	let dead_fo = file.add_dataset(&child.finalize()).unwrap();
+	let child_fo = file.add_dataset(&child.finalize()).unwrap();
+
+	// The child dataset is referenced twice but only copied once
+	let children = [child_fo, udf::format::FileOffset::NULL, child_fo];
+	let mut root = udf::Dataset::new();
+	root.names.add("Children", udf::hash("Children"));
+	root.add_table(udf::TableRef {
+		key_name: udf::hash!("Children"),
+		data: children.as_data_ref(),
+		..Default::default()
+	});
+	root.checksums = true;
+	let dead_root = file.add_dataset(&root.finalize()).unwrap();
+	let root_fo = file.add_dataset(&root.finalize()).unwrap();
+	file.set_root(root_fo);
+	file.write_header().unwrap();
+
+	let mut dest = udf::FileIO::create_with(std::io::Cursor::new(Vec::new()), file.id()).unwrap();
+	let stats = udf::compact(&mut file, &mut dest).unwrap();
+	assert_eq!(stats.datasets, 2);
+	assert_eq!(stats.reclaimed(), dead_fo.size + dead_root.size);
+	assert_eq!(stats.new_size, dest.get_ref().get_ref().len() as u64);
+
+	// The compacted file is valid and the file offsets are remapped
+	let mut dest = udf::FileIO::open_with(std::io::Cursor::new(dest.into_inner().into_inner())).unwrap();
+	assert_eq!(dest.id(), *b"CMPT");
+	let report = udf::validate(&mut dest);
+	assert_eq!(report.errors(), 0, "{:#?}", report.diagnostics);
+	let ds = dest.read_dataset(dest.root()).unwrap();
+	let ds = ds.as_ref();
+	let data = ds.get_data_ref(ds.find_table(udf::hash!("Children")).unwrap()).unwrap();
+	let children = data.as_slice::<udf::format::FileOffset>().unwrap();
+	assert_eq!(children[0], children[2]);
+	assert!(children[1].is_null());
+	let ds = dest.read_dataset(children[0]).unwrap();
+	let ds = ds.as_ref();
+	let data = ds.get_data_ref(ds.find_table(udf::hash!("Floats")).unwrap()).unwrap();
+	assert_eq!(data.as_slice::<f32>(), Some(&FLOATS[..]));
+
+	// Cyclic references are rejected
+	let mut file = udf::FileIO::create_with(std::io::Cursor::new(Vec::new()), [0; 4]).unwrap();
+	let fo = file.add_dataset(&root.finalize()).unwrap();
+	root.storage[..2].copy_from_slice(&[fo.offset, fo.size]);
+	file.write_dataset(fo, &root.finalize()).unwrap();
+	file.set_root(fo);
+	let mut dest = udf::FileIO::create_with(std::io::Cursor::new(Vec::new()), [0; 4]).unwrap();
+	assert!(matches!(udf::compact(&mut file, &mut dest), Err(udf::Error::CyclicDataset { file_offset }) if file_offset == fo));
+}
+
+#[test]
+fn compact_table_offsets() {
+	let mut file = udf::FileIO::create_with(std::io::Cursor::new(Vec::new()), [0; 4]).unwrap();
+
+	let mut child = udf::Dataset::new();
+	child.insert("Floats", FLOATS.as_data_ref());
+	let _dead_fo = file.add_dataset(&child.finalize()).unwrap();
+	let child_fo = file.add_dataset(&child.finalize()).unwrap();
+
+	// The file offsets are stored at an odd memory offset
+	let mut root = udf::Dataset::new();
+	root.insert("Id", [42u64].as_data_ref());
+	root.insert("Children", [udf::format::FileOffset::NULL, child_fo].as_data_ref());
+	let root_fo = file.add_dataset(&root.finalize()).unwrap();
+	file.set_root(root_fo);
+	let ds = root.as_ref();
+	let table = ds.find_table(udf::hash!("Children")).unwrap();
+	assert_eq!((table.mem_start, table.mem_end), (1, 5));
+
+	let mut dest = udf::FileIO::create_with(std::io::Cursor::new(Vec::new()), [0; 4]).unwrap();
+	udf::compact(&mut file, &mut dest).unwrap();
+	let ds = dest.read_dataset(dest.root()).unwrap();
+	let ds = ds.as_ref();
+	assert_eq!(ds.get::<u64>("Id").unwrap()[..], [42]);
+	let children = ds.get::<udf::format::FileOffset>("Children").unwrap();
+	assert!(children[0].is_null());
+	let child = dest.read_dataset(children[1]).unwrap();
+	assert_eq!(child.as_ref().get::<f32>("Floats").unwrap()[..], FLOATS);
+
+	// Compressed file offsets cannot be remapped
+	let children = [child_fo];
+	let data = children.as_data_ref();
+	root.insert("Children", udf::DataRef { compress_info: udf::format::COMPRESS_SIMPLE_U64, ..data });
+	let root_fo = file.add_dataset(&root.finalize()).unwrap();
+	file.set_root(root_fo);
+	let mut dest = udf::FileIO::create_with(std::io::Cursor::new(Vec::new()), [0; 4]).unwrap();
+	assert!(matches!(udf::compact(&mut file, &mut dest), Err(udf::Error::InvalidTableData { file_offset, key_name }) if file_offset == root_fo && key_name == udf::hash!("Children")));
+}
+
+#[test]
+fn walk() {
+	let mut file = udf::FileIO::create_with(std::io::Cursor::new(Vec::new()), [0; 4]).unwrap();
+
+	let mut leaf = udf::Dataset::new();
+	leaf.insert("Floats", FLOATS.as_data_ref());
+	let leaf_fo = file.add_dataset(&leaf.finalize()).unwrap();
+
+	let mut mesh = udf::Dataset::new();
+	mesh.insert("Leaves", [leaf_fo, udf::format::FileOffset::NULL, leaf_fo].as_data_ref());
+	let mesh_fo = file.add_dataset(&mesh.finalize()).unwrap();
+
+	let mut root = udf::Dataset::new();
+	root.insert("Meshes", [mesh_fo].as_data_ref());
+	root.insert("Other", [leaf_fo].as_data_ref());
+	let root_fo = file.add_dataset(&root.finalize()).unwrap();
+
+	// Datasets are visited depth first, null file offsets are skipped
+	let mut visited = Vec::new();
+	file.walk(root_fo, |walk| {
+		visited.push((walk.path().to_string(), walk.depth, walk.ancestors().count()));
+		udf::WalkControl::Continue
+	}).unwrap();
+	assert_eq!(visited, [
+		(String::new(), 0, 1),
+		("Meshes[0]".to_string(), 1, 2),
+		("Meshes[0].Leaves[0]".to_string(), 2, 3),
+		("Meshes[0].Leaves[2]".to_string(), 2, 3),
+		("Other[0]".to_string(), 1, 2),
+	]);
+
+	// Depth limits and pruning skip the children
+	let mut count = 0;
+	file.walk_depth(root_fo, 1, |_| { count += 1; udf::WalkControl::Continue }).unwrap();
+	assert_eq!(count, 3);
+	let mut count = 0;
+	file.walk(root_fo, |walk| {
+		count += 1;
+		if walk.dataset.find_table(udf::hash!("Leaves")).is_some() { udf::WalkControl::Prune } else { udf::WalkControl::Continue }
+	}).unwrap();
+	assert_eq!(count, 3);
+	let mut count = 0;
+	file.walk(root_fo, |_| { count += 1; udf::WalkControl::Stop }).unwrap();
+	assert_eq!(count, 1);
+
+	// Cyclic references are rejected
+	mesh.storage[..2].copy_from_slice(&[root_fo.offset, root_fo.size]);
+	file.write_dataset(mesh_fo, &mesh.finalize()).unwrap();
+	let result = file.walk(root_fo, |_| udf::WalkControl::Continue);
+	assert!(matches!(result, Err(udf::Error::CyclicDataset { file_offset }) if file_offset == root_fo));
+}
+
+#[test]
+fn resolve() {
+	let mut file = udf::FileIO::create_with(std::io::Cursor::new(Vec::new()), [0; 4]).unwrap();
+
+	// The index name is only defined by the root dataset
+	let mut slice = udf::Dataset::new();
+	slice.insert("Height", 0.5f32.as_data_ref());
+	slice.add_table(udf::TableRef {
+		key_name: udf::hash!("Contours"),
+		data: [udf::format::RangeU32 { start: 0, end: 2 }].as_data_ref(),
+		index_name: udf::hash!("Points"),
+		..Default::default()
+	});
+	let slice_fo = file.add_dataset(&slice.finalize()).unwrap();
+
+	let mut root = udf::Dataset::new();
+	root.insert("Points", FLOATS.as_data_ref());
+	root.insert("Slices", [udf::format::FileOffset::NULL, slice_fo].as_data_ref());
+	let root_fo = file.add_dataset(&root.finalize()).unwrap();
+
+	let resolved = file.resolve(root_fo, "Slices[1].Contours").unwrap();
+	assert_eq!(resolved.datasets().map(|(fo, _)| fo).collect::<Vec<_>>(), [root_fo, slice_fo]);
+	fn is_send<T: Send>(_: &T) {}
+	is_send(&resolved);
+	assert_eq!(resolved.file_offset(), slice_fo);
+	assert_eq!(resolved.key_name(), udf::hash!("Contours"));
+	assert_eq!(resolved.data(&mut Vec::new()).unwrap().as_slice::<u32>(), Some(&[0, 2][..]));
+	let table = resolved.table().unwrap();
+	let (dataset, target) = resolved.index_table(table).unwrap();
+	assert_eq!(target.key_name, udf::hash!("Points"));
+	assert_eq!(dataset.get_data_ref(target).unwrap().as_slice::<f32>(), Some(&FLOATS[..]));
+	assert_eq!(resolved.lookup(udf::hash!("Points")), Ok("Points"));
+	let contours = resolved.get_indexed::<f32>().unwrap();
+	assert_eq!(contours.get(0), Some(&FLOATS[0..2]));
+	assert!(file.resolve(root_fo, "Slices[1].Height").unwrap().get_indexed::<f32>().is_err());
+
+	// Paths may refer to datasets
+	let resolved = file.resolve(root_fo, "Slices[1]").unwrap();
+	assert_eq!(resolved.file_offset(), slice_fo);
+	assert!(resolved.table().is_none());
+	assert_eq!(file.resolve(root_fo, "").unwrap().file_offset(), root_fo);
+
+	// Wildcards and ranges skip null datasets
+	let matches = file.resolve_all(root_fo, "Slices[*].Height").unwrap();
+	assert_eq!(matches.len(), 1);
+	assert_eq!(matches[0].path(), "Slices[1].Height");
+	assert!(file.resolve_all(root_fo, "Slices[0:1]").unwrap().is_empty());
+	assert!(file.resolve(root_fo, "Slices[0:1]").is_err());
+
+	// Rows of a table
+	let resolved = file.resolve(root_fo, "Points[1:3]").unwrap();
+	assert_eq!(resolved.rows(), Some(1..3));
+	assert_eq!(resolved.data(&mut Vec::new()).unwrap().as_slice::<f32>(), Some(&FLOATS[1..3]));
+	assert!(file.resolve(root_fo, "Points[3:5]").is_err());
+
+	// 2D dataset tables
+	let grid = [slice_fo, udf::format::FileOffset::NULL, slice_fo, slice_fo];
+	root.insert("Grid", udf::DataRef {
+		bytes: dataview::bytes(&grid[..]),
+		type_info: udf::format::TYPE_HINT_DATASET | udf::format::TYPE_DIM_2D | udf::format::TYPE_PRIM_U64,
+		compress_info: udf::format::COMPRESS_NONE,
+		shape: udf::Shape::D3(2, 2, 2),
+	});
+	let root_fo = file.add_dataset(&root.finalize()).unwrap();
+	let mut paths = |path| file.resolve_all(root_fo, path).map(|matches| matches.iter().map(|m| m.path().to_string()).collect::<Vec<_>>());
+	assert_eq!(paths("Grid[1,0]").unwrap(), ["Grid[1,0]"]);
+	assert_eq!(paths("Grid[*]").unwrap(), ["Grid[0,0]", "Grid[1,0]", "Grid[1,1]"]);
+	assert_eq!(paths("Grid[:,1].Height").unwrap(), ["Grid[1,1].Height"]);
+	assert!(paths("Grid[0,1]").is_err());
+	assert!(paths("Slices[0,1]").is_err());
+
+	// Walked paths of 2D dataset tables resolve to the walked datasets
+	let mut walked = Vec::new();
+	file.walk_depth(root_fo, 1, |walk| {
+		if walk.depth == 1 {
+			walked.push((walk.path().to_string(), walk.file_offset));
+		}
+		udf::WalkControl::Continue
+	}).unwrap();
+	let grid_paths: Vec<_> = walked.iter().map(|(path, _)| path.as_str()).filter(|path| path.starts_with("Grid")).collect();
+	assert_eq!(grid_paths, ["Grid[0,0]", "Grid[1,0]", "Grid[1,1]"]);
+	for (path, fo) in &walked {
+		assert_eq!(file.resolve(root_fo, path).unwrap().file_offset(), *fo);
+	}
+
+	// Scalar dataset tables have a single row
+	root.insert("Single", slice_fo.as_data_ref());
+	let root_fo = file.add_dataset(&root.finalize()).unwrap();
+	assert_eq!(file.resolve(root_fo, "Single[0].Height").unwrap().file_offset(), slice_fo);
+	assert!(file.resolve(root_fo, "Single[1]").is_err());
+
+	// Key names are found in the names lookup of the dataset and its parents
+	let mut custom = udf::Dataset::new();
+	custom.add_table(udf::TableRef { key_name: 0x100, data: 0.5f32.as_data_ref(), ..Default::default() });
+	custom.add_table(udf::TableRef { key_name: 0x200, data: 1.5f32.as_data_ref(), ..Default::default() });
+	custom.names.add("Width", 0x100);
+	let custom_fo = file.add_dataset(&custom.finalize()).unwrap();
+	root.add_table(udf::TableRef { key_name: 0x300, data: [custom_fo].as_data_ref(), ..Default::default() });
+	root.names.add("Custom", 0x300);
+	root.names.add("Depth", 0x200);
+	let root_fo = file.add_dataset(&root.finalize()).unwrap();
+	assert_eq!(file.resolve(root_fo, "Custom[0].Width").unwrap().key_name(), 0x100);
+	assert_eq!(file.resolve(root_fo, "Custom[0].Depth").unwrap().key_name(), 0x200);
+
+	// Errors
+	assert!(matches!(file.resolve(root_fo, "Slices[1].Width"), Err(udf::Error::TableNotFound { file_offset, .. }) if file_offset == slice_fo));
+	assert!(matches!(file.resolve(root_fo, "Points[0].Height"), Err(udf::Error::TypeMismatch { .. })));
+	assert!(matches!(file.resolve(root_fo, "Slices[0].Height"), Err(udf::Error::Custom { .. })));
+	assert!(matches!(file.resolve(root_fo, "Slices[2].Height"), Err(udf::Error::Custom { .. })));
+	assert!(matches!(file.resolve(root_fo, "Slices.Height"), Err(udf::Error::Custom { .. })));
+	assert!(matches!(file.resolve(root_fo, "Slices[x]"), Err(udf::Error::Custom { .. })));
+}
+
+#[test]
+fn path_syntax() {
+	use udf::{PathEl, Select};
+
+	let parse = |path: &'static str| {
+		let mut state = path;
+		let mut els = Vec::new();
+		while !state.is_empty() {
+			els.push(PathEl::parse(&mut state)?);
+		}
+		Ok::<_, udf::ParseError>(els)
+	};
+
+	assert_eq!(parse("Slices[3].Points").unwrap(), [
+		PathEl::Dir { name: "Slices", index: Select::Index(3), index2: None },
+		PathEl::Name("Points"),
+	]);
+	assert_eq!(parse("Points[100:200]").unwrap(), [PathEl::Dir { name: "Points", index: Select::Range(Some(100), Some(200)), index2: None }]);
+	assert_eq!(parse("Grid[:2,*]").unwrap(), [PathEl::Dir { name: "Grid", index: Select::Range(None, Some(2)), index2: Some(Select::All) }]);
+	assert_eq!(parse("\"a.b[c]\"[1].\"x\"").unwrap(), [
+		PathEl::Dir { name: "a.b[c]", index: Select::Index(1), index2: None },
+		PathEl::Name("x"),
+	]);
+	for path in ["a.", ".a", "a[", "a[1", "a[1]b", "a[x]", "a[1,2,3]", "\"a", "\"\""] {
+		assert!(parse(path).is_err(), "{:?}", path);
+	}
+
+	// Elements print as they are parsed
+	let el = PathEl::Dir { name: "a.b", index: Select::Range(Some(1), None), index2: Some(Select::All) };
+	assert_eq!(el.to_string(), "\"a.b\"[1:,*]");
+	assert_eq!(Select::Range(None, None).range(5), Some(0..5));
+	assert_eq!(Select::Index(5).range(5), None);
+}
+
+#[test]
+fn free_space() {
+	let mut file = udf::FileIO::create_with(std::io::Cursor::new(Vec::new()), [0; 4]).unwrap();
+	let floats = [1.0f32; 64];
+
+	let dataset = |data: &[f32]| {
+		let mut ds = udf::Dataset::new();
+		ds.names.add("Floats", udf::hash("Floats"));
+		ds.add_table(udf::TableRef {
+			key_name: udf::hash!("Floats"),
+			data: data.as_data_ref(),
+			..Default::default()
+		});
+		ds
+	};
+
+	let a = file.add_dataset(&dataset(&floats).finalize()).unwrap();
+	let b = file.add_dataset(&dataset(&floats).finalize()).unwrap();
+	file.set_root(b);
+	let end = b.offset + b.size;
+
+	// The file header cannot be released
+	let header = udf::format::FileOffset { offset: 0x30, size: 0x20 };
+	assert!(matches!(file.free(header), Err(udf::Error::OutOfBounds { field: udf::Field::FileOffset, file_offset, .. }) if file_offset == header));
+	assert!(file.free_list().is_empty());
+
+	// Released regions are reused
+	file.free(a).unwrap();
+	assert_eq!(file.free_list(), &[a]);
+	let c = file.add_dataset(&dataset(&FLOATS).finalize()).unwrap();
+	assert_eq!(c.offset, a.offset);
+	assert!(c.size < a.size);
+	assert_eq!(file.free_list(), &[udf::format::FileOffset { offset: a.offset + c.size, size: a.size - c.size }]);
+
+	// Smaller datasets are replaced in place and the tail is released
+	let root = file.replace_dataset(b, &dataset(&FLOATS).finalize()).unwrap();
+	assert_eq!(root, udf::format::FileOffset { offset: b.offset, size: c.size });
+	assert_eq!(file.root(), root);
+	assert_eq!(file.free_list().len(), 2);
+
+	// Larger datasets are moved into the merged released regions
+	let root = file.replace_dataset(root, &dataset(&floats).finalize()).unwrap();
+	assert_eq!(root, udf::format::FileOffset { offset: c.offset + c.size, size: b.size });
+	assert_eq!(file.free_list(), &[udf::format::FileOffset { offset: root.offset + root.size, size: a.size - c.size }]);
+	assert_eq!(file.get_mut().get_ref().len() as u64, end);
+
+	let ds = file.read_dataset(file.root()).unwrap();
+	let ds = ds.as_ref();
+	let data = ds.get_data_ref(ds.find_table(udf::hash!("Floats")).unwrap()).unwrap();
+	assert_eq!(data.as_slice::<f32>(), Some(&floats[..]));
+	let ds = file.read_dataset(c).unwrap();
+	let ds = ds.as_ref();
+	let data = ds.get_data_ref(ds.find_table(udf::hash!("Floats")).unwrap()).unwrap();
+	assert_eq!(data.as_slice::<f32>(), Some(&FLOATS[..]));
+
+	// Zero sizes are rounded up, released regions never hand out the same offset twice
+	let free = file.free_list()[0];
+	let x = file.allocate(0);
+	let y = file.allocate(0);
+	assert_eq!(x, udf::format::FileOffset { offset: free.offset, size: 16 });
+	assert_eq!(y, udf::format::FileOffset { offset: free.offset + 16, size: 16 });
+}
+
+#[test]
+fn commit() {
+	let mut file = udf::FileIO::create_with(std::io::Cursor::new(Vec::new()), [0; 4]).unwrap();
+	let mut ds = udf::Dataset::new();
+	ds.names.add("Floats", udf::hash("Floats"));
+	ds.add_table(udf::TableRef {
+		key_name: udf::hash!("Floats"),
+		data: FLOATS.as_data_ref(),
+		..Default::default()
+	});
+
+	let a = file.add_dataset(&ds.finalize()).unwrap();
+	file.commit(a).unwrap();
+	let first = file.header().next;
+	let header_a = file.get_ref().get_ref()[..0x40].to_vec();
+
+	let b = file.add_dataset(&ds.finalize()).unwrap();
+	file.commit(b).unwrap();
+	let record = file.read_commit(file.header().next).unwrap();
+	assert_eq!((record.seq, record.root, record.prev), (2, b, first));
+	let bytes = file.into_inner().into_inner();
+
+	let open = |bytes: &[u8]| udf::FileIO::open_with(std::io::Cursor::new(bytes.to_vec())).unwrap().root();
+	assert_eq!(open(&bytes), b);
+
+	// Crashed before the file header was updated
+	let mut crashed = bytes.clone();
+	crashed[..0x40].copy_from_slice(&header_a);
+	assert_eq!(open(&crashed), b);
+
+	// Crashed before the commit record was written
+	let len = crashed.len();
+	crashed[len - 8] ^= 1;
+	assert_eq!(open(&crashed), a);
+
+	// Torn file header with the root not updated
+	let mut torn = bytes.clone();
+	torn[0x10..0x20].copy_from_slice(&header_a[0x10..0x20]);
+	assert_eq!(open(&torn), b);
+
+	// Datasets written after the last commit
+	let mut file = udf::FileIO::open_with(std::io::Cursor::new(bytes)).unwrap();
+	file.add_dataset(&ds.finalize()).unwrap();
+	assert_eq!(open(file.get_ref().get_ref()), b);
+
+	// Setting the root without a commit unlinks the commit records
+	file.set_root(a);
+	file.write_header().unwrap();
+	assert_eq!(file.header().next, 0);
+	assert_eq!(open(file.get_ref().get_ref()), a);
+}
@@ -0,0 +1,76 @@
+use udf::{format, AsDataRef};
+
+#[test]
+fn indexing() {
+	let cube: Vec<[[u16; 4]; 3]> = (0..2).map(|i| std::array::from_fn(|j| std::array::from_fn(|k| (i * 100 + j * 10 + k) as u16))).collect();
+	let data = cube.as_data_ref();
+	let view = data.view::<u16>().unwrap();
+	assert_eq!(view.axes(), &[2, 3, 4]);
+	assert_eq!(view.shape(), udf::Shape::D3(2, 3, 4));
+	assert_eq!(view.len(), 24);
+	assert_eq!(view[[1, 2, 3]], 123);
+	assert_eq!(view.get(&[1, 3, 0]), None);
+	assert_eq!(view.get(&[1, 2]), None);
+	assert_eq!(view.as_slice().map(|values| values.len()), Some(24));
+
+	// Sub-views along any axis
+	let plane = view.index_axis(1, 2).unwrap();
+	assert_eq!(plane.axes(), &[2, 4]);
+	assert_eq!(plane.to_vec(), [20, 21, 22, 23, 120, 121, 122, 123]);
+	assert!(plane.as_slice().is_none());
+	let column = plane.index_axis(1, 1).unwrap();
+	assert_eq!(column.to_vec(), [21, 121]);
+	assert_eq!(view.index_axis(0, 1).unwrap().as_slice().unwrap()[0], 100);
+	assert!(view.index_axis(3, 0).is_none());
+	assert!(view.index_axis(0, 2).is_none());
+
+	let slice = view.slice_axis(2, 1..3).unwrap();
+	assert_eq!(slice.axes(), &[2, 3, 2]);
+	assert_eq!(slice[[1, 0, 1]], 102);
+	assert_eq!(slice.rows().map(|row| row.len()).collect::<Vec<_>>(), [6, 6]);
+	assert_eq!(slice.columns().map(|column| column[[0, 0]]).collect::<Vec<_>>(), [1, 11, 21]);
+	assert!(view.slice_axis(2, 3..5).is_none());
+	assert!(slice.slice_axis(0, 1..1).unwrap().is_empty());
+
+	// Scalars have no axes
+	let scalar = 5u32.as_data_ref();
+	let view = scalar.view::<u32>().unwrap();
+	assert_eq!((view.rank(), view[[]]), (0, 5));
+
+	// Mismatched element sizes and compressed data have no view
+	assert!(data.view::<u32>().is_none());
+	assert!(udf::DataRef { compress_info: format::COMPRESS_SIMPLE_U32, ..data }.view::<u16>().is_none());
+}
+
+#[test]
+fn ghost_dimensions() {
+	let points = [format::Coord2F32 { x: 1.0, y: 2.0 }, format::Coord2F32 { x: 3.0, y: 4.0 }, format::Coord2F32 { x: 5.0, y: 6.0 }];
+	let data = points.as_data_ref();
+	let view = data.view::<f32>().unwrap();
+	assert_eq!(view.axes(), &[3, 2]);
+	assert_eq!(view.logical_axes(), &[3]);
+	assert_eq!(view.ghost_axes(), &[2]);
+
+	// Every logical element is a view of its ghost dimensions
+	let elements: Vec<Vec<f32>> = view.elements().map(|element| element.to_vec()).collect();
+	assert_eq!(elements, [[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]]);
+	assert!(view.elements().all(|element| element.logical_axes().is_empty() && element.ghost_axes() == [2]));
+
+	// Removing an axis removes a logical or ghost dimension
+	let xs = view.index_axis(1, 0).unwrap();
+	assert_eq!((xs.logical_axes(), xs.ghost_axes()), (&[3][..], &[][..]));
+	assert_eq!(xs.to_vec(), [1.0, 3.0, 5.0]);
+	let point = view.index_axis(0, 1).unwrap();
+	assert_eq!((point.logical_axes(), point.ghost_axes()), (&[][..], &[2][..]));
+
+	// Transforms have two ghost dimensions
+	let transforms = [[[1.0f32, 0.0, 5.0], [0.0, 1.0, 6.0]]; 2];
+	let data = transforms.as_data_ref().with_type_hint(format::TYPE_HINT_TRANSFORM);
+	let view = data.view::<f32>().unwrap();
+	assert_eq!((view.logical_axes(), view.ghost_axes()), (&[2][..], &[2, 3][..]));
+	assert_eq!(view.elements().map(|element| element[[1, 2]]).collect::<Vec<_>>(), [6.0, 6.0]);
+
+	// Sub-views can be printed
+	let pa = udf::PrintArray::from_view(&view.index_axis(0, 0).unwrap()).unwrap();
+	assert_eq!(pa.to_string(), "[[1, 0, 5],\n [0, 1, 6]]");
+}
@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum Kind {
+	Outer,
+	Inner,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Contour {
+	kind: Kind,
+	points: Vec<[f32; 2]>,
+	closed: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Slice {
+	height: f32,
+	contours: Vec<Contour>,
+	labels: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Job {
+	name: String,
+	version: u32,
+	marker: char,
+	slices: Vec<Slice>,
+	settings: Option<Settings>,
+	comment: Option<String>,
+	transforms: Vec<[[f64; 3]; 2]>,
+	data: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Settings {
+	thickness: f64,
+	offsets: [i16; 3],
+}
+
+fn job() -> Job {
+	let contour = |kind, n| Contour {
+		kind,
+		points: (0..n).map(|i| [i as f32, -(i as f32)]).collect(),
+		closed: n % 2 == 0,
+	};
+	Job {
+		name: String::from("Example"),
+		version: 3,
+		marker: 'é',
+		slices: vec![
+			Slice { height: 0.1, contours: vec![contour(Kind::Outer, 4), contour(Kind::Inner, 3)], labels: vec!["a".into(), "bc".into()] },
+			Slice { height: 0.2, contours: vec![], labels: vec![] },
+		],
+		settings: Some(Settings { thickness: 0.05, offsets: [1, -2, 3] }),
+		comment: None,
+		transforms: vec![[[1.0, 0.0, 0.5], [0.0, 1.0, -0.5]]],
+		data: vec![1, 2, 3],
+	}
+}
+
+#[test]
+fn roundtrip() {
+	let mut file = udf::FileIO::create_with(std::io::Cursor::new(Vec::new()), *b"JOB0").unwrap();
+	let job = job();
+	let fo = udf::to_file(&mut file, &job).unwrap();
+	file.commit(fo).unwrap();
+
+	assert_eq!(udf::validate(&mut file).errors(), 0);
+
+	let bytes = file.into_inner().into_inner();
+	let mut file = udf::FileIO::open_with(std::io::Cursor::new(&bytes)).unwrap();
+	let root = file.root();
+	assert_eq!(udf::from_file::<Job, _>(&mut file, root).unwrap(), job);
+
+	// The tables are regular datatables
+	let ds = file.read_dataset(root).unwrap();
+	let ds = ds.as_ref();
+	assert_eq!(ds.get_text("name").unwrap(), "Example");
+	assert_eq!(ds.get::<udf::format::FileOffset>("slices").unwrap().len(), 2);
+	assert!(ds.find_table(udf::hash!("comment")).is_none());
+	let transform = ds.find_table(udf::hash!("transforms")).unwrap();
+	assert_eq!(transform.type_info & udf::format::TYPE_DIM_MASK, udf::format::TYPE_DIM_3D);
+}
+
+#[test]
+fn errors() {
+	let mut file = udf::FileIO::create_with(std::io::Cursor::new(Vec::new()), *b"JOB0").unwrap();
+
+	// Only structs can be serialized as datasets
+	assert!(matches!(udf::to_file(&mut file, &[1, 2, 3]), Err(udf::Error::Custom { .. })));
+	let map = std::collections::HashMap::from([("a", 1)]);
+	assert!(matches!(udf::to_file(&mut file, &map), Err(udf::Error::Custom { .. })));
+
+	// Ragged arrays are rejected with the key name of the field
+	#[derive(Serialize)]
+	struct Ragged {
+		rows: Vec<Vec<u32>>,
+	}
+	let err = udf::to_file(&mut file, &Ragged { rows: vec![vec![1, 2], vec![3]] }).err().unwrap();
+	assert!(matches!(err, udf::Error::Custom { key_name, .. } if key_name == udf::hash!("rows")));
+
+	// Missing items are only supported in sequences of structs
+	#[derive(Serialize)]
+	struct Optional {
+		values: Vec<Option<f32>>,
+	}
+	let err = udf::to_file(&mut file, &Optional { values: vec![None, Some(1.0)] }).err().unwrap();
+	assert!(matches!(&err, udf::Error::Custom { key_name, message, .. } if *key_name == udf::hash!("values") && message.contains("missing items")));
+
+	// Missing and mismatched tables
+	let fo = udf::to_file(&mut file, &Settings { thickness: 1.0, offsets: [0; 3] }).unwrap();
+	#[derive(Deserialize, Debug)]
+	struct Missing {
+		#[allow(dead_code)]
+		other: u32,
+	}
+	let err = udf::from_file::<Missing, _>(&mut file, fo).err().unwrap();
+	assert!(matches!(err, udf::Error::TableNotFound { key_name, file_offset } if key_name == udf::hash!("other") && file_offset == fo));
+	#[derive(Deserialize, Debug)]
+	struct Mismatch {
+		#[allow(dead_code)]
+		thickness: String,
+	}
+	let err = udf::from_file::<Mismatch, _>(&mut file, fo).err().unwrap();
+	assert!(matches!(err, udf::Error::Custom { key_name, .. } if key_name == udf::hash!("thickness")));
+}
@@ -0,0 +1,308 @@
+use std::io::Cursor;
+use udf::{format, AsDataRef, Rule};
+
+static FLOATS: [f32; 4] = [0.0, 1.0, 2.5, -4.0];
+
+fn dataset(tables: &[(&str, udf::DataRef, &str)]) -> udf::Dataset {
+	let mut ds = udf::Dataset::new();
+	for &(name, data, index_name) in tables {
+		let table = ds.insert(name, data);
+		if !index_name.is_empty() {
+			table.index(index_name);
+		}
+	}
+	return ds;
+}
+
+// Writes the dataset as the root, lets the caller corrupt the bytes and validates the result
+fn validate_with(ds: &mut udf::Dataset, patch: impl FnOnce(&mut Vec<u8>, usize)) -> udf::Report {
+	let mut file = udf::FileIO::create_with(Cursor::new(Vec::new()), [0; 4]).unwrap();
+	let fo = file.add_dataset(&ds.finalize()).unwrap();
+	file.set_root(fo);
+	file.write_header().unwrap();
+	let mut bytes = file.into_inner().into_inner();
+	patch(&mut bytes, fo.offset as usize);
+	let mut file = udf::FileIO::open_with(Cursor::new(bytes)).unwrap();
+	udf::validate(&mut file)
+}
+
+fn validate(ds: &mut udf::Dataset) -> udf::Report {
+	validate_with(ds, |_, _| ())
+}
+
+#[track_caller]
+fn assert_rule(report: &udf::Report, rule: Rule) {
+	assert!(report.diagnostics.iter().any(|diag| diag.rule == rule), "expected {} in {:#?}", rule, report.diagnostics);
+}
+
+#[test]
+fn valid() {
+	let coords = [format::Coord2F32 { x: 1.0, y: 2.0 }; 3];
+	let transforms = [format::Transform2F32::default(); 2];
+	let indices = [format::IndexU32(0), format::IndexU32(3)];
+	let ranges = [format::RangeU32 { start: 0, end: 4 }, format::RangeU32 { start: 2, end: 2 }];
+	let mut buffer = Vec::new();
+	let strings = udf::build_string_array_utf8(["a", "bcd", "ef"], &mut buffer);
+
+	// Child dataset indexes into the parent dataset
+	let mut child = dataset(&[("Indices", indices.as_data_ref(), "Floats")]);
+	let mut file = udf::FileIO::create_with(Cursor::new(Vec::new()), *b"TST\0").unwrap();
+	let child_fo = file.add_dataset(&child.finalize()).unwrap();
+	let children = [child_fo];
+
+	let mut ds = dataset(&[
+		("Floats", FLOATS.as_data_ref(), ""),
+		("Text", "Hello world".as_data_ref(), ""),
+		("Strings", strings, ""),
+		("Coords", coords.as_data_ref(), ""),
+		("Transforms", transforms.as_data_ref(), ""),
+		("Ranges", ranges.as_data_ref(), "Floats"),
+		("Children", children.as_data_ref(), ""),
+	]);
+	ds.checksums = true;
+	let fo = file.add_dataset(&ds.finalize()).unwrap();
+	file.set_root(fo);
+
+	let report = udf::validate(&mut file);
+	assert_eq!(report.datasets, 2);
+	assert!(report.diagnostics.is_empty(), "{:#?}", report.diagnostics);
+}
+
+#[test]
+fn reserved_field() {
+	let mut ds = dataset(&[("Floats", FLOATS.as_data_ref(), "")]);
+	ds.descs[0].type_info |= 0x0040;
+	assert_rule(&validate(&mut ds), Rule::ReservedField);
+
+	let mut ds = dataset(&[("Floats", FLOATS.as_data_ref(), "")]);
+	ds.header.reserved = [1];
+	assert_rule(&validate(&mut ds), Rule::ReservedField);
+
+	let mut ds = dataset(&[("Floats", FLOATS.as_data_ref(), "")]);
+	let report = validate_with(&mut ds, |bytes, _| bytes[0x20] = 1);
+	assert_rule(&report, Rule::ReservedField);
+	assert_eq!(report.errors(), 1);
+}
+
+#[test]
+fn invalid_id() {
+	let mut ds = dataset(&[("Floats", FLOATS.as_data_ref(), "")]);
+	ds.header.id = *b"A\0B\0";
+	assert_rule(&validate(&mut ds), Rule::InvalidId);
+
+	let mut ds = dataset(&[("Floats", FLOATS.as_data_ref(), "")]);
+	ds.header.id = [0x7f, 0, 0, 0];
+	assert_rule(&validate(&mut ds), Rule::InvalidId);
+}
+
+#[test]
+fn invalid_file_offset() {
+	let mut file = udf::FileIO::create_with(Cursor::new(Vec::new()), [0; 4]).unwrap();
+	file.set_root(format::FileOffset { offset: 0, size: 0x10 });
+	assert_rule(&udf::validate(&mut file), Rule::InvalidFileOffset);
+}
+
+#[test]
+fn dataset_tables() {
+	let mut file = udf::FileIO::create_with(Cursor::new(Vec::new()), [0; 4]).unwrap();
+	let mut ds = dataset(&[("Children", [format::FileOffset::NULL].as_data_ref(), "")]);
+	let fo = file.add_dataset(&ds.finalize()).unwrap();
+	file.set_root(fo);
+	assert_rule(&udf::validate(&mut file), Rule::NullDataset);
+
+	// The dataset refers to itself
+	ds.insert("Children", [fo].as_data_ref());
+	file.write_dataset(fo, &ds.finalize()).unwrap();
+	let report = udf::validate(&mut file);
+	assert_rule(&report, Rule::CyclicDataset);
+	assert_eq!(report.datasets, 1);
+
+	// Compressed file offsets are not walked
+	ds.descs[0].compress_info = format::COMPRESS_SIMPLE_U64;
+	file.write_dataset(fo, &ds.finalize()).unwrap();
+	let report = udf::validate(&mut file);
+	assert_rule(&report, Rule::InvalidFileOffset);
+	assert_eq!(report.errors(), 1, "{:#?}", report.diagnostics);
+}
+
+#[test]
+fn check_value() {
+	let mut ds = dataset(&[("Floats", FLOATS.as_data_ref(), "")]);
+	let report = validate_with(&mut ds, |bytes, offset| bytes[offset] ^= 1);
+	assert_rule(&report, Rule::CheckValue);
+}
+
+#[test]
+fn header_size() {
+	let mut ds = dataset(&[("Floats", FLOATS.as_data_ref(), "")]);
+	// Shrink the string_len field
+	let report = validate_with(&mut ds, |bytes, offset| bytes[offset + 0x12] -= 1);
+	assert_rule(&report, Rule::HeaderSize);
+}
+
+#[test]
+fn unsorted_tables() {
+	let mut ds = dataset(&[("A", FLOATS.as_data_ref(), ""), ("B", FLOATS.as_data_ref(), "")]);
+	// Swap the two descriptors following the header
+	let report = validate_with(&mut ds, |bytes, offset| {
+		let descs = offset + 0x18;
+		let (a, b) = bytes[descs..descs + 0x60].split_at_mut(0x30);
+		a.swap_with_slice(b);
+	});
+	assert_rule(&report, Rule::UnsortedTables);
+}
+
+#[test]
+fn invalid_lookup() {
+	let mut ds = dataset(&[("A", FLOATS.as_data_ref(), ""), ("B", FLOATS.as_data_ref(), "")]);
+	// Swap the two lookup entries following the descriptors
+	let report = validate_with(&mut ds, |bytes, offset| {
+		let entries = offset + 0x18 + 0x30 * 2;
+		let (a, b) = bytes[entries..entries + 16].split_at_mut(8);
+		a.swap_with_slice(b);
+	});
+	assert_rule(&report, Rule::InvalidLookup);
+
+	let mut ds = dataset(&[("Floats", FLOATS.as_data_ref(), "")]);
+	ds.names.entries[0].len = 100;
+	assert_rule(&validate(&mut ds), Rule::InvalidLookup);
+}
+
+#[test]
+fn unknown_compression() {
+	let mut ds = dataset(&[("Floats", FLOATS.as_data_ref(), "")]);
+	ds.descs[0].compress_info = 99;
+	let report = validate(&mut ds);
+	assert_rule(&report, Rule::UnknownCompression);
+	assert_eq!(report.errors(), 0);
+}
+
+#[test]
+fn unknown_hint() {
+	let mut ds = dataset(&[("Floats", FLOATS.as_data_ref(), "")]);
+	ds.descs[0].type_info |= 10 << 8;
+	assert_rule(&validate(&mut ds), Rule::UnknownHint);
+
+	// Custom type hints are allowed
+	let mut ds = dataset(&[("Floats", FLOATS.as_data_ref(), "")]);
+	ds.descs[0].type_info |= 32 << 8;
+	assert!(validate(&mut ds).diagnostics.is_empty());
+}
+
+#[test]
+fn hint_primitive() {
+	let mut ds = dataset(&[("Floats", FLOATS.as_data_ref(), "")]);
+	ds.descs[0].type_info |= format::TYPE_HINT_TEXT;
+	assert_rule(&validate(&mut ds), Rule::HintPrimitive);
+
+	let indices: [i32; 2] = [0, 1];
+	let mut ds = dataset(&[("Floats", FLOATS.as_data_ref(), ""), ("Indices", indices.as_data_ref(), "Floats")]);
+	ds.descs[1].type_info |= format::TYPE_HINT_INDEX;
+	assert_rule(&validate(&mut ds), Rule::HintPrimitive);
+}
+
+#[test]
+fn ghost_dimensions() {
+	// Coordinates without ghost dimension
+	let mut ds = dataset(&[("Coords", FLOATS.as_data_ref(), "")]);
+	ds.descs[0].type_info |= format::TYPE_HINT_COORD;
+	assert_rule(&validate(&mut ds), Rule::GhostDimensions);
+
+	// Ghost dimension without type hint
+	let coords = [format::Coord2F32 { x: 1.0, y: 2.0 }; 2];
+	let mut ds = dataset(&[("Coords", coords.as_data_ref(), "")]);
+	ds.descs[0].type_info &= !format::TYPE_HINT_MASK;
+	assert_rule(&validate(&mut ds), Rule::GhostDimensions);
+
+	// Colors must have 3 or 4 channels
+	let colors: [[u8; 2]; 2] = [[0, 0]; 2];
+	let mut ds = dataset(&[("Colors", colors.as_data_ref(), "")]);
+	ds.descs[0].type_info = format::TYPE_HINT_RGB | format::TYPE_DIM_1D | format::TYPE_PRIM_U8;
+	assert_rule(&validate(&mut ds), Rule::GhostDimensions);
+}
+
+#[test]
+fn memory_range() {
+	let mut ds = dataset(&[("Floats", FLOATS.as_data_ref(), "")]);
+	ds.descs[0].mem_end = 0;
+	ds.descs[0].mem_start = 1;
+	assert_rule(&validate(&mut ds), Rule::MemoryRange);
+
+	let mut ds = dataset(&[("Floats", FLOATS.as_data_ref(), "")]);
+	ds.descs[0].data_size += 8;
+	assert_rule(&validate(&mut ds), Rule::MemoryRange);
+
+	let mut ds = dataset(&[("Floats", FLOATS.as_data_ref(), "")]);
+	ds.descs[0].mem_end += 8;
+	assert_rule(&validate(&mut ds), Rule::MemoryRange);
+}
+
+#[test]
+fn invalid_index() {
+	// Index tables require an index name
+	let indices = [format::IndexU32(0)];
+	let mut ds = dataset(&[("Indices", indices.as_data_ref(), "")]);
+	assert_rule(&validate(&mut ds), Rule::InvalidIndex);
+
+	// Index name is only allowed on index tables
+	let mut ds = dataset(&[("A", FLOATS.as_data_ref(), ""), ("B", FLOATS.as_data_ref(), "A")]);
+	assert_rule(&validate(&mut ds), Rule::InvalidIndex);
+
+	// Target table must be 1D
+	let mut ds = dataset(&[("Scalar", 1.0f32.as_data_ref(), ""), ("Indices", indices.as_data_ref(), "Scalar")]);
+	assert_rule(&validate(&mut ds), Rule::InvalidIndex);
+}
+
+#[test]
+fn index_bounds() {
+	let indices = [format::IndexU32(0), format::IndexU32(4)];
+	let mut ds = dataset(&[("Floats", FLOATS.as_data_ref(), ""), ("Indices", indices.as_data_ref(), "Floats")]);
+	assert_rule(&validate(&mut ds), Rule::IndexBounds);
+
+	let ranges = [format::RangeU32 { start: 2, end: 1 }];
+	let mut ds = dataset(&[("Floats", FLOATS.as_data_ref(), ""), ("Ranges", ranges.as_data_ref(), "Floats")]);
+	assert_rule(&validate(&mut ds), Rule::IndexBounds);
+
+	let ranges = [format::RangeU32 { start: 2, end: 5 }];
+	let mut ds = dataset(&[("Floats", FLOATS.as_data_ref(), ""), ("Ranges", ranges.as_data_ref(), "Floats")]);
+	assert_rule(&validate(&mut ds), Rule::IndexBounds);
+}
+
+#[test]
+fn invalid_text() {
+	let bytes: &[u8] = &[b'a', 0xff, b'b'];
+	let mut ds = dataset(&[("Text", bytes.as_data_ref(), "")]);
+	ds.descs[0].type_info = format::TYPE_HINT_TEXT | format::TYPE_DIM_SCALAR | format::TYPE_PRIM_U8;
+	assert_rule(&validate(&mut ds), Rule::InvalidText);
+
+	let chars: &[u32] = &[0x61, 0xd800];
+	let mut ds = dataset(&[("Text", chars.as_data_ref(), "")]);
+	ds.descs[0].type_info = format::TYPE_HINT_TEXT | format::TYPE_DIM_SCALAR | format::TYPE_PRIM_U32;
+	assert_rule(&validate(&mut ds), Rule::InvalidText);
+}
+
+#[test]
+fn invalid_json() {
+	let bytes: &[u8] = &[b'[', 0xff, b']'];
+	let mut ds = dataset(&[("Json", bytes.as_data_ref(), "")]);
+	ds.descs[0].type_info = format::TYPE_HINT_JSON | format::TYPE_DIM_SCALAR | format::TYPE_PRIM_CUSTOM;
+	ds.descs[0].data_shape = [0, 0];
+	assert_rule(&validate(&mut ds), Rule::InvalidJson);
+}
+
+#[test]
+fn invalid_commit() {
+	let mut ds = dataset(&[("Floats", FLOATS.as_data_ref(), "")]);
+	let mut file = udf::FileIO::create_with(Cursor::new(Vec::new()), [0; 4]).unwrap();
+	let fo = file.add_dataset(&ds.finalize()).unwrap();
+	file.commit(fo).unwrap();
+	assert!(udf::validate(&mut file).diagnostics.is_empty());
+
+	// Corrupt the commit record
+	let next = file.header().next as usize;
+	let mut bytes = file.into_inner().into_inner();
+	bytes[next + 0x10] ^= 1;
+	let mut file = udf::FileIO::open_with(Cursor::new(bytes)).unwrap();
+	assert_eq!(file.root(), fo);
+	assert_rule(&udf::validate(&mut file), Rule::InvalidCommit);
+}